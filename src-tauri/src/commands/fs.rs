@@ -1,5 +1,11 @@
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::State;
+
+use crate::managers::SettingsManager;
+use crate::models::DEFAULT_FILE_TREE_EXCLUDE;
+use crate::utils::{glob_match, is_within_dir};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileEntry {
@@ -30,12 +36,32 @@ pub struct MoveOperation {
     pub destination_path: String, // folder path
 }
 
+/// Whether a file tree entry matches one of the `file_tree_exclude` patterns
+fn is_excluded(patterns: &[String], file_name: &str, rel_path: &str) -> bool {
+    let rel_path = rel_path.replace('\\', "/");
+    patterns.iter().any(|pattern| {
+        let pattern = pattern.trim().trim_end_matches('/');
+        if pattern.contains('/') {
+            glob_match(pattern.trim_start_matches('/'), &rel_path)
+        } else {
+            glob_match(pattern, file_name)
+        }
+    })
+}
+
 #[tauri::command]
 pub async fn list_directory(
+    settings_manager: State<'_, Arc<SettingsManager>>,
     project_path: String,
     relative_path: Option<String>,
 ) -> Result<Vec<FileEntry>, String> {
     let project = PathBuf::from(&project_path);
+    let exclude_patterns = settings_manager
+        .get_effective_settings(&project)
+        .settings
+        .general
+        .and_then(|g| g.file_tree_exclude)
+        .unwrap_or_else(|| DEFAULT_FILE_TREE_EXCLUDE.iter().map(|p| p.to_string()).collect());
     let target_dir = match &relative_path {
         Some(rel) => project.join(rel),
         None => project.clone(),
@@ -60,7 +86,18 @@ pub async fn list_directory(
                 Err(_) => continue,
             };
 
-            let file_name = entry.file_name().to_string_lossy().to_string();
+            // Paths travel to the frontend and back as strings, so a non-UTF-8
+            // name could never be opened again; leave such entries out
+            let file_name = match entry.file_name().into_string() {
+                Ok(name) => name,
+                Err(raw) => {
+                    eprintln!(
+                        "[FS] Skipping entry with non-UTF-8 name: {}",
+                        raw.to_string_lossy()
+                    );
+                    continue;
+                }
+            };
 
             // Skip hidden files/directories (starting with ".")
             if file_name.starts_with('.') {
@@ -81,6 +118,10 @@ pub async fn list_directory(
                 }
             }
 
+            if is_excluded(&exclude_patterns, &file_name, &rel_path) {
+                continue;
+            }
+
             let metadata = match entry.metadata() {
                 Ok(m) => m,
                 Err(_) => continue,
@@ -321,6 +362,11 @@ pub async fn rename_item(operation: RenameOperation) -> Result<String, String> {
         return Err("Invalid path: outside project directory".to_string());
     }
 
+    // Check if trying to rename directory into itself
+    if old_full_path.is_dir() && is_within_dir(&new_full_path, &old_full_path) {
+        return Err("Cannot rename directory into itself".to_string());
+    }
+
     // Check if new path already exists
     if new_full_path.exists() {
         return Err(format!("Item already exists: {}", operation.new_name));
@@ -380,8 +426,8 @@ pub async fn move_item(operation: MoveOperation) -> Result<String, String> {
         return Err("Source and destination are the same".to_string());
     }
 
-    // Check if trying to move directory into itself
-    if source_full_path.is_dir() && dest_full_path.starts_with(&source_full_path) {
+    // Check if trying to move directory into itself, including through symlinks
+    if source_full_path.is_dir() && is_within_dir(&dest_full_path, &source_full_path) {
         return Err("Cannot move directory into itself".to_string());
     }
 
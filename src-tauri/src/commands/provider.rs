@@ -4,7 +4,7 @@ use tauri::State;
 
 use crate::error::AppResult;
 use crate::managers::SettingsManager;
-use crate::models::{builtin_definitions, ProviderDefinition, ProviderInfo};
+use crate::models::{builtin_definitions, AuthStatus, ProviderDefinition, ProviderInfo, ProviderType};
 use crate::providers::ProviderDetector;
 
 #[tauri::command]
@@ -28,6 +28,42 @@ pub async fn detect_providers(
     Ok(result)
 }
 
+/// Whether the user is logged into a provider's CLI, via its auth status subcommand
+#[tauri::command]
+pub async fn check_provider_auth(
+    settings_manager: State<'_, Arc<SettingsManager>>,
+    provider_type: ProviderType,
+) -> AppResult<AuthStatus> {
+    let settings = settings_manager.get_settings();
+    let id = provider_type.as_id().to_string();
+    let definition = builtin_definitions()
+        .into_iter()
+        .chain(settings.custom_providers.iter().cloned())
+        .find(|d| d.id == id)
+        .ok_or_else(|| {
+            crate::error::AppError::NotFound(format!("No provider definition found for '{}'", id))
+        })?;
+    let custom_cli_path = settings
+        .provider_settings
+        .get(&id)
+        .and_then(|s| s.custom_cli_path.clone());
+
+    tokio::task::spawn_blocking(move || {
+        ProviderDetector::check_auth(&definition, custom_cli_path.as_deref())
+    })
+    .await
+    .map_err(|e| crate::error::AppError::Provider(format!("Task failed: {}", e)))
+}
+
+/// Re-read the PATH from the user's shell so CLIs installed after launch are
+/// found. Detection is not cached, so the next `detect_providers` sees them.
+#[tauri::command]
+pub async fn refresh_shell_path() -> AppResult<Option<String>> {
+    tokio::task::spawn_blocking(ProviderDetector::refresh_shell_path)
+        .await
+        .map_err(|e| crate::error::AppError::Provider(format!("Task failed: {}", e)))
+}
+
 #[tauri::command]
 pub async fn add_custom_provider(
     settings_manager: State<'_, Arc<SettingsManager>>,
@@ -1,12 +1,22 @@
+use std::collections::HashMap;
 use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use agent_client_protocol::SessionConfigOption;
 use tauri::State;
 
-use crate::managers::{SessionManager, WorktreeManager};
+use crate::db::{DatabaseStatus, WalCheckpoint};
+use crate::managers::{SessionManager, SettingsManager, WorktreeManager};
 use crate::models::{
-    ChatMessage, CreateSessionRequest, PromptContent, Session,
+    ActiveSessionInfo, ActivityLogEntry, AgentSessionInfo, AvailableCommand, ChatMessage,
+    CommandResult, ExportFormat, CreateSessionRequest, FeedEvent, ImageContent, MessageBookmark,
+    PendingRequest, PerformanceMetrics, ProcessInfo, ProjectSummary, PromptContent, ProviderType,
+    Session, SessionStatus, SessionTiming, ToolCallDetail,
 };
 use crate::models::session::{
-    ConflictContent, GitScmStatus, MergeRebaseResult,
+    BlameLine, ConflictContent, FileDiff, GitScmStatus, MergeRebaseResult, ProjectInfo,
+    SessionFileDiff, SessionGitOverview, WorktreeInfo, WorktreeRepairReport,
 };
 
 #[tauri::command]
@@ -25,6 +35,106 @@ pub async fn list_sessions(manager: State<'_, SessionManager>) -> Result<Vec<Ses
     Ok(manager.list_sessions().await)
 }
 
+#[tauri::command]
+pub async fn reload_sessions(manager: State<'_, SessionManager>) -> Result<Vec<Session>, String> {
+    manager.reload_from_db().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_active_sessions(
+    manager: State<'_, SessionManager>,
+) -> Result<Vec<ActiveSessionInfo>, String> {
+    Ok(manager.list_active_sessions().await)
+}
+
+#[tauri::command]
+pub async fn search_sessions(
+    manager: State<'_, SessionManager>,
+    query: String,
+) -> Result<Vec<Session>, String> {
+    Ok(manager.search_sessions(&query).await)
+}
+
+#[tauri::command]
+pub async fn get_session_status_counts(
+    manager: State<'_, SessionManager>,
+) -> Result<HashMap<SessionStatus, usize>, String> {
+    Ok(manager.status_counts().await)
+}
+
+#[tauri::command]
+pub async fn get_session_timing(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<SessionTiming, String> {
+    manager
+        .get_session_timing(&session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_performance_metrics(
+    manager: State<'_, SessionManager>,
+) -> Result<PerformanceMetrics, String> {
+    manager
+        .get_performance_metrics()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_projects(
+    manager: State<'_, SessionManager>,
+) -> Result<Vec<ProjectSummary>, String> {
+    Ok(manager.list_projects().await)
+}
+
+/// Default number of entries returned by `get_recent_projects`
+const DEFAULT_RECENT_PROJECTS_LIMIT: usize = 10;
+
+#[tauri::command]
+pub async fn get_recent_projects(
+    manager: State<'_, SessionManager>,
+    limit: Option<usize>,
+) -> Result<Vec<String>, String> {
+    Ok(manager
+        .recent_projects(limit.unwrap_or(DEFAULT_RECENT_PROJECTS_LIMIT))
+        .await)
+}
+
+#[tauri::command]
+pub async fn get_worktree_count(
+    manager: State<'_, SessionManager>,
+    project_path: String,
+) -> Result<usize, String> {
+    manager
+        .get_worktree_count(&project_path)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_worktrees(
+    manager: State<'_, SessionManager>,
+    project_path: String,
+) -> Result<Vec<WorktreeInfo>, String> {
+    manager
+        .list_worktrees(&project_path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn cleanup_project_worktrees(
+    manager: State<'_, SessionManager>,
+    project_path: String,
+) -> Result<Vec<String>, String> {
+    manager
+        .cleanup_project_worktrees(&project_path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn get_session(
     manager: State<'_, SessionManager>,
@@ -41,25 +151,91 @@ pub async fn send_message(
     manager: State<'_, SessionManager>,
     session_id: String,
     content: Vec<PromptContent>,
-) -> Result<(), String> {
+) -> Result<String, String> {
     manager
         .send_message(&session_id, content)
         .await
         .map_err(|e| e.to_string())
 }
 
+/// Default time `send_message_blocking` waits for the turn to complete
+const DEFAULT_SEND_BLOCKING_TIMEOUT_SECS: u64 = 600;
+
+/// Send a text prompt and wait for the turn to complete, returning the
+/// assistant's text. Meant for scripts and tests.
+#[tauri::command]
+pub async fn send_message_blocking(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    message: String,
+    timeout_secs: Option<u64>,
+) -> Result<String, String> {
+    let timeout = Duration::from_secs(timeout_secs.unwrap_or(DEFAULT_SEND_BLOCKING_TIMEOUT_SECS));
+    manager
+        .send_message_blocking(&session_id, &message, timeout)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn send_command(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    command_name: String,
+    args: Option<String>,
+) -> Result<String, String> {
+    manager
+        .send_command(&session_id, &command_name, args.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn terminate_session(
     manager: State<'_, SessionManager>,
     session_id: String,
     cleanup_worktree: bool,
-) -> Result<(), String> {
+) -> Result<CommandResult<()>, String> {
     manager
         .terminate_session(&session_id, cleanup_worktree)
         .await
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn forget_session(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<(), String> {
+    manager
+        .forget_session(&session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_session_branch(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<(), String> {
+    manager
+        .delete_session_branch(&session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn graduate_session(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    new_branch_name: String,
+) -> Result<String, String> {
+    manager
+        .graduate_session(&session_id, &new_branch_name)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn merge_session(
     manager: State<'_, SessionManager>,
@@ -72,11 +248,87 @@ pub async fn merge_session(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn preview_session_merge(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    target_branch: String,
+) -> Result<Vec<FileDiff>, String> {
+    manager
+        .preview_session_merge(&session_id, &target_branch)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn verify_worktree(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<WorktreeRepairReport, String> {
+    manager
+        .verify_worktree(&session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_session_ahead_behind(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    base_branch: Option<String>,
+) -> Result<(usize, usize), String> {
+    manager
+        .session_ahead_behind(&session_id, base_branch.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn list_branches(project_path: String, include_remote: bool) -> Result<Vec<String>, String> {
     WorktreeManager::list_branches(Path::new(&project_path), include_remote).map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn inspect_project(path: String) -> Result<ProjectInfo, String> {
+    WorktreeManager::inspect_project(Path::new(&path)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_project_sessions_overview(
+    manager: State<'_, SessionManager>,
+    project_path: String,
+) -> Result<Vec<SessionGitOverview>, String> {
+    manager
+        .project_sessions_overview(&project_path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn discover_orphan_agent_sessions(
+    manager: State<'_, SessionManager>,
+    project_path: String,
+) -> Result<Vec<AgentSessionInfo>, String> {
+    manager
+        .discover_orphan_agent_sessions(&project_path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn import_agent_session(
+    manager: State<'_, SessionManager>,
+    project_path: String,
+    acp_session_id: String,
+    name: String,
+    provider: ProviderType,
+) -> Result<Session, String> {
+    manager
+        .import_agent_session(&project_path, &acp_session_id, &name, provider)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn rename_session(
     manager: State<'_, SessionManager>,
@@ -89,6 +341,30 @@ pub async fn rename_session(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn set_session_pinned(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    pinned: bool,
+) -> Result<Session, String> {
+    manager
+        .set_session_pinned(&session_id, pinned)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_session_color(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    color: Option<String>,
+) -> Result<Session, String> {
+    manager
+        .set_session_color(&session_id, color.as_deref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn send_interaction_response(
     manager: State<'_, SessionManager>,
@@ -101,6 +377,62 @@ pub async fn send_interaction_response(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn clear_pending_permission(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<bool, String> {
+    manager
+        .clear_pending_permission(&session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_session_pending_requests(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<Vec<PendingRequest>, String> {
+    manager
+        .get_pending_requests(&session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_session_activity_log(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    limit: usize,
+) -> Result<Vec<ActivityLogEntry>, String> {
+    manager
+        .get_activity_log(&session_id, limit)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_session_process_info(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<Option<ProcessInfo>, String> {
+    manager
+        .get_session_process_info(&session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn clear_pending_requests(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<usize, String> {
+    manager
+        .clear_pending_requests(&session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn resume_session(
     manager: State<'_, SessionManager>,
@@ -112,6 +444,95 @@ pub async fn resume_session(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn cancel_resume(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<bool, String> {
+    manager
+        .cancel_resume(&session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn is_session_resumable(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<bool, String> {
+    Ok(manager.is_resumable(&session_id).await)
+}
+
+#[tauri::command]
+pub async fn attach_session(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<Session, String> {
+    manager
+        .attach_session(&session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn reload_session_settings(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<Session, String> {
+    manager
+        .reload_session_settings(&session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_session_cwd(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    path: String,
+) -> Result<Session, String> {
+    manager
+        .set_session_cwd(&session_id, &path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_session_commands(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<Vec<AvailableCommand>, String> {
+    manager
+        .get_session_commands(&session_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_session_to_file(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    destination_path: String,
+    format: ExportFormat,
+) -> Result<String, String> {
+    manager
+        .export_session_to_file(&session_id, &destination_path, format)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_session_patch(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    destination_path: String,
+) -> Result<String, String> {
+    manager
+        .export_session_patch(&session_id, &destination_path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn get_session_messages(
     manager: State<'_, SessionManager>,
@@ -127,13 +548,150 @@ pub async fn get_session_messages(
 #[tauri::command]
 pub async fn save_message(
     manager: State<'_, SessionManager>,
-    message: ChatMessage,
+    mut message: ChatMessage,
 ) -> Result<(), String> {
+    if message.meta.is_none() {
+        message.meta = manager.take_message_meta(&message.id).await;
+    }
     let db = manager.database().clone();
-    let result = tokio::task::spawn_blocking(move || db.save_message(&message))
+    let limit = manager.message_size_limit();
+    let message = tokio::task::spawn_blocking(move || {
+        db.save_message(&message, Some(&limit)).map(|()| message)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+    .map_err(|e| e.to_string())?;
+    manager.notify_message_saved(&message);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_full_message_content(
+    manager: State<'_, SessionManager>,
+    message_id: String,
+) -> Result<String, String> {
+    let db = manager.database().clone();
+    tokio::task::spawn_blocking(move || db.load_message_overflow(&message_id))
         .await
-        .map_err(|e| format!("Task join error: {}", e))?;
-    result.map_err(|e| e.to_string())
+        .map_err(|e| format!("Task join error: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_message_meta(
+    manager: State<'_, SessionManager>,
+    message_id: String,
+) -> Result<Option<serde_json::Value>, String> {
+    let db = manager.database().clone();
+    tokio::task::spawn_blocking(move || db.get_message_meta(&message_id))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn rotate_database(manager: State<'_, SessionManager>) -> Result<String, String> {
+    manager.rotate_database().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn checkpoint_database(
+    manager: State<'_, SessionManager>,
+) -> Result<WalCheckpoint, String> {
+    let db = manager.database().clone();
+    tokio::task::spawn_blocking(move || db.checkpoint())
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_database_status(
+    manager: State<'_, SessionManager>,
+) -> Result<DatabaseStatus, String> {
+    Ok(manager.database().status())
+}
+
+#[tauri::command]
+pub async fn get_session_image(
+    manager: State<'_, SessionManager>,
+    image_id: String,
+) -> Result<ImageContent, String> {
+    let db = manager.database().clone();
+    tokio::task::spawn_blocking(move || db.load_image(&image_id))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_tool_call_detail(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    tool_call_id: String,
+) -> Result<ToolCallDetail, String> {
+    let db = manager.database().clone();
+    let result = tokio::task::spawn_blocking(move || {
+        db.get_tool_call_detail(&session_id, &tool_call_id)
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?;
+    result
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Tool call not found".to_string())
+}
+
+#[tauri::command]
+pub async fn add_bookmark(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    message_id: String,
+    note: Option<String>,
+) -> Result<MessageBookmark, String> {
+    let db = manager.database().clone();
+    tokio::task::spawn_blocking(move || db.add_bookmark(&session_id, &message_id, note.as_deref()))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_bookmarks(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<Vec<MessageBookmark>, String> {
+    let db = manager.database().clone();
+    tokio::task::spawn_blocking(move || db.list_bookmarks(&session_id))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn remove_bookmark(
+    manager: State<'_, SessionManager>,
+    bookmark_id: String,
+) -> Result<(), String> {
+    let db = manager.database().clone();
+    tokio::task::spawn_blocking(move || db.remove_bookmark(&bookmark_id))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+        .map_err(|e| e.to_string())
+}
+
+/// Recent events of all sessions, newest first. `since` keeps only later events.
+#[tauri::command]
+pub async fn get_activity_feed(
+    manager: State<'_, SessionManager>,
+    limit: Option<usize>,
+    since: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<Vec<FeedEvent>, String> {
+    let db = manager.database().clone();
+    let limit = limit.unwrap_or(100).min(1000);
+    tokio::task::spawn_blocking(move || db.get_events(limit, since))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -173,6 +731,25 @@ pub async fn set_session_config_option(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn set_session_config_options(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    options: Vec<(String, String)>,
+) -> Result<Vec<SessionConfigOption>, String> {
+    manager
+        .set_session_config_options(&session_id, options)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn cancel_all_generation(
+    manager: State<'_, SessionManager>,
+) -> Result<HashMap<String, String>, String> {
+    Ok(manager.cancel_all().await)
+}
+
 #[tauri::command]
 pub async fn cancel_generation(
     manager: State<'_, SessionManager>,
@@ -185,6 +762,26 @@ pub async fn cancel_generation(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn get_active_tool_calls(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+) -> Result<Vec<String>, String> {
+    Ok(manager.get_active_tool_calls(&session_id).await)
+}
+
+#[tauri::command]
+pub async fn cancel_tool_call(
+    manager: State<'_, SessionManager>,
+    session_id: String,
+    tool_call_id: String,
+) -> Result<(), String> {
+    manager
+        .cancel_tool_call(&session_id, &tool_call_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn git_sync(project_path: String) -> Result<String, String> {
     WorktreeManager::sync_repository(Path::new(&project_path)).map_err(|e| e.to_string())
@@ -222,6 +819,48 @@ pub async fn git_file_diff(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn get_session_file_diff(
+    project_path: String,
+    session_id: String,
+    relative_path: String,
+) -> Result<SessionFileDiff, String> {
+    WorktreeManager::diff_file(Path::new(&project_path), &session_id, &relative_path)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn blame_file(
+    project_path: String,
+    session_id: String,
+    relative_path: String,
+) -> Result<Vec<BlameLine>, String> {
+    WorktreeManager::blame_file(Path::new(&project_path), &session_id, &relative_path)
+        .map_err(|e| e.to_string())
+}
+
+/// Archive a session's worktree as a `.tar.gz` at `destination_path`.
+/// Returns the number of files archived.
+#[tauri::command]
+pub async fn export_worktree_snapshot(
+    project_path: String,
+    session_id: String,
+    destination_path: String,
+    respect_gitignore: Option<bool>,
+) -> Result<usize, String> {
+    tokio::task::spawn_blocking(move || {
+        WorktreeManager::snapshot_worktree(
+            Path::new(&project_path),
+            &session_id,
+            Path::new(&destination_path),
+            respect_gitignore.unwrap_or(false),
+        )
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+    .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn git_stage_file(repo_path: String, file_path: String) -> Result<(), String> {
     WorktreeManager::stage_file(Path::new(&repo_path), &file_path).map_err(|e| e.to_string())
@@ -272,14 +911,17 @@ pub async fn git_rebase_onto(
 
 #[tauri::command]
 pub async fn git_merge_to(
+    settings: State<'_, Arc<SettingsManager>>,
     session_id: String,
     project_path: String,
     target_branch: String,
 ) -> Result<MergeRebaseResult, String> {
+    let project_path = Path::new(&project_path);
     WorktreeManager::merge_to_branch_with_result(
-        Path::new(&project_path),
+        project_path,
         &session_id,
         &target_branch,
+        &settings.branch_prefix(project_path),
     )
     .map_err(|e| e.to_string())
 }
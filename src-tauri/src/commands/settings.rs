@@ -1,10 +1,14 @@
+use std::path::Path;
 use std::sync::Arc;
 
 use serde::{Deserialize, Serialize};
 use tauri::State;
 
 use crate::managers::SettingsManager;
-use crate::models::{AppearanceSettings, AppSettings, GeneralSettings, NotificationSettings, ProviderSettings};
+use crate::models::{
+    AppearanceSettings, AppSettings, EffectiveSettings, GeneralSettings, NotificationSettings,
+    ProviderSettings,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -66,6 +70,27 @@ pub async fn update_provider_settings(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn get_effective_settings(
+    manager: State<'_, Arc<SettingsManager>>,
+    project_path: String,
+) -> Result<EffectiveSettings, String> {
+    Ok(manager.get_effective_settings(Path::new(&project_path)))
+}
+
+/// Override the session branch prefix for one project, or drop the override
+/// when `branch_prefix` is None
+#[tauri::command]
+pub async fn set_project_branch_prefix(
+    manager: State<'_, Arc<SettingsManager>>,
+    project_path: String,
+    branch_prefix: Option<String>,
+) -> Result<(), String> {
+    manager
+        .set_project_branch_prefix(Path::new(&project_path), branch_prefix.as_deref())
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn get_ui_settings(
     manager: State<'_, Arc<SettingsManager>>,
@@ -85,7 +110,22 @@ pub async fn update_ui_settings(
 ) -> Result<(), String> {
     let mut settings = manager.get_settings();
 
-    if let Some(general) = ui_settings.general {
+    if let Some(mut general) = ui_settings.general {
+        // Keep fields the UI doesn't manage rather than wiping them
+        if let Some(current) = settings.general.as_ref() {
+            if general.auto_commit_per_turn.is_none() {
+                general.auto_commit_per_turn = current.auto_commit_per_turn;
+            }
+            if general.quick_new_session_shortcut.is_none() {
+                general.quick_new_session_shortcut = current.quick_new_session_shortcut.clone();
+            }
+            if general.auto_resume_on_startup.is_none() {
+                general.auto_resume_on_startup = current.auto_resume_on_startup;
+            }
+            if general.branch_prefix.is_none() {
+                general.branch_prefix = current.branch_prefix.clone();
+            }
+        }
         settings.general = Some(general);
     }
 
@@ -1,7 +1,7 @@
 use tauri::State;
 
 use crate::error::AppResult;
-use crate::managers::terminal_manager::TerminalInfo;
+use crate::managers::terminal_manager::{TerminalInfo, TerminalSummary};
 use crate::managers::TerminalManager;
 
 #[derive(serde::Deserialize)]
@@ -23,6 +23,13 @@ pub async fn list_terminals(
     Ok(terminal_manager.list_terminals().await)
 }
 
+#[tauri::command]
+pub async fn list_all_terminals(
+    terminal_manager: State<'_, TerminalManager>,
+) -> AppResult<Vec<TerminalSummary>> {
+    Ok(terminal_manager.list_all().await)
+}
+
 #[tauri::command]
 pub async fn create_terminal(
     terminal_manager: State<'_, TerminalManager>,
@@ -58,6 +65,14 @@ pub async fn send_terminal_input(
         .await
 }
 
+#[tauri::command]
+pub async fn clear_terminal(
+    terminal_manager: State<'_, TerminalManager>,
+    terminal_id: String,
+) -> AppResult<()> {
+    terminal_manager.clear_terminal(&terminal_id).await
+}
+
 #[derive(serde::Deserialize)]
 pub struct ResizeTerminalRequest {
     pub terminal_id: String,
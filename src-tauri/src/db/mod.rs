@@ -1,16 +1,106 @@
-use std::sync::{Arc, Mutex};
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError};
+use std::time::Duration;
 
-use rusqlite::{params, Connection};
+use rusqlite::{params, Connection, ErrorCode, OptionalExtension};
+use serde::Serialize;
 use tauri::{AppHandle, Manager};
 
 use crate::error::{AppError, AppResult};
 use crate::models::{
-    ChatMessage, MessageContentType, MessageRole, ProviderType, Session, SessionError,
-    SessionStatus, ToolCallInfo, ToolUseInfo,
+    ChatMessage, DatabaseMetrics, FeedEvent, FeedEventKind, ImageContent, MessageBookmark,
+    MessageContentType, MessageRole, ProviderType, Session, SessionError, SessionStatus,
+    ToolCallDetail, ToolCallInfo, ToolUseInfo, TurnTiming,
 };
+use crate::utils::truncate_str;
+
+/// Start of the note appended to a message whose content was cut at the size limit
+pub const TRUNCATED_MESSAGE_MARKER: &str = "[forkestra: message truncated,";
+
+/// Cap on the content stored for a single message
+#[derive(Debug, Clone, Copy)]
+pub struct MessageSizeLimit {
+    /// Largest content kept, in bytes (0 = unlimited)
+    pub max_bytes: usize,
+    /// Refuse larger messages instead of truncating them
+    pub reject: bool,
+}
+
+/// How the database came up at startup
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum DatabaseStatus {
+    Ok,
+    /// The database was corrupt; it was moved to `archive_path` and whatever rows
+    /// could still be read were copied into a fresh database
+    Recovered {
+        archive_path: String,
+        recovered_sessions: usize,
+        recovered_messages: usize,
+    },
+    /// Neither the database nor a fresh one could be opened; running in memory
+    Degraded { error: String },
+}
+
+/// Activity feed events kept; older ones are pruned
+const MAX_FEED_EVENTS: i64 = 5000;
+/// Prune the activity feed once every this many inserts
+const FEED_PRUNE_INTERVAL: i64 = 100;
+/// Longest summary stored for an activity feed event, in bytes
+const MAX_FEED_SUMMARY_BYTES: usize = 200;
+
+/// Why `Database::open` failed, and whether the file itself is damaged
+struct OpenError {
+    error: AppError,
+    /// SQLite reported the file as corrupt or not a database at all
+    corrupt: bool,
+}
+
+impl OpenError {
+    fn sqlite(context: &str, e: rusqlite::Error) -> Self {
+        let corrupt = matches!(
+            e.sqlite_error_code(),
+            Some(ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase)
+        );
+        Self {
+            error: AppError::Database(format!("{}: {}", context, e)),
+            corrupt,
+        }
+    }
+}
+
+impl From<OpenError> for AppError {
+    fn from(e: OpenError) -> Self {
+        e.error
+    }
+}
+
+/// Tables copied during recovery, parents before children
+const RECOVERABLE_TABLES: [&str; 6] = [
+    "sessions",
+    "messages",
+    "tool_call_details",
+    "turn_timings",
+    "session_bookmarks",
+    "events",
+];
+
+/// Outcome of `PRAGMA wal_checkpoint(TRUNCATE)`
+#[derive(Debug, Clone, Serialize)]
+pub struct WalCheckpoint {
+    /// Another connection held a lock, so the checkpoint did not finish
+    pub busy: bool,
+    /// Frames in the WAL before the checkpoint, -1 when not in WAL mode
+    pub wal_frames: i64,
+    /// Frames copied into the database file, -1 when not in WAL mode
+    pub checkpointed_frames: i64,
+}
 
 pub struct Database {
     conn: Arc<Mutex<Connection>>,
+    path: PathBuf,
+    status: DatabaseStatus,
 }
 
 // Safety: Connection is only accessed while the Mutex is held
@@ -25,28 +115,494 @@ impl Database {
             .map_err(|e| AppError::Io(format!("Failed to get app data dir: {}", e)))?;
 
         std::fs::create_dir_all(&app_dir)?;
-        let db_path = app_dir.join("forkestra.db");
+        Self::open_at(app_dir.join("forkestra.db"))
+    }
+
+    /// Open the database file at `db_path`. Only a corrupt file is moved aside
+    /// and recovered; any other failure, such as the file being locked by
+    /// another instance, is returned so the file is left untouched.
+    fn open_at(db_path: PathBuf) -> AppResult<Self> {
+        let (conn, status) = match Self::open(&db_path) {
+            Ok(conn) => (conn, DatabaseStatus::Ok),
+            Err(OpenError {
+                error,
+                corrupt: true,
+            }) => {
+                eprintln!(
+                    "[Database] {} is corrupt: {}. Attempting recovery",
+                    db_path.display(),
+                    error
+                );
+                Self::recover(&db_path)?
+            }
+            Err(OpenError { error, .. }) => return Err(error),
+        };
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            path: db_path,
+            status,
+        })
+    }
 
-        let conn = Connection::open(&db_path)
-            .map_err(|e| AppError::Database(format!("Failed to open database: {}", e)))?;
+    /// Last-resort database that lives only for this run, used when the file
+    /// database cannot be opened at all so the app can still start
+    pub fn in_memory(error: &AppError) -> AppResult<Self> {
+        let conn = Connection::open_in_memory()
+            .map_err(|e| AppError::Database(format!("Failed to open in-memory database: {}", e)))?;
+        conn.execute_batch("PRAGMA foreign_keys = ON;")
+            .map_err(|e| AppError::Database(format!("Failed to set pragmas: {}", e)))?;
+        conn.execute_batch(include_str!("schema.sql"))
+            .map_err(|e| AppError::Database(format!("Failed to initialize schema: {}", e)))?;
+        // Columns added after the base schema only exist through migrations
+        Self::migrate(&conn)?;
+
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            path: PathBuf::from(":memory:"),
+            status: DatabaseStatus::Degraded {
+                error: error.to_string(),
+            },
+        })
+    }
+
+    pub fn status(&self) -> DatabaseStatus {
+        self.status.clone()
+    }
+
+    /// Directory next to the database file, `None` for an in-memory database
+    fn sibling_dir(&self, name: &str) -> Option<PathBuf> {
+        self.path
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty())
+            .map(|p| p.join(name))
+    }
+
+    /// Directory next to the database file holding agent images
+    fn images_dir(&self) -> AppResult<PathBuf> {
+        self.sibling_dir("session-images").ok_or_else(|| {
+            AppError::InvalidOperation("Images are not stored for an in-memory database".to_string())
+        })
+    }
+
+    /// Directory next to the database file holding the full content of
+    /// truncated messages
+    fn message_overflow_dir(&self) -> AppResult<PathBuf> {
+        self.sibling_dir("message-overflow").ok_or_else(|| {
+            AppError::InvalidOperation(
+                "Message overflow is not stored for an in-memory database".to_string(),
+            )
+        })
+    }
+
+    /// Apply `limit` to a message about to be saved. Returns the content to
+    /// store, which is the original unless it had to be truncated.
+    fn limit_message_content<'a>(
+        &self,
+        message: &'a ChatMessage,
+        limit: &MessageSizeLimit,
+    ) -> AppResult<Cow<'a, str>> {
+        let size = message.content.len();
+        if limit.max_bytes == 0 || size <= limit.max_bytes {
+            return Ok(Cow::Borrowed(&message.content));
+        }
+        if limit.reject {
+            return Err(AppError::InvalidOperation(format!(
+                "Message content is {} bytes, over the {} byte limit",
+                size, limit.max_bytes
+            )));
+        }
+
+        // Ids name the side file, so only UUIDs are accepted
+        let overflow_path = uuid::Uuid::parse_str(&message.id)
+            .map_err(|_| AppError::InvalidOperation(format!("Invalid message id '{}'", message.id)))
+            .and_then(|_| self.message_overflow_dir())
+            .and_then(|dir| {
+                std::fs::create_dir_all(&dir)?;
+                let path = dir.join(format!("{}.txt", message.id));
+                std::fs::write(&path, &message.content)?;
+                Ok(path)
+            });
+        let reference = match overflow_path {
+            Ok(path) => format!("full content in {}", path.display()),
+            Err(e) => {
+                eprintln!(
+                    "[Database] Failed to keep full content of message {}: {}",
+                    message.id, e
+                );
+                "full content discarded".to_string()
+            }
+        };
+        println!(
+            "[Database] Truncated message {} from {} to {} bytes",
+            message.id, size, limit.max_bytes
+        );
+
+        Ok(Cow::Owned(format!(
+            "{}\n\n{} {} bytes total, {}]",
+            crate::utils::truncate_str(&message.content, limit.max_bytes),
+            TRUNCATED_MESSAGE_MARKER,
+            size,
+            reference
+        )))
+    }
+
+    /// Provider-specific meta stored with a message, `None` when it has none
+    pub fn get_message_meta(&self, message_id: &str) -> AppResult<Option<serde_json::Value>> {
+        let conn = self.lock_conn();
+        let meta: Option<String> = conn
+            .query_row(
+                "SELECT meta FROM messages WHERE id = ?1",
+                params![message_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| AppError::Database(format!("Failed to load message meta: {}", e)))?
+            .ok_or_else(|| AppError::NotFound(format!("Message '{}' not found", message_id)))?;
+        Ok(meta.and_then(|m| serde_json::from_str(&m).ok()))
+    }
+
+    /// Full content of a message that was truncated on save
+    pub fn load_message_overflow(&self, message_id: &str) -> AppResult<String> {
+        uuid::Uuid::parse_str(message_id)
+            .map_err(|_| AppError::InvalidOperation(format!("Invalid message id '{}'", message_id)))?;
+
+        let path = self.message_overflow_dir()?.join(format!("{}.txt", message_id));
+        if !path.is_file() {
+            return Err(AppError::NotFound(format!(
+                "No stored full content for message '{}'",
+                message_id
+            )));
+        }
+        Ok(std::fs::read_to_string(&path)?)
+    }
+
+    /// Write an agent image to `session-images/<image_id>.<ext>`, the extension
+    /// following its MIME type. Unsupported types are rejected.
+    pub fn save_image(&self, image_id: &str, image: &ImageContent) -> AppResult<PathBuf> {
+        use base64::Engine;
+
+        let extension = crate::utils::image_extension_for_mime(&image.mime_type).ok_or_else(|| {
+            AppError::InvalidOperation(format!("Unsupported image type '{}'", image.mime_type))
+        })?;
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(image.data.trim())
+            .map_err(|e| AppError::InvalidOperation(format!("Invalid image data: {}", e)))?;
+
+        let dir = self.images_dir()?;
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.{}", image_id, extension));
+        std::fs::write(&path, bytes)?;
+        Ok(path)
+    }
+
+    /// Read back an image stored by `save_image`, with the MIME type implied by
+    /// its extension
+    pub fn load_image(&self, image_id: &str) -> AppResult<ImageContent> {
+        use base64::Engine;
+
+        // Ids are UUIDs; anything else could escape the images directory
+        uuid::Uuid::parse_str(image_id)
+            .map_err(|_| AppError::InvalidOperation(format!("Invalid image id '{}'", image_id)))?;
+
+        let dir = self.images_dir()?;
+        for extension in ["png", "jpg", "gif", "webp"] {
+            let path = dir.join(format!("{}.{}", image_id, extension));
+            if !path.is_file() {
+                continue;
+            }
+            let mime_type = crate::utils::image_mime_for_extension(extension)
+                .unwrap_or("application/octet-stream");
+            let bytes = std::fs::read(&path)?;
+            return Ok(ImageContent {
+                data: base64::engine::general_purpose::STANDARD.encode(bytes),
+                mime_type: mime_type.to_string(),
+                uri: None,
+            });
+        }
+
+        Err(AppError::NotFound(format!("Image '{}' not found", image_id)))
+    }
+
+    /// Move a corrupt database aside, open a fresh one in its place and copy
+    /// over every row that can still be read from the old file
+    fn recover(db_path: &Path) -> AppResult<(Connection, DatabaseStatus)> {
+        let stamp = chrono::Utc::now().format("%Y%m%d-%H%M%S");
+        let archive_path = db_path.with_file_name(format!("forkestra-corrupt-{}.db", stamp));
+        std::fs::rename(db_path, &archive_path)?;
+        for suffix in ["-wal", "-shm"] {
+            let side_file = PathBuf::from(format!("{}{}", db_path.display(), suffix));
+            if side_file.exists() {
+                let archived_side = PathBuf::from(format!("{}{}", archive_path.display(), suffix));
+                std::fs::rename(side_file, archived_side)?;
+            }
+        }
+
+        let conn = Self::open(db_path)?;
+
+        let mut recovered = [0usize; RECOVERABLE_TABLES.len()];
+        match conn.execute(
+            "ATTACH DATABASE ?1 AS corrupt",
+            params![archive_path.to_string_lossy()],
+        ) {
+            Ok(_) => {
+                // Orphaned rows are still worth keeping; don't let them abort a table
+                let _ = conn.execute_batch("PRAGMA foreign_keys = OFF;");
+                for (i, table) in RECOVERABLE_TABLES.iter().enumerate() {
+                    match Self::copy_recoverable_rows(&conn, table) {
+                        Ok(count) => recovered[i] = count,
+                        Err(e) => eprintln!(
+                            "[Database] Could not recover rows from {}: {}",
+                            table, e
+                        ),
+                    }
+                }
+                let _ = conn.execute_batch("PRAGMA foreign_keys = ON;");
+                let _ = conn.execute_batch("DETACH DATABASE corrupt");
+            }
+            Err(e) => eprintln!("[Database] Could not attach corrupt database: {}", e),
+        }
+
+        println!(
+            "[Database] Recovered {} sessions and {} messages, corrupt database moved to {}",
+            recovered[0],
+            recovered[1],
+            archive_path.display()
+        );
+
+        Ok((
+            conn,
+            DatabaseStatus::Recovered {
+                archive_path: archive_path.to_string_lossy().to_string(),
+                recovered_sessions: recovered[0],
+                recovered_messages: recovered[1],
+            },
+        ))
+    }
+
+    /// Copy the columns `table` has in both databases from `corrupt` into `main`
+    fn copy_recoverable_rows(conn: &Connection, table: &str) -> rusqlite::Result<usize> {
+        let columns = |schema: &str| -> rusqlite::Result<Vec<String>> {
+            let mut stmt = conn.prepare(&format!("PRAGMA {}.table_info({})", schema, table))?;
+            let cols = stmt
+                .query_map([], |row| row.get::<_, String>(1))?
+                .filter_map(|r| r.ok())
+                .collect();
+            Ok(cols)
+        };
+
+        let main_cols = columns("main")?;
+        let shared: Vec<String> = columns("corrupt")?
+            .into_iter()
+            .filter(|c| main_cols.contains(c))
+            .collect();
+        if shared.is_empty() {
+            return Ok(0);
+        }
+
+        let cols = shared.join(", ");
+        conn.execute(
+            &format!(
+                "INSERT OR IGNORE INTO main.{table} ({cols}) SELECT {cols} FROM corrupt.{table}",
+                table = table,
+                cols = cols
+            ),
+            [],
+        )
+    }
+
+    /// Open a connection, apply pragmas, and bring the schema up to date
+    fn open(db_path: &Path) -> Result<Connection, OpenError> {
+        let conn = Connection::open(db_path)
+            .map_err(|e| OpenError::sqlite("Failed to open database", e))?;
 
         // WAL mode + foreign keys
         conn.execute_batch(
             "PRAGMA journal_mode = WAL;
              PRAGMA foreign_keys = ON;",
         )
-        .map_err(|e| AppError::Database(format!("Failed to set pragmas: {}", e)))?;
+        .map_err(|e| OpenError::sqlite("Failed to set pragmas", e))?;
+
+        let integrity: String = conn
+            .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+            .map_err(|e| OpenError::sqlite("Failed to check integrity", e))?;
+        if integrity != "ok" {
+            return Err(OpenError {
+                error: AppError::Database(format!("Integrity check failed: {}", integrity)),
+                corrupt: true,
+            });
+        }
 
         // Initialize schema
         conn.execute_batch(include_str!("schema.sql"))
-            .map_err(|e| AppError::Database(format!("Failed to initialize schema: {}", e)))?;
+            .map_err(|e| OpenError::sqlite("Failed to initialize schema", e))?;
 
         // Run migrations for existing databases
-        Self::migrate(&conn)?;
+        Self::migrate(&conn).map_err(|error| OpenError {
+            error,
+            corrupt: false,
+        })?;
 
-        Ok(Self {
-            conn: Arc::new(Mutex::new(conn)),
+        Ok(conn)
+    }
+
+    /// Move the database (and its WAL/SHM files) to a timestamped archive next to
+    /// it and start over with an empty schema. The connection lock is held for the
+    /// whole rotation, so other database calls wait until it finishes.
+    /// Returns the path of the archived database.
+    pub fn rotate(&self) -> AppResult<PathBuf> {
+        let stamp = chrono::Utc::now().format("%Y%m%d-%H%M%S");
+        let archive_path = self.path.with_file_name(format!("forkestra-{}.db", stamp));
+        self.rotate_to(&archive_path)?;
+        Ok(archive_path)
+    }
+
+    /// Rotate into `archive_path`. If the database can't be archived or the
+    /// fresh one can't be created, the original is moved back and reopened.
+    fn rotate_to(&self, archive_path: &Path) -> AppResult<()> {
+        let mut conn = self.lock_conn();
+
+        // Fold the WAL into the main file so the archive is self-contained
+        conn.execute_batch("PRAGMA wal_checkpoint(TRUNCATE);")
+            .map_err(|e| AppError::Database(format!("Failed to checkpoint WAL: {}", e)))?;
+
+        // Swap in a placeholder so the file connection is closed before moving it
+        let placeholder = Connection::open_in_memory()
+            .map_err(|e| AppError::Database(format!("Failed to open placeholder: {}", e)))?;
+        drop(std::mem::replace(&mut *conn, placeholder));
+
+        let fresh = std::fs::rename(&self.path, archive_path)
+            .map_err(AppError::from)
+            .and_then(|()| {
+                let fresh = Self::move_side_files(&self.path, archive_path)
+                    .and_then(|()| Self::open(&self.path).map_err(AppError::from));
+                if fresh.is_err() {
+                    // Put the original back in place of whatever was half created
+                    let _ = std::fs::remove_file(&self.path);
+                    let restored = std::fs::rename(archive_path, &self.path)
+                        .map_err(AppError::from)
+                        .and_then(|()| Self::move_side_files(archive_path, &self.path));
+                    if let Err(e) = restored {
+                        eprintln!(
+                            "[Database] Failed to move {} back: {}",
+                            archive_path.display(),
+                            e
+                        );
+                    }
+                }
+                fresh
+            });
+        match fresh {
+            Ok(fresh) => *conn = fresh,
+            Err(e) => {
+                eprintln!(
+                    "[Database] Rotation failed, reopening the original database: {}",
+                    e
+                );
+                *conn = Self::open(&self.path)?;
+                return Err(e);
+            }
+        }
+
+        println!(
+            "[Database] Rotated database, archive at {}",
+            archive_path.display()
+        );
+        Ok(())
+    }
+
+    /// Move the WAL/SHM files of database `from` next to database `to`
+    fn move_side_files(from: &Path, to: &Path) -> AppResult<()> {
+        for suffix in ["-wal", "-shm"] {
+            let side_file = PathBuf::from(format!("{}{}", from.display(), suffix));
+            if side_file.exists() {
+                std::fs::rename(side_file, format!("{}{}", to.display(), suffix))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Lock the connection. A panic while the lock was held poisons it, but the
+    /// connection is still usable, so recover it rather than failing every
+    /// later call for the rest of the process.
+    fn lock_conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn
+            .lock()
+            .unwrap_or_else(|poisoned| self.recover_conn(poisoned))
+    }
+
+    /// Take the guard out of a poisoned lock and roll back whatever the
+    /// panicking call left half done
+    fn recover_conn<'a>(
+        &self,
+        poisoned: PoisonError<MutexGuard<'a, Connection>>,
+    ) -> MutexGuard<'a, Connection> {
+        eprintln!("[Database] Warning: recovering connection lock poisoned by a panic");
+        self.conn.clear_poison();
+        let conn = poisoned.into_inner();
+        if !conn.is_autocommit() {
+            let _ = conn.execute_batch("ROLLBACK");
+        }
+        conn
+    }
+
+    /// Fold the WAL into the main database file and truncate it to zero bytes
+    pub fn checkpoint(&self) -> AppResult<WalCheckpoint> {
+        let conn = self.lock_conn();
+        Self::checkpoint_conn(&conn)
+    }
+
+    /// Checkpoint only when the connection is free and the WAL has grown, so
+    /// the background task never makes a write wait. Returns `None` when skipped.
+    pub fn checkpoint_if_idle(&self) -> AppResult<Option<WalCheckpoint>> {
+        if self.wal_size() == 0 {
+            return Ok(None);
+        }
+        let conn = match self.conn.try_lock() {
+            Ok(conn) => conn,
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Poisoned(poisoned)) => self.recover_conn(poisoned),
+        };
+        Self::checkpoint_conn(&conn).map(Some)
+    }
+
+    fn checkpoint_conn(conn: &Connection) -> AppResult<WalCheckpoint> {
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| {
+            Ok(WalCheckpoint {
+                busy: row.get::<_, i64>(0)? != 0,
+                wal_frames: row.get(1)?,
+                checkpointed_frames: row.get(2)?,
+            })
         })
+        .map_err(|e| AppError::Database(format!("Failed to checkpoint WAL: {}", e)))
+    }
+
+    /// Size of the `-wal` file in bytes, 0 when there is none
+    pub fn wal_size(&self) -> u64 {
+        std::fs::metadata(format!("{}-wal", self.path.display()))
+            .map(|m| m.len())
+            .unwrap_or(0)
+    }
+
+    /// Checkpoint the WAL every `interval` while the database is idle
+    pub fn spawn_wal_checkpoints(db: Arc<Database>, interval: Duration) {
+        tauri::async_runtime::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick fires immediately; nothing has been written yet
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let db = db.clone();
+                match tokio::task::spawn_blocking(move || db.checkpoint_if_idle()).await {
+                    Ok(Ok(Some(result))) if result.busy => {
+                        println!("[Database] WAL checkpoint could not complete, retrying later");
+                    }
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => eprintln!("[Database] Background WAL checkpoint failed: {}", e),
+                    Err(e) => eprintln!("[Database] WAL checkpoint task failed: {}", e),
+                }
+            }
+        });
     }
 
     /// Run database migrations for schema changes on existing databases
@@ -220,26 +776,178 @@ impl Database {
             println!("[Database] Migrated: added error_code and error_message columns to sessions");
         }
 
+        // Migration for pinned column in sessions
+        let has_pinned_col: bool = conn
+            .prepare("PRAGMA table_info(sessions)")
+            .and_then(|mut stmt| {
+                let cols: Vec<String> = stmt
+                    .query_map([], |row| row.get::<_, String>(1))
+                    .unwrap()
+                    .filter_map(|r| r.ok())
+                    .collect();
+                Ok(cols.contains(&"pinned".to_string()))
+            })
+            .unwrap_or(false);
+
+        if !has_pinned_col {
+            conn.execute_batch("ALTER TABLE sessions ADD COLUMN pinned INTEGER NOT NULL DEFAULT 0")
+                .map_err(|e| {
+                    AppError::Database(format!("Failed to add pinned column: {}", e))
+                })?;
+            println!("[Database] Migrated: added pinned column to sessions");
+        }
+
+        // Migration for system_prompt column in sessions
+        let has_system_prompt_col: bool = conn
+            .prepare("PRAGMA table_info(sessions)")
+            .and_then(|mut stmt| {
+                let cols: Vec<String> = stmt
+                    .query_map([], |row| row.get::<_, String>(1))
+                    .unwrap()
+                    .filter_map(|r| r.ok())
+                    .collect();
+                Ok(cols.contains(&"system_prompt".to_string()))
+            })
+            .unwrap_or(false);
+
+        if !has_system_prompt_col {
+            conn.execute_batch("ALTER TABLE sessions ADD COLUMN system_prompt TEXT")
+                .map_err(|e| {
+                    AppError::Database(format!("Failed to add system_prompt column: {}", e))
+                })?;
+            println!("[Database] Migrated: added system_prompt column to sessions");
+        }
+
+        // Migration for ui_color column in sessions
+        let has_ui_color_col: bool = conn
+            .prepare("PRAGMA table_info(sessions)")
+            .and_then(|mut stmt| {
+                let cols: Vec<String> = stmt
+                    .query_map([], |row| row.get::<_, String>(1))
+                    .unwrap()
+                    .filter_map(|r| r.ok())
+                    .collect();
+                Ok(cols.contains(&"ui_color".to_string()))
+            })
+            .unwrap_or(false);
+
+        if !has_ui_color_col {
+            conn.execute_batch("ALTER TABLE sessions ADD COLUMN ui_color TEXT")
+                .map_err(|e| {
+                    AppError::Database(format!("Failed to add ui_color column: {}", e))
+                })?;
+            println!("[Database] Migrated: added ui_color column to sessions");
+        }
+
+        // Migration for acp_cwd column in sessions
+        let has_acp_cwd_col: bool = conn
+            .prepare("PRAGMA table_info(sessions)")
+            .and_then(|mut stmt| {
+                let cols: Vec<String> = stmt
+                    .query_map([], |row| row.get::<_, String>(1))
+                    .unwrap()
+                    .filter_map(|r| r.ok())
+                    .collect();
+                Ok(cols.contains(&"acp_cwd".to_string()))
+            })
+            .unwrap_or(false);
+
+        if !has_acp_cwd_col {
+            conn.execute_batch("ALTER TABLE sessions ADD COLUMN acp_cwd TEXT")
+                .map_err(|e| {
+                    AppError::Database(format!("Failed to add acp_cwd column: {}", e))
+                })?;
+            println!("[Database] Migrated: added acp_cwd column to sessions");
+        }
+
+        // Migration for seq column in messages (stable ordering for equal timestamps)
+        let has_seq_col: bool = conn
+            .prepare("PRAGMA table_info(messages)")
+            .and_then(|mut stmt| {
+                let cols: Vec<String> = stmt
+                    .query_map([], |row| row.get::<_, String>(1))
+                    .unwrap()
+                    .filter_map(|r| r.ok())
+                    .collect();
+                Ok(cols.contains(&"seq".to_string()))
+            })
+            .unwrap_or(false);
+
+        if !has_seq_col {
+            // Backfill seq in the order messages are currently returned
+            conn.execute_batch(
+                "ALTER TABLE messages ADD COLUMN seq INTEGER;
+                 UPDATE messages SET seq = (
+                     SELECT rn FROM (
+                         SELECT id, ROW_NUMBER() OVER (ORDER BY timestamp, rowid) AS rn
+                         FROM messages
+                     ) ordered
+                     WHERE ordered.id = messages.id
+                 );",
+            )
+            .map_err(|e| AppError::Database(format!("Failed to add seq column: {}", e)))?;
+            println!("[Database] Migrated: added seq column to messages");
+        }
+
+        // Migration for meta column in messages
+        let has_meta_col: bool = conn
+            .prepare("PRAGMA table_info(messages)")
+            .and_then(|mut stmt| {
+                let cols: Vec<String> = stmt
+                    .query_map([], |row| row.get::<_, String>(1))
+                    .unwrap()
+                    .filter_map(|r| r.ok())
+                    .collect();
+                Ok(cols.contains(&"meta".to_string()))
+            })
+            .unwrap_or(false);
+
+        if !has_meta_col {
+            conn.execute_batch("ALTER TABLE messages ADD COLUMN meta TEXT")
+                .map_err(|e| AppError::Database(format!("Failed to add meta column: {}", e)))?;
+            println!("[Database] Migrated: added meta column to messages");
+        }
+
         Ok(())
     }
 
     // ── Session operations ──
 
     pub fn save_session(&self, session: &Session) -> AppResult<()> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|e| AppError::Database(format!("Database lock poisoned: {}", e)))?;
+        let conn = self.lock_conn();
 
         let config_options_json = serde_json::to_string(&session.config_options)
             .unwrap_or_else(|_| "[]".to_string());
 
         conn.execute(
-            "INSERT OR REPLACE INTO sessions
+            // An upsert rather than INSERT OR REPLACE: a replace deletes the row
+            // first, which cascades into the session's messages, events and bookmarks
+            "INSERT INTO sessions
              (id, name, provider, status, worktree_path, branch_name,
               project_path, is_local, created_at, updated_at, acp_session_id, model,
-              config_options, error_code, error_message)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+              config_options, error_code, error_message, pinned, system_prompt, ui_color,
+              acp_cwd)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                     ?18, ?19)
+             ON CONFLICT(id) DO UPDATE SET
+              name = excluded.name,
+              provider = excluded.provider,
+              status = excluded.status,
+              worktree_path = excluded.worktree_path,
+              branch_name = excluded.branch_name,
+              project_path = excluded.project_path,
+              is_local = excluded.is_local,
+              created_at = excluded.created_at,
+              updated_at = excluded.updated_at,
+              acp_session_id = excluded.acp_session_id,
+              model = excluded.model,
+              config_options = excluded.config_options,
+              error_code = excluded.error_code,
+              error_message = excluded.error_message,
+              pinned = excluded.pinned,
+              system_prompt = excluded.system_prompt,
+              ui_color = excluded.ui_color,
+              acp_cwd = excluded.acp_cwd",
             params![
                 session.id,
                 session.name,
@@ -256,6 +964,10 @@ impl Database {
                 config_options_json,
                 session.error.as_ref().map(|e| e.code.as_str()),
                 session.error.as_ref().map(|e| e.message.as_str()),
+                session.pinned as i32,
+                session.system_prompt,
+                session.ui_color,
+                session.acp_cwd,
             ],
         )
         .map_err(|e| AppError::Database(format!("Failed to save session: {}", e)))?;
@@ -267,15 +979,17 @@ impl Database {
         session_id: &str,
         status: &SessionStatus,
     ) -> AppResult<()> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|e| AppError::Database(format!("Database lock poisoned: {}", e)))?;
-        conn.execute(
-            "UPDATE sessions SET status = ?1 WHERE id = ?2",
-            params![session_status_to_str(status), session_id],
-        )
-        .map_err(|e| AppError::Database(format!("Failed to update session status: {}", e)))?;
+        let conn = self.lock_conn();
+        let changed = conn
+            .execute(
+                "UPDATE sessions SET status = ?1 WHERE id = ?2 AND status != ?1",
+                params![session_status_to_str(status), session_id],
+            )
+            .map_err(|e| AppError::Database(format!("Failed to update session status: {}", e)))?;
+        if changed > 0 {
+            let summary = format!("Status changed to {}", session_status_to_str(status));
+            insert_feed_event(&conn, session_id, FeedEventKind::StatusChanged, &summary)?;
+        }
         Ok(())
     }
 
@@ -285,10 +999,7 @@ impl Database {
         status: &SessionStatus,
         error: &SessionError,
     ) -> AppResult<()> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|e| AppError::Database(format!("Database lock poisoned: {}", e)))?;
+        let conn = self.lock_conn();
         conn.execute(
             "UPDATE sessions SET status = ?1, error_code = ?2, error_message = ?3 WHERE id = ?4",
             params![
@@ -303,10 +1014,7 @@ impl Database {
     }
 
     pub fn update_session_name(&self, session_id: &str, name: &str) -> AppResult<()> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|e| AppError::Database(format!("Database lock poisoned: {}", e)))?;
+        let conn = self.lock_conn();
         conn.execute(
             "UPDATE sessions SET name = ?1 WHERE id = ?2",
             params![name, session_id],
@@ -315,11 +1023,32 @@ impl Database {
         Ok(())
     }
 
+    pub fn update_session_ui_color(
+        &self,
+        session_id: &str,
+        ui_color: Option<&str>,
+    ) -> AppResult<()> {
+        let conn = self.lock_conn();
+        conn.execute(
+            "UPDATE sessions SET ui_color = ?1 WHERE id = ?2",
+            params![ui_color, session_id],
+        )
+        .map_err(|e| AppError::Database(format!("Failed to update session color: {}", e)))?;
+        Ok(())
+    }
+
+    pub fn update_session_pinned(&self, session_id: &str, pinned: bool) -> AppResult<()> {
+        let conn = self.lock_conn();
+        conn.execute(
+            "UPDATE sessions SET pinned = ?1 WHERE id = ?2",
+            params![pinned as i32, session_id],
+        )
+        .map_err(|e| AppError::Database(format!("Failed to update session pinned: {}", e)))?;
+        Ok(())
+    }
+
     pub fn update_session_branch(&self, session_id: &str, branch_name: &str) -> AppResult<()> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|e| AppError::Database(format!("Database lock poisoned: {}", e)))?;
+        let conn = self.lock_conn();
         conn.execute(
             "UPDATE sessions SET branch_name = ?1 WHERE id = ?2",
             params![branch_name, session_id],
@@ -328,15 +1057,28 @@ impl Database {
         Ok(())
     }
 
+    pub fn update_session_worktree_path(
+        &self,
+        session_id: &str,
+        worktree_path: &str,
+    ) -> AppResult<()> {
+        let conn = self.lock_conn();
+        conn.execute(
+            "UPDATE sessions SET worktree_path = ?1 WHERE id = ?2",
+            params![worktree_path, session_id],
+        )
+        .map_err(|e| {
+            AppError::Database(format!("Failed to update session worktree_path: {}", e))
+        })?;
+        Ok(())
+    }
+
     pub fn update_session_acp_id(
         &self,
         session_id: &str,
         acp_session_id: &str,
     ) -> AppResult<()> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|e| AppError::Database(format!("Database lock poisoned: {}", e)))?;
+        let conn = self.lock_conn();
         conn.execute(
             "UPDATE sessions SET acp_session_id = ?1 WHERE id = ?2",
             params![acp_session_id, session_id],
@@ -347,15 +1089,26 @@ impl Database {
         Ok(())
     }
 
+    pub fn update_session_acp_cwd(
+        &self,
+        session_id: &str,
+        acp_cwd: Option<&str>,
+    ) -> AppResult<()> {
+        let conn = self.lock_conn();
+        conn.execute(
+            "UPDATE sessions SET acp_cwd = ?1 WHERE id = ?2",
+            params![acp_cwd, session_id],
+        )
+        .map_err(|e| AppError::Database(format!("Failed to update session acp_cwd: {}", e)))?;
+        Ok(())
+    }
+
     pub fn update_session_model(
         &self,
         session_id: &str,
         model_id: &str,
     ) -> AppResult<()> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|e| AppError::Database(format!("Database lock poisoned: {}", e)))?;
+        let conn = self.lock_conn();
         conn.execute(
             "UPDATE sessions SET model = ?1 WHERE id = ?2",
             params![model_id, session_id],
@@ -367,15 +1120,13 @@ impl Database {
     }
 
     pub fn load_sessions(&self) -> AppResult<Vec<Session>> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|e| AppError::Database(format!("Database lock poisoned: {}", e)))?;
+        let conn = self.lock_conn();
         let mut stmt = conn
             .prepare(
                 "SELECT id, name, provider, status, worktree_path,
                         branch_name, project_path, is_local, created_at, updated_at,
-                        acp_session_id, model, config_options, error_code, error_message
+                        acp_session_id, model, config_options, error_code, error_message,
+                        pinned, system_prompt, ui_color, acp_cwd
                  FROM sessions ORDER BY created_at DESC",
             )
             .map_err(|e| AppError::Database(format!("Failed to prepare query: {}", e)))?;
@@ -396,7 +1147,7 @@ impl Database {
                     .unwrap_or_default();
 
                 let error = match (error_code, error_message) {
-                    (Some(code), Some(message)) => Some(SessionError { code, message }),
+                    (Some(code), Some(message)) => Some(SessionError::new(code, message)),
                     _ => None,
                 };
 
@@ -418,6 +1169,7 @@ impl Database {
                             .map(|dt| dt.with_timezone(&chrono::Utc))
                     }),
                     acp_session_id: row.get(10)?,
+                    acp_cwd: row.get(18)?,
                     model: model_str,
                     available_models: vec![],
                     mode: None,
@@ -426,6 +1178,9 @@ impl Database {
                     plan_entries: vec![],
                     config_options,
                     error,
+                    pinned: row.get::<_, i32>(15)? != 0,
+                    system_prompt: row.get(16)?,
+                    ui_color: row.get(17)?,
                 })
             })
             .map_err(|e| AppError::Database(format!("Failed to query sessions: {}", e)))?;
@@ -441,20 +1196,14 @@ impl Database {
     }
 
     pub fn delete_session(&self, session_id: &str) -> AppResult<()> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|e| AppError::Database(format!("Database lock poisoned: {}", e)))?;
+        let conn = self.lock_conn();
         conn.execute("DELETE FROM sessions WHERE id = ?1", params![session_id])
             .map_err(|e| AppError::Database(format!("Failed to delete session: {}", e)))?;
         Ok(())
     }
 
     pub fn update_session_updated_at(&self, session_id: &str) -> AppResult<()> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|e| AppError::Database(format!("Database lock poisoned: {}", e)))?;
+        let conn = self.lock_conn();
         let now = chrono::Utc::now().to_rfc3339();
         conn.execute(
             "UPDATE sessions SET updated_at = ?1 WHERE id = ?2",
@@ -466,11 +1215,18 @@ impl Database {
 
     // ── Message operations ──
 
-    pub fn save_message(&self, message: &ChatMessage) -> AppResult<()> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|e| AppError::Database(format!("Database lock poisoned: {}", e)))?;
+    /// Save a message, applying `limit` to its content first when given
+    pub fn save_message(
+        &self,
+        message: &ChatMessage,
+        limit: Option<&MessageSizeLimit>,
+    ) -> AppResult<()> {
+        let content = match limit {
+            Some(limit) => self.limit_message_content(message, limit)?,
+            None => Cow::Borrowed(message.content.as_str()),
+        };
+
+        let conn = self.lock_conn();
 
         let tool_use_json = message
             .tool_use
@@ -487,26 +1243,56 @@ impl Database {
             .as_ref()
             .map(|p| serde_json::to_string(p).unwrap_or_default());
 
+        let meta_json = message
+            .meta
+            .as_ref()
+            .map(|m| serde_json::to_string(m).unwrap_or_default());
+
+        // Upserted so a message saved several times while streaming keeps its row:
+        // a replace would cascade into its bookmarks. New meta entries are merged
+        // into the stored ones so every tool call's meta is kept.
         conn.execute(
-            "INSERT OR REPLACE INTO messages
+            "INSERT INTO messages
              (id, session_id, role, content, content_type, tool_use,
-              tool_calls, parts, timestamp, is_streaming)
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+              tool_calls, parts, timestamp, is_streaming, meta)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)
+             ON CONFLICT(id) DO UPDATE SET
+              session_id = excluded.session_id,
+              role = excluded.role,
+              content = excluded.content,
+              content_type = excluded.content_type,
+              tool_use = excluded.tool_use,
+              tool_calls = excluded.tool_calls,
+              parts = excluded.parts,
+              timestamp = excluded.timestamp,
+              is_streaming = excluded.is_streaming,
+              meta = CASE WHEN excluded.meta IS NULL THEN messages.meta
+                          ELSE json_patch(COALESCE(messages.meta, '{}'), excluded.meta)
+                     END",
             params![
                 message.id,
                 message.session_id,
                 message_role_to_str(&message.role),
-                message.content,
+                content,
                 content_type_to_str(&message.content_type),
                 tool_use_json,
                 tool_calls_json,
                 parts_json,
                 message.timestamp.to_rfc3339(),
                 message.is_streaming as i32,
+                meta_json,
             ],
         )
         .map_err(|e| AppError::Database(format!("Failed to save message: {}", e)))?;
 
+        // The first save fixes the message's `seq` to its rowid, which SQLite
+        // assigns above every existing row and keeps on later updates
+        conn.execute(
+            "UPDATE messages SET seq = rowid WHERE id = ?1 AND seq IS NULL",
+            params![message.id],
+        )
+        .map_err(|e| AppError::Database(format!("Failed to assign message seq: {}", e)))?;
+
         // Update session's updated_at timestamp
         let now = chrono::Utc::now().to_rfc3339();
         conn.execute(
@@ -519,17 +1305,14 @@ impl Database {
     }
 
     pub fn get_messages(&self, session_id: &str) -> AppResult<Vec<ChatMessage>> {
-        let conn = self
-            .conn
-            .lock()
-            .map_err(|e| AppError::Database(format!("Database lock poisoned: {}", e)))?;
+        let conn = self.lock_conn();
         let mut stmt = conn
             .prepare(
                 "SELECT id, session_id, role, content, content_type,
                         tool_use, tool_calls, parts, timestamp, is_streaming
                  FROM messages
                  WHERE session_id = ?1
-                 ORDER BY timestamp ASC",
+                 ORDER BY timestamp ASC, seq ASC",
             )
             .map_err(|e| AppError::Database(format!("Failed to prepare query: {}", e)))?;
 
@@ -563,6 +1346,7 @@ impl Database {
                     // Always set is_streaming to false for loaded messages
                     // because they are historical and no longer actively streaming
                     is_streaming: false,
+                    meta: None,
                 })
             })
             .map_err(|e| AppError::Database(format!("Failed to query messages: {}", e)))?;
@@ -575,10 +1359,365 @@ impl Database {
         }
         Ok(result)
     }
+
+    pub fn save_turn_timing(&self, session_id: &str, timing: &TurnTiming) -> AppResult<()> {
+        let conn = self.lock_conn();
+        conn.execute(
+            "INSERT INTO turn_timings (session_id, started_at, ended_at, duration_ms)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![
+                session_id,
+                timing.started_at.to_rfc3339(),
+                timing.ended_at.to_rfc3339(),
+                timing.duration_ms as i64,
+            ],
+        )
+        .map_err(|e| AppError::Database(format!("Failed to save turn timing: {}", e)))?;
+        Ok(())
+    }
+
+    pub fn get_turn_timings(&self, session_id: &str) -> AppResult<Vec<TurnTiming>> {
+        let conn = self.lock_conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT started_at, ended_at, duration_ms FROM turn_timings
+                 WHERE session_id = ?1 ORDER BY started_at ASC, id ASC",
+            )
+            .map_err(|e| AppError::Database(format!("Failed to prepare query: {}", e)))?;
+
+        let parse = |s: String| {
+            chrono::DateTime::parse_from_rfc3339(&s)
+                .unwrap_or_else(|_| chrono::Utc::now().into())
+                .with_timezone(&chrono::Utc)
+        };
+        let timings = stmt
+            .query_map(params![session_id], |row| {
+                Ok(TurnTiming {
+                    started_at: parse(row.get(0)?),
+                    ended_at: parse(row.get(1)?),
+                    duration_ms: row.get::<_, i64>(2)?.max(0) as u64,
+                })
+            })
+            .map_err(|e| AppError::Database(format!("Failed to query turn timings: {}", e)))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(format!("Failed to read turn timing row: {}", e)))?;
+
+        Ok(timings)
+    }
+
+    // ── Activity feed ──
+
+    /// Append an event to the activity feed, pruning the oldest ones now and then
+    pub fn record_event(
+        &self,
+        session_id: &str,
+        kind: FeedEventKind,
+        summary: &str,
+    ) -> AppResult<()> {
+        let conn = self.lock_conn();
+        insert_feed_event(&conn, session_id, kind, summary)
+    }
+
+    /// Most recent activity feed events across all sessions, newest first,
+    /// optionally only those after `since`
+    pub fn get_events(
+        &self,
+        limit: usize,
+        since: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> AppResult<Vec<FeedEvent>> {
+        let conn = self.lock_conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, session_id, kind, summary, timestamp FROM events
+                 WHERE ?1 IS NULL OR timestamp > ?1
+                 ORDER BY timestamp DESC, id DESC LIMIT ?2",
+            )
+            .map_err(|e| AppError::Database(format!("Failed to prepare query: {}", e)))?;
+
+        let since = since.map(|t| t.to_rfc3339_opts(chrono::SecondsFormat::Micros, true));
+        let events = stmt
+            .query_map(params![since, limit as i64], |row| {
+                let kind: String = row.get(2)?;
+                let timestamp: String = row.get(4)?;
+                Ok(FeedEvent {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    kind: str_to_feed_event_kind(&kind),
+                    summary: row.get(3)?,
+                    timestamp: chrono::DateTime::parse_from_rfc3339(&timestamp)
+                        .unwrap_or_else(|_| chrono::Utc::now().into())
+                        .with_timezone(&chrono::Utc),
+                })
+            })
+            .map_err(|e| AppError::Database(format!("Failed to query events: {}", e)))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(format!("Failed to read event row: {}", e)))?;
+
+        Ok(events)
+    }
+
+    pub fn add_bookmark(
+        &self,
+        session_id: &str,
+        message_id: &str,
+        note: Option<&str>,
+    ) -> AppResult<MessageBookmark> {
+        let conn = self.lock_conn();
+
+        let exists: bool = conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM messages WHERE id = ?1 AND session_id = ?2)",
+                params![message_id, session_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| AppError::Database(format!("Failed to look up message: {}", e)))?;
+        if !exists {
+            return Err(AppError::NotFound(format!(
+                "Message '{}' not found in session '{}'",
+                message_id, session_id
+            )));
+        }
+
+        let bookmark = MessageBookmark {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id: session_id.to_string(),
+            message_id: message_id.to_string(),
+            note: note.map(str::trim).filter(|n| !n.is_empty()).map(str::to_string),
+            created_at: chrono::Utc::now(),
+        };
+        conn.execute(
+            "INSERT INTO session_bookmarks (id, session_id, message_id, note, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                bookmark.id,
+                bookmark.session_id,
+                bookmark.message_id,
+                bookmark.note,
+                bookmark.created_at.to_rfc3339(),
+            ],
+        )
+        .map_err(|e| AppError::Database(format!("Failed to save bookmark: {}", e)))?;
+
+        Ok(bookmark)
+    }
+
+    pub fn list_bookmarks(&self, session_id: &str) -> AppResult<Vec<MessageBookmark>> {
+        let conn = self.lock_conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, session_id, message_id, note, created_at FROM session_bookmarks
+                 WHERE session_id = ?1 ORDER BY created_at ASC",
+            )
+            .map_err(|e| AppError::Database(format!("Failed to prepare query: {}", e)))?;
+
+        let bookmarks = stmt
+            .query_map(params![session_id], |row| {
+                let created_at_str: String = row.get(4)?;
+                Ok(MessageBookmark {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    message_id: row.get(2)?,
+                    note: row.get(3)?,
+                    created_at: chrono::DateTime::parse_from_rfc3339(&created_at_str)
+                        .unwrap_or_else(|_| chrono::Utc::now().into())
+                        .with_timezone(&chrono::Utc),
+                })
+            })
+            .map_err(|e| AppError::Database(format!("Failed to query bookmarks: {}", e)))?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| AppError::Database(format!("Failed to read bookmark row: {}", e)))?;
+
+        Ok(bookmarks)
+    }
+
+    pub fn remove_bookmark(&self, bookmark_id: &str) -> AppResult<()> {
+        let conn = self.lock_conn();
+        let removed = conn
+            .execute(
+                "DELETE FROM session_bookmarks WHERE id = ?1",
+                params![bookmark_id],
+            )
+            .map_err(|e| AppError::Database(format!("Failed to remove bookmark: {}", e)))?;
+        if removed == 0 {
+            return Err(AppError::NotFound(format!("Bookmark '{}' not found", bookmark_id)));
+        }
+        Ok(())
+    }
+
+    /// Row counts per table and the mean recorded turn duration
+    pub fn metrics(&self) -> AppResult<DatabaseMetrics> {
+        let conn = self.lock_conn();
+        let count = |table: &str| -> AppResult<u64> {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .map(|n| n.max(0) as u64)
+            .map_err(|e| AppError::Database(format!("Failed to count {}: {}", table, e)))
+        };
+
+        let average_turn_ms = conn
+            .query_row("SELECT AVG(duration_ms) FROM turn_timings", [], |row| {
+                row.get::<_, Option<f64>>(0)
+            })
+            .map_err(|e| AppError::Database(format!("Failed to average turn timings: {}", e)))?
+            .map(|avg| avg.max(0.0).round() as u64);
+
+        Ok(DatabaseMetrics {
+            session_rows: count("sessions")?,
+            message_rows: count("messages")?,
+            tool_call_detail_rows: count("tool_call_details")?,
+            turn_timing_rows: count("turn_timings")?,
+            average_turn_ms,
+        })
+    }
+
+    /// Insert or merge a streamed tool call. Updates only carry the fields that
+    /// changed, so missing values keep what was stored previously.
+    pub fn upsert_tool_call_detail(
+        &self,
+        session_id: &str,
+        message_id: &str,
+        tool_call: &ToolCallInfo,
+    ) -> AppResult<()> {
+        let conn = self.lock_conn();
+
+        let content_json = tool_call
+            .content
+            .as_ref()
+            .map(|c| serde_json::to_string(c).unwrap_or_default());
+        let raw_input_json = tool_call.raw_input.as_ref().map(|v| v.to_string());
+        let raw_output_json = tool_call.raw_output.as_ref().map(|v| v.to_string());
+        let title = (!tool_call.title.is_empty()).then_some(tool_call.title.as_str());
+
+        conn.execute(
+            "INSERT INTO tool_call_details
+             (session_id, tool_call_id, message_id, tool_name, status, title, kind,
+              content, raw_input, raw_output, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, COALESCE(?6, ''), ?7, ?8, ?9, ?10, ?11)
+             ON CONFLICT(session_id, tool_call_id) DO UPDATE SET
+                message_id = excluded.message_id,
+                tool_name = COALESCE(excluded.tool_name, tool_name),
+                status = excluded.status,
+                title = COALESCE(?6, title),
+                kind = COALESCE(excluded.kind, kind),
+                content = COALESCE(excluded.content, content),
+                raw_input = COALESCE(excluded.raw_input, raw_input),
+                raw_output = COALESCE(excluded.raw_output, raw_output),
+                updated_at = excluded.updated_at",
+            params![
+                session_id,
+                tool_call.tool_call_id,
+                message_id,
+                tool_call.tool_name,
+                tool_call.status,
+                title,
+                tool_call.kind,
+                content_json,
+                raw_input_json,
+                raw_output_json,
+                chrono::Utc::now().to_rfc3339(),
+            ],
+        )
+        .map_err(|e| AppError::Database(format!("Failed to save tool call detail: {}", e)))?;
+        Ok(())
+    }
+
+    pub fn get_tool_call_detail(
+        &self,
+        session_id: &str,
+        tool_call_id: &str,
+    ) -> AppResult<Option<ToolCallDetail>> {
+        let conn = self.lock_conn();
+        let mut stmt = conn
+            .prepare(
+                "SELECT session_id, tool_call_id, message_id, tool_name, status, title, kind,
+                        content, raw_input, raw_output, updated_at
+                 FROM tool_call_details
+                 WHERE session_id = ?1 AND tool_call_id = ?2",
+            )
+            .map_err(|e| AppError::Database(format!("Failed to prepare query: {}", e)))?;
+
+        let mut rows = stmt
+            .query_map(params![session_id, tool_call_id], |row| {
+                let content_str: Option<String> = row.get(7)?;
+                let raw_input_str: Option<String> = row.get(8)?;
+                let raw_output_str: Option<String> = row.get(9)?;
+                let updated_at_str: String = row.get(10)?;
+
+                Ok(ToolCallDetail {
+                    session_id: row.get(0)?,
+                    tool_call_id: row.get(1)?,
+                    message_id: row.get(2)?,
+                    tool_name: row.get(3)?,
+                    status: row.get(4)?,
+                    title: row.get(5)?,
+                    kind: row.get(6)?,
+                    content: content_str.and_then(|s| serde_json::from_str(&s).ok()),
+                    raw_input: raw_input_str.and_then(|s| serde_json::from_str(&s).ok()),
+                    raw_output: raw_output_str.and_then(|s| serde_json::from_str(&s).ok()),
+                    updated_at: chrono::DateTime::parse_from_rfc3339(&updated_at_str)
+                        .unwrap_or_else(|_| chrono::Utc::now().into())
+                        .with_timezone(&chrono::Utc),
+                })
+            })
+            .map_err(|e| AppError::Database(format!("Failed to query tool call detail: {}", e)))?;
+
+        rows.next()
+            .transpose()
+            .map_err(|e| AppError::Database(format!("Failed to read tool call detail row: {}", e)))
+    }
+}
+
+/// Insert an activity feed event on a connection that is already locked.
+/// Timestamps use a fixed-width format so they sort as text.
+fn insert_feed_event(
+    conn: &Connection,
+    session_id: &str,
+    kind: FeedEventKind,
+    summary: &str,
+) -> AppResult<()> {
+    conn.execute(
+        "INSERT INTO events (session_id, kind, summary, timestamp) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            session_id,
+            feed_event_kind_to_str(kind),
+            truncate_str(summary, MAX_FEED_SUMMARY_BYTES),
+            chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+        ],
+    )
+    .map_err(|e| AppError::Database(format!("Failed to record event: {}", e)))?;
+
+    let id = conn.last_insert_rowid();
+    if id % FEED_PRUNE_INTERVAL == 0 {
+        conn.execute(
+            "DELETE FROM events WHERE id <= ?1",
+            params![id - MAX_FEED_EVENTS],
+        )
+        .map_err(|e| AppError::Database(format!("Failed to prune events: {}", e)))?;
+    }
+    Ok(())
 }
 
 // ── Enum conversion helpers ──
 
+fn feed_event_kind_to_str(k: FeedEventKind) -> &'static str {
+    match k {
+        FeedEventKind::SessionCreated => "session_created",
+        FeedEventKind::Message => "message",
+        FeedEventKind::StatusChanged => "status_changed",
+        FeedEventKind::Merged => "merged",
+    }
+}
+
+fn str_to_feed_event_kind(s: &str) -> FeedEventKind {
+    match s {
+        "session_created" => FeedEventKind::SessionCreated,
+        "status_changed" => FeedEventKind::StatusChanged,
+        "merged" => FeedEventKind::Merged,
+        _ => FeedEventKind::Message,
+    }
+}
+
 fn provider_type_to_str(p: &ProviderType) -> String {
     p.as_id().to_string()
 }
@@ -629,6 +1768,7 @@ fn content_type_to_str(ct: &MessageContentType) -> &'static str {
         MessageContentType::ToolUse => "tool_use",
         MessageContentType::ToolResult => "tool_result",
         MessageContentType::Error => "error",
+        MessageContentType::Image => "image",
     }
 }
 
@@ -637,6 +1777,470 @@ fn str_to_content_type(s: &str) -> MessageContentType {
         "tool_use" => MessageContentType::ToolUse,
         "tool_result" => MessageContentType::ToolResult,
         "error" => MessageContentType::Error,
+        "image" => MessageContentType::Image,
         _ => MessageContentType::Text,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_db() -> Database {
+        Database::in_memory(&AppError::Internal("test".to_string())).unwrap()
+    }
+
+    fn test_session(id: &str) -> Session {
+        Session {
+            id: id.to_string(),
+            name: "Test session".to_string(),
+            provider: ProviderType::Claude,
+            status: SessionStatus::Creating,
+            worktree_path: "/tmp/worktree".to_string(),
+            branch_name: format!("forkestra/session-{}", id),
+            created_at: chrono::Utc::now(),
+            updated_at: None,
+            project_path: "/tmp/project".to_string(),
+            is_local: false,
+            acp_session_id: None,
+            acp_cwd: None,
+            model: None,
+            available_models: vec![],
+            mode: None,
+            available_modes: vec![],
+            available_commands: vec![],
+            plan_entries: vec![],
+            config_options: vec![],
+            error: None,
+            pinned: false,
+            system_prompt: None,
+            ui_color: None,
+        }
+    }
+
+    #[test]
+    fn resaving_a_session_keeps_its_feed_events() {
+        let db = test_db();
+        let mut session = test_session("s1");
+        db.save_session(&session).unwrap();
+        db.record_event("s1", FeedEventKind::SessionCreated, "Created Test session")
+            .unwrap();
+
+        session.status = SessionStatus::Active;
+        session.acp_session_id = Some("acp-1".to_string());
+        db.save_session(&session).unwrap();
+
+        let events = db.get_events(10, None).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, FeedEventKind::SessionCreated);
+
+        let saved = db.load_sessions().unwrap();
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].status, SessionStatus::Active);
+        assert_eq!(saved[0].acp_session_id.as_deref(), Some("acp-1"));
+    }
+
+    fn corrupt_archives(dir: &Path) -> usize {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .filter(|e| {
+                e.file_name()
+                    .to_string_lossy()
+                    .starts_with("forkestra-corrupt-")
+            })
+            .count()
+    }
+
+    #[test]
+    fn garbage_file_is_recovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("forkestra.db");
+        std::fs::write(&path, "this is not a sqlite database ".repeat(200)).unwrap();
+
+        let db = Database::open_at(path).unwrap();
+        assert!(matches!(db.status(), DatabaseStatus::Recovered { .. }));
+        assert_eq!(corrupt_archives(dir.path()), 1);
+        db.save_session(&test_session("s1")).unwrap();
+    }
+
+    #[test]
+    fn locked_file_is_not_recovered() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("forkestra.db");
+        let holder = Connection::open(&path).unwrap();
+        holder
+            .execute_batch(
+                "PRAGMA locking_mode = EXCLUSIVE;
+                 CREATE TABLE held (x);
+                 BEGIN EXCLUSIVE;
+                 INSERT INTO held VALUES (1);",
+            )
+            .unwrap();
+
+        assert!(Database::open_at(path.clone()).is_err());
+        assert_eq!(corrupt_archives(dir.path()), 0);
+
+        holder.execute_batch("COMMIT;").unwrap();
+        drop(holder);
+        let db = Database::open_at(path).unwrap();
+        assert!(matches!(db.status(), DatabaseStatus::Ok));
+    }
+
+    #[test]
+    fn resaving_a_message_keeps_its_bookmarks_seq_and_meta() {
+        let db = test_db();
+        db.save_session(&test_session("s1")).unwrap();
+
+        let mut message = ChatMessage::user("s1", "first draft");
+        message.is_streaming = true;
+        message.meta = Some(serde_json::json!({ "call-1": { "a": 1 } }));
+        db.save_message(&message, None).unwrap();
+        let bookmark = db.add_bookmark("s1", &message.id, Some("keep")).unwrap();
+
+        message.content = "final text".to_string();
+        message.is_streaming = false;
+        message.meta = Some(serde_json::json!({ "call-2": { "b": 2 } }));
+        db.save_message(&message, None).unwrap();
+        let next = ChatMessage::user("s1", "next");
+        db.save_message(&next, None).unwrap();
+
+        let bookmarks = db.list_bookmarks("s1").unwrap();
+        assert_eq!(bookmarks.len(), 1);
+        assert_eq!(bookmarks[0].id, bookmark.id);
+
+        let messages = db.get_messages("s1").unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].id, message.id);
+        assert_eq!(messages[0].content, "final text");
+        assert!(!messages[0].is_streaming);
+
+        let meta = db.get_message_meta(&message.id).unwrap().unwrap();
+        assert_eq!(meta["call-1"]["a"], 1);
+        assert_eq!(meta["call-2"]["b"], 2);
+    }
+
+    #[test]
+    fn messages_with_equal_timestamps_keep_insertion_order() {
+        let db = test_db();
+        db.save_session(&test_session("s1")).unwrap();
+
+        let timestamp = chrono::Utc::now();
+        let mut saved = Vec::new();
+        // Ids sort differently from the insertion order
+        for id in ["c", "a", "d", "b"] {
+            let mut message = ChatMessage::user("s1", id);
+            message.id = id.to_string();
+            message.timestamp = timestamp;
+            db.save_message(&message, None).unwrap();
+            saved.push(message);
+        }
+        // Saving a message again does not move it
+        saved[0].content = "c, edited".to_string();
+        db.save_message(&saved[0], None).unwrap();
+
+        let ids: Vec<String> = db
+            .get_messages("s1")
+            .unwrap()
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        assert_eq!(ids, ["c", "a", "d", "b"]);
+    }
+
+    #[test]
+    fn large_tool_call_input_round_trips_intact() {
+        let db = test_db();
+        db.save_session(&test_session("s1")).unwrap();
+
+        let raw_input = serde_json::json!({
+            "file_path": "src/big.rs",
+            "content": "fn main() {}\n".repeat(100_000),
+            "edits": (0..500).map(|i| serde_json::json!({ "line": i })).collect::<Vec<_>>(),
+        });
+        let mut tool_call = ToolCallInfo {
+            tool_call_id: "call-1".to_string(),
+            tool_name: Some("Write".to_string()),
+            status: "pending".to_string(),
+            title: "Write src/big.rs".to_string(),
+            content: None,
+            kind: Some("edit".to_string()),
+            locations: None,
+            raw_input: Some(raw_input.clone()),
+            raw_output: None,
+            meta: None,
+        };
+        db.upsert_tool_call_detail("s1", "m1", &tool_call).unwrap();
+
+        // The completion update only carries the fields that changed
+        tool_call.status = "completed".to_string();
+        tool_call.title = String::new();
+        tool_call.raw_input = None;
+        tool_call.raw_output = Some(serde_json::json!({ "ok": true }));
+        db.upsert_tool_call_detail("s1", "m1", &tool_call).unwrap();
+
+        let detail = db.get_tool_call_detail("s1", "call-1").unwrap().unwrap();
+        assert_eq!(detail.raw_input, Some(raw_input));
+        assert_eq!(detail.raw_output, Some(serde_json::json!({ "ok": true })));
+        assert_eq!(detail.status, "completed");
+        assert_eq!(detail.title, "Write src/big.rs");
+        assert_eq!(detail.message_id, "m1");
+        assert!(db.get_tool_call_detail("s1", "call-2").unwrap().is_none());
+    }
+
+    #[test]
+    fn rotation_archives_the_database_and_starts_fresh() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open_at(dir.path().join("forkestra.db")).unwrap();
+        db.save_session(&test_session("s1")).unwrap();
+
+        let archive_path = db.rotate().unwrap();
+        assert!(archive_path.is_file());
+        assert!(db.load_sessions().unwrap().is_empty());
+        db.save_session(&test_session("s2")).unwrap();
+
+        let archived = Database::open_at(archive_path).unwrap();
+        let ids: Vec<String> = archived
+            .load_sessions()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.id)
+            .collect();
+        assert_eq!(ids, ["s1"]);
+    }
+
+    #[test]
+    fn failed_rotation_reopens_the_original_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open_at(dir.path().join("forkestra.db")).unwrap();
+        db.save_session(&test_session("s1")).unwrap();
+
+        let unreachable = dir.path().join("missing").join("archive.db");
+        assert!(db.rotate_to(&unreachable).is_err());
+
+        // Still the file database with its schema and rows, not the placeholder
+        assert_eq!(db.load_sessions().unwrap().len(), 1);
+        db.save_session(&test_session("s2")).unwrap();
+        drop(db);
+        let reopened = Database::open_at(dir.path().join("forkestra.db")).unwrap();
+        assert_eq!(reopened.load_sessions().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn recovery_copies_every_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let old_path = dir.path().join("old.db");
+        let old = Database::open_at(old_path.clone()).unwrap();
+        old.save_session(&test_session("s1")).unwrap();
+        let message = ChatMessage::user("s1", "hello");
+        old.save_message(&message, None).unwrap();
+        old.add_bookmark("s1", &message.id, None).unwrap();
+        old.record_event("s1", FeedEventKind::Message, "hello")
+            .unwrap();
+        let now = chrono::Utc::now();
+        old.save_turn_timing(
+            "s1",
+            &TurnTiming {
+                started_at: now,
+                ended_at: now,
+                duration_ms: 0,
+            },
+        )
+        .unwrap();
+        let tool_call = ToolCallInfo {
+            tool_call_id: "call-1".to_string(),
+            tool_name: None,
+            status: "completed".to_string(),
+            title: "Read".to_string(),
+            content: None,
+            kind: None,
+            locations: None,
+            raw_input: None,
+            raw_output: None,
+            meta: None,
+        };
+        old.upsert_tool_call_detail("s1", &message.id, &tool_call)
+            .unwrap();
+        drop(old);
+
+        let conn = Database::open(&dir.path().join("new.db"))
+            .map_err(AppError::from)
+            .unwrap();
+        let tables: Vec<String> = conn
+            .prepare(
+                "SELECT name FROM sqlite_master
+                 WHERE type = 'table' AND name NOT LIKE 'sqlite_%'",
+            )
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        for table in &tables {
+            assert!(
+                RECOVERABLE_TABLES.contains(&table.as_str()),
+                "{} is not recovered",
+                table
+            );
+        }
+
+        conn.execute(
+            "ATTACH DATABASE ?1 AS corrupt",
+            params![old_path.to_string_lossy()],
+        )
+        .unwrap();
+        for table in RECOVERABLE_TABLES {
+            assert_eq!(
+                Database::copy_recoverable_rows(&conn, table).unwrap(),
+                1,
+                "{}",
+                table
+            );
+        }
+    }
+
+    #[test]
+    fn turn_timings_feed_the_metrics() {
+        let db = test_db();
+        db.save_session(&test_session("s1")).unwrap();
+        assert_eq!(db.metrics().unwrap().average_turn_ms, None);
+
+        let start = chrono::Utc::now();
+        for (offset, duration_ms) in [(10, 300), (0, 100)] {
+            let started_at = start + chrono::Duration::seconds(offset);
+            let timing = TurnTiming {
+                started_at,
+                ended_at: started_at + chrono::Duration::milliseconds(duration_ms),
+                duration_ms: duration_ms as u64,
+            };
+            db.save_turn_timing("s1", &timing).unwrap();
+        }
+
+        let timings = db.get_turn_timings("s1").unwrap();
+        let durations: Vec<u64> = timings.iter().map(|t| t.duration_ms).collect();
+        assert_eq!(durations, [100, 300]);
+
+        let metrics = db.metrics().unwrap();
+        assert_eq!(metrics.session_rows, 1);
+        assert_eq!(metrics.turn_timing_rows, 2);
+        assert_eq!(metrics.average_turn_ms, Some(200));
+    }
+
+    #[test]
+    fn bookmarks_need_a_message_of_the_session() {
+        let db = test_db();
+        db.save_session(&test_session("s1")).unwrap();
+        db.save_session(&test_session("s2")).unwrap();
+        let message = ChatMessage::user("s1", "hello");
+        db.save_message(&message, None).unwrap();
+
+        let missing = db.add_bookmark("s2", &message.id, None);
+        assert!(matches!(missing, Err(AppError::NotFound(_))));
+
+        let bookmark = db.add_bookmark("s1", &message.id, Some("  ")).unwrap();
+        assert_eq!(bookmark.note, None);
+        assert_eq!(db.list_bookmarks("s1").unwrap().len(), 1);
+
+        db.remove_bookmark(&bookmark.id).unwrap();
+        assert!(db.list_bookmarks("s1").unwrap().is_empty());
+        let removed_again = db.remove_bookmark(&bookmark.id);
+        assert!(matches!(removed_again, Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn checkpoint_truncates_the_wal() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Database::open_at(dir.path().join("forkestra.db")).unwrap();
+        for i in 0..20 {
+            db.save_session(&test_session(&format!("s{}", i))).unwrap();
+        }
+        assert!(db.wal_size() > 0);
+
+        let result = db.checkpoint().unwrap();
+        assert!(!result.busy);
+        assert_eq!(db.wal_size(), 0);
+        assert!(db.checkpoint_if_idle().unwrap().is_none());
+        assert_eq!(db.load_sessions().unwrap().len(), 20);
+    }
+
+    #[test]
+    fn status_changes_are_recorded_once() {
+        let db = test_db();
+        db.save_session(&test_session("s1")).unwrap();
+
+        db.update_session_status("s1", &SessionStatus::Active)
+            .unwrap();
+        db.update_session_status("s1", &SessionStatus::Active)
+            .unwrap();
+
+        let events = db.get_events(10, None).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].kind, FeedEventKind::StatusChanged);
+        assert_eq!(events[0].session_id, "s1");
+    }
+
+    #[test]
+    fn events_are_newest_first_and_filtered_by_since() {
+        let db = test_db();
+        db.save_session(&test_session("s1")).unwrap();
+        db.record_event("s1", FeedEventKind::SessionCreated, "first")
+            .unwrap();
+        let first = db.get_events(1, None).unwrap().remove(0);
+        db.record_event("s1", FeedEventKind::Merged, &"x".repeat(500))
+            .unwrap();
+
+        let events = db.get_events(10, None).unwrap();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].kind, FeedEventKind::Merged);
+        assert_eq!(events[0].summary.len(), MAX_FEED_SUMMARY_BYTES);
+
+        let since = db.get_events(10, Some(first.timestamp)).unwrap();
+        assert_eq!(since.len(), 1);
+        assert_eq!(since[0].kind, FeedEventKind::Merged);
+    }
+
+    #[test]
+    fn old_events_are_pruned() {
+        let db = test_db();
+        db.save_session(&test_session("s1")).unwrap();
+        let total = MAX_FEED_EVENTS + FEED_PRUNE_INTERVAL;
+        for i in 0..total {
+            db.record_event("s1", FeedEventKind::SessionCreated, &i.to_string())
+                .unwrap();
+        }
+
+        let events = db.get_events(total as usize, None).unwrap();
+        assert_eq!(events.len() as i64, MAX_FEED_EVENTS);
+        assert_eq!(events[0].summary, (total - 1).to_string());
+    }
+
+    #[test]
+    fn checkpoint_recovers_a_lock_poisoned_by_a_panic() {
+        let dir = tempfile::tempdir().unwrap();
+        let db = Arc::new(Database::open_at(dir.path().join("forkestra.db")).unwrap());
+        db.save_session(&test_session("s1")).unwrap();
+
+        let poisoner = db.clone();
+        let _ = std::thread::spawn(move || {
+            let conn = poisoner.lock_conn();
+            conn.execute_batch("BEGIN").unwrap();
+            panic!("panic while holding the connection");
+        })
+        .join();
+        assert!(db.conn.is_poisoned());
+
+        // Run on another thread so a deadlock fails the test instead of hanging it
+        let (tx, rx) = std::sync::mpsc::channel();
+        let checkpointing = db.clone();
+        std::thread::spawn(move || {
+            let _ = tx.send(checkpointing.checkpoint_if_idle().map(|c| c.is_some()));
+        });
+        let checkpointed = rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("checkpoint_if_idle deadlocked on a poisoned lock");
+        assert!(checkpointed.unwrap());
+        assert!(!db.conn.is_poisoned());
+        assert_eq!(db.wal_size(), 0);
+        assert_eq!(db.load_sessions().unwrap().len(), 1);
+    }
+}
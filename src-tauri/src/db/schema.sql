@@ -10,7 +10,11 @@ CREATE TABLE IF NOT EXISTS sessions (
     created_at TEXT NOT NULL,
     updated_at TEXT,
     acp_session_id TEXT,
-    config_options TEXT DEFAULT '[]'
+    config_options TEXT DEFAULT '[]',
+    pinned INTEGER NOT NULL DEFAULT 0,
+    system_prompt TEXT,
+    ui_color TEXT,
+    acp_cwd TEXT
 );
 
 CREATE TABLE IF NOT EXISTS messages (
@@ -24,9 +28,59 @@ CREATE TABLE IF NOT EXISTS messages (
     parts TEXT,
     timestamp TEXT NOT NULL,
     is_streaming INTEGER NOT NULL DEFAULT 0,
+    seq INTEGER,
+    meta TEXT,
     FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
 );
 
+CREATE TABLE IF NOT EXISTS tool_call_details (
+    session_id TEXT NOT NULL,
+    tool_call_id TEXT NOT NULL,
+    message_id TEXT NOT NULL,
+    tool_name TEXT,
+    status TEXT NOT NULL,
+    title TEXT NOT NULL,
+    kind TEXT,
+    content TEXT,
+    raw_input TEXT,
+    raw_output TEXT,
+    updated_at TEXT NOT NULL,
+    PRIMARY KEY (session_id, tool_call_id),
+    FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS turn_timings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    started_at TEXT NOT NULL,
+    ended_at TEXT NOT NULL,
+    duration_ms INTEGER NOT NULL,
+    FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS session_bookmarks (
+    id TEXT PRIMARY KEY,
+    session_id TEXT NOT NULL,
+    message_id TEXT NOT NULL,
+    note TEXT,
+    created_at TEXT NOT NULL,
+    FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE,
+    FOREIGN KEY (message_id) REFERENCES messages(id) ON DELETE CASCADE
+);
+
+CREATE TABLE IF NOT EXISTS events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    session_id TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    summary TEXT NOT NULL,
+    timestamp TEXT NOT NULL,
+    FOREIGN KEY (session_id) REFERENCES sessions(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_events_timestamp ON events(timestamp);
+CREATE INDEX IF NOT EXISTS idx_session_bookmarks_session_id ON session_bookmarks(session_id);
+CREATE INDEX IF NOT EXISTS idx_turn_timings_session_id ON turn_timings(session_id);
 CREATE INDEX IF NOT EXISTS idx_messages_session_id ON messages(session_id);
 CREATE INDEX IF NOT EXISTS idx_messages_timestamp ON messages(timestamp);
+CREATE INDEX IF NOT EXISTS idx_messages_session_timestamp ON messages(session_id, timestamp);
 CREATE INDEX IF NOT EXISTS idx_sessions_created_at ON sessions(created_at);
@@ -61,16 +61,10 @@ impl AppError {
                     let after_code = &full[code_start + 6..];
                     if let Some(code_end) = after_code.find(',') {
                         let nested_code = after_code[..code_end].trim();
-                        return SessionError {
-                            code: nested_code.to_string(),
-                            message: nested_message.to_string(),
-                        };
+                        return SessionError::new(nested_code, nested_message);
                     }
                 }
-                return SessionError {
-                    code: code.to_string(),
-                    message: nested_message.to_string(),
-                };
+                return SessionError::new(code, nested_message);
             }
         }
 
@@ -87,10 +81,7 @@ impl AppError {
             | AppError::Skill(m) => m.clone(),
         };
 
-        SessionError {
-            code: code.to_string(),
-            message,
-        }
+        SessionError::new(code, message)
     }
 }
 
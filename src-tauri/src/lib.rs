@@ -4,12 +4,66 @@ mod error;
 mod managers;
 mod models;
 mod providers;
+mod utils;
 
 use std::sync::Arc;
+use std::time::Duration;
 
 use managers::{McpManager, SessionManager, SettingsManager, SkillsManager, TerminalManager};
+use models::{DEFAULT_QUICK_NEW_SESSION_SHORTCUT, DEFAULT_WAL_CHECKPOINT_INTERVAL_SECS};
 use tauri::menu::{MenuBuilder, MenuItemBuilder, SubmenuBuilder};
-use tauri::{Emitter, Manager};
+use tauri::{AppHandle, Emitter, Manager};
+use tauri_plugin_dialog::{DialogExt, MessageDialogKind};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+
+/// Tell the user that part of the app's data could not be opened. The app keeps
+/// running on in-memory fallbacks, so the dialog doesn't block startup.
+fn show_startup_error(app: &AppHandle, message: String) {
+    app.dialog()
+        .message(message)
+        .title("Forkestra")
+        .kind(MessageDialogKind::Error)
+        .show(|_| {});
+}
+
+/// Register the global "new session" hotkey. Failures (invalid combo, or the
+/// combo already taken by another app) are logged and otherwise ignored.
+fn register_quick_new_session_shortcut(app: &AppHandle, settings_manager: &SettingsManager) {
+    let combo = settings_manager
+        .get_settings()
+        .general
+        .and_then(|g| g.quick_new_session_shortcut)
+        .unwrap_or_else(|| DEFAULT_QUICK_NEW_SESSION_SHORTCUT.to_string());
+    let combo = combo.trim();
+    if combo.is_empty() {
+        return;
+    }
+
+    let shortcut: Shortcut = match combo.parse() {
+        Ok(shortcut) => shortcut,
+        Err(e) => {
+            eprintln!("[Shortcut] Invalid quick new session shortcut '{}': {}", combo, e);
+            return;
+        }
+    };
+
+    let result = app
+        .global_shortcut()
+        .on_shortcut(shortcut, |app, _shortcut, event| {
+            if event.state != ShortcutState::Pressed {
+                return;
+            }
+            if let Some(window) = app.get_webview_window("main") {
+                let _ = window.unminimize();
+                let _ = window.show();
+                let _ = window.set_focus();
+            }
+            let _ = app.emit("quick-new-session", ());
+        });
+    if let Err(e) = result {
+        eprintln!("[Shortcut] Failed to register '{}': {}", combo, e);
+    }
+}
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -129,17 +183,75 @@ pub fn run() {
             });
 
             // Initialize settings manager first
-            let settings_manager = Arc::new(
-                SettingsManager::new(app.handle())
-                    .expect("Failed to initialize settings manager"),
-            );
+            let settings_manager = match SettingsManager::new(app.handle()) {
+                Ok(settings_manager) => settings_manager,
+                Err(e) => {
+                    show_startup_error(
+                        app.handle(),
+                        format!(
+                            "Forkestra could not open its settings in ~/.forkestra ({}).\n\n\
+                             Default settings are used and changes made during this run \
+                             will not be saved.",
+                            e
+                        ),
+                    );
+                    SettingsManager::in_memory(&e)
+                }
+            };
+            let settings_manager = Arc::new(settings_manager);
             app.manage(settings_manager.clone());
 
+            register_quick_new_session_shortcut(app.handle(), &settings_manager);
+
             // Initialize database
-            let database = Arc::new(
-                db::Database::new(app.handle())
-                    .expect("Failed to initialize database"),
-            );
+            let database = match db::Database::new(app.handle()) {
+                Ok(database) => database,
+                Err(e) => {
+                    eprintln!(
+                        "[Database] Failed to initialize database, falling back to in-memory: {}",
+                        e
+                    );
+                    match db::Database::in_memory(&e) {
+                        Ok(database) => {
+                            show_startup_error(
+                                app.handle(),
+                                format!(
+                                    "Forkestra could not open its session database ({}).\n\n\
+                                     Sessions from earlier runs are unavailable and new ones \
+                                     will not be saved. Check that the app data directory is \
+                                     writable and the disk is not full.",
+                                    e
+                                ),
+                            );
+                            database
+                        }
+                        Err(fatal) => {
+                            app.dialog()
+                                .message(format!(
+                                    "Forkestra cannot start: no database is available.\n\n{}\n{}",
+                                    e, fatal
+                                ))
+                                .title("Forkestra")
+                                .kind(MessageDialogKind::Error)
+                                .blocking_show();
+                            std::process::exit(1);
+                        }
+                    }
+                }
+            };
+            let database = Arc::new(database);
+
+            let checkpoint_interval = settings_manager
+                .get_settings()
+                .advanced
+                .and_then(|a| a.wal_checkpoint_interval_secs)
+                .unwrap_or(DEFAULT_WAL_CHECKPOINT_INTERVAL_SECS);
+            if checkpoint_interval > 0 {
+                db::Database::spawn_wal_checkpoints(
+                    database.clone(),
+                    Duration::from_secs(checkpoint_interval),
+                );
+            }
 
             // Initialize MCP manager
             let mcp_manager = Arc::new(McpManager::new(settings_manager.clone()));
@@ -156,6 +268,15 @@ pub fn run() {
                 SessionManager::new(app.handle().clone(), settings_manager, database, mcp_manager, skills_manager);
             app.manage(session_manager);
 
+            // Reconnect the sessions that were active last time, if enabled
+            let handle = app.handle().clone();
+            tauri::async_runtime::spawn(async move {
+                handle
+                    .state::<SessionManager>()
+                    .auto_resume_sessions()
+                    .await;
+            });
+
             // Initialize terminal manager
             let terminal_manager = TerminalManager::new(app.handle().clone());
             app.manage(terminal_manager);
@@ -164,28 +285,86 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             commands::detect_providers,
+            commands::check_provider_auth,
+            commands::refresh_shell_path,
             commands::create_session,
             commands::list_sessions,
+            commands::reload_sessions,
+            commands::list_active_sessions,
+            commands::search_sessions,
+            commands::get_session_status_counts,
+            commands::get_session_timing,
+            commands::get_performance_metrics,
+            commands::list_projects,
+            commands::get_recent_projects,
+            commands::get_worktree_count,
+            commands::list_worktrees,
+            commands::cleanup_project_worktrees,
             commands::get_session,
+            commands::get_session_commands,
             commands::send_message,
+            commands::send_message_blocking,
+            commands::send_command,
             commands::terminate_session,
+            commands::forget_session,
+            commands::delete_session_branch,
+            commands::graduate_session,
             commands::resume_session,
+            commands::cancel_resume,
+            commands::is_session_resumable,
+            commands::attach_session,
+            commands::reload_session_settings,
+            commands::set_session_cwd,
             commands::send_interaction_response,
+            commands::clear_pending_permission,
+            commands::get_session_pending_requests,
+            commands::clear_pending_requests,
+            commands::get_session_process_info,
+            commands::get_session_activity_log,
             commands::merge_session,
+            commands::preview_session_merge,
+            commands::get_session_ahead_behind,
+            commands::verify_worktree,
             commands::list_branches,
+            commands::inspect_project,
+            commands::get_project_sessions_overview,
+            commands::discover_orphan_agent_sessions,
+            commands::import_agent_session,
             commands::rename_session,
+            commands::set_session_pinned,
+            commands::set_session_color,
             commands::get_session_messages,
+            commands::export_session_to_file,
+            commands::export_session_patch,
             commands::save_message,
+            commands::get_full_message_content,
+            commands::get_message_meta,
+            commands::get_tool_call_detail,
+            commands::get_session_image,
+            commands::add_bookmark,
+            commands::list_bookmarks,
+            commands::remove_bookmark,
+            commands::get_activity_feed,
+            commands::rotate_database,
+            commands::checkpoint_database,
+            commands::get_database_status,
             commands::set_session_model,
             commands::set_session_mode,
             commands::set_session_config_option,
+            commands::set_session_config_options,
             commands::cancel_generation,
+            commands::cancel_all_generation,
+            commands::get_active_tool_calls,
+            commands::cancel_tool_call,
             commands::git_sync,
             commands::git_pull,
             commands::git_push,
             commands::git_status,
             commands::git_scm_status,
             commands::git_file_diff,
+            commands::get_session_file_diff,
+            commands::blame_file,
+            commands::export_worktree_snapshot,
             commands::git_stage_file,
             commands::git_unstage_file,
             commands::git_stage_all,
@@ -207,15 +386,19 @@ pub fn run() {
             commands::get_settings,
             commands::get_settings_json,
             commands::get_settings_path,
+            commands::get_effective_settings,
+            commands::set_project_branch_prefix,
             commands::update_settings_json,
             commands::update_settings,
             commands::update_provider_settings,
             commands::get_ui_settings,
             commands::update_ui_settings,
             commands::list_terminals,
+            commands::list_all_terminals,
             commands::create_terminal,
             commands::close_terminal,
             commands::send_terminal_input,
+            commands::clear_terminal,
             commands::resize_terminal,
             commands::list_directory,
             commands::read_file,
@@ -244,6 +427,15 @@ pub fn run() {
             commands::remove_custom_provider,
             commands::update_custom_provider,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                if let Some(settings_manager) = app.try_state::<Arc<SettingsManager>>() {
+                    if let Err(e) = settings_manager.flush() {
+                        eprintln!("[SettingsManager] Failed to flush settings on exit: {}", e);
+                    }
+                }
+            }
+        });
 }
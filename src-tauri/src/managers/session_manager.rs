@@ -1,30 +1,603 @@
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-use chrono::Utc;
-use tauri::{AppHandle, Emitter};
+use agent_client_protocol::SessionConfigOption;
+use chrono::{DateTime, Utc};
+use futures::StreamExt;
+use serde::Serialize;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::{mpsc, RwLock};
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
 
-use crate::db::Database;
+use crate::db::{Database, MessageSizeLimit};
 use crate::error::{AppError, AppResult};
 use crate::managers::mcp_manager::McpManager;
 use crate::managers::settings_manager::SettingsManager;
 use crate::managers::skills_manager::SkillsManager;
 use crate::managers::worktree_manager::WorktreeManager;
 use crate::models::{
-    AvailableCommand, CreateSessionRequest, PlanEntry, PromptContent, ProviderType, Session,
-    SessionStatus, SessionStatusEvent, StreamChunk, builtin_definitions, ProviderDefinition,
+    ActiveSessionInfo, ActivityKind, ActivityLogEntry, AgentSessionInfo, AutoResumeProgressEvent,
+    AvailableCommand, ChatMessage, CommandResult, FeedEventKind, MessageSavedEvent, ModelInfo,
+    ProcessInfo, WorktreeInfo, WorktreeRepairReport, CreateSessionRequest, ExportFormat, FileDiff,
+    MessagePart, MessageRole, ModelChangedEvent, PendingRequest, PerformanceMetrics,
+    PermissionClearedEvent, PlanEntry, ProjectSummary, PromptContent, ProviderType, Session,
+    SessionError, SessionGitOverview, SessionProgressEvent, SessionProgressPhase, SessionStatus,
+    SessionTiming, SessionStatusEvent, StreamChunk, StreamChunkType, TurnCommittedEvent, TurnTiming,
+    builtin_definitions, ProviderDefinition, DEFAULT_MAX_MESSAGE_CONTENT_BYTES,
+    DEFAULT_STREAM_CHANNEL_CAPACITY,
 };
+use crate::providers::acp_client_sdk::AcpTimeouts;
 use crate::providers::{GenericAcpAdapter, ProviderAdapter};
+use crate::utils::truncate_str;
 
 /// Code-level switch: set to `true` to prepend skill contents into the first
 /// message of each ACP session. This is an experimental feature for internal use.
 const SKILLS_INJECT_ENABLED: bool = false;
 
+type SessionAdapter = Arc<tokio::sync::Mutex<Box<dyn ProviderAdapter>>>;
+
 struct SessionEntry {
     session: Session,
-    adapter: Option<Arc<tokio::sync::Mutex<Box<dyn ProviderAdapter>>>>,
+    adapter: Option<SessionAdapter>,
+}
+
+/// The adapter of a session whose agent process is still running, as is: no
+/// process is spawned and the stream forwarder keeps its channel. If the agent
+/// exited behind our back, the stale adapter is dropped, the session is paused
+/// so it can be resumed normally, and `None` is returned.
+async fn live_adapter(
+    sessions: &RwLock<HashMap<String, SessionEntry>>,
+    session_id: &str,
+) -> AppResult<Option<SessionAdapter>> {
+    let adapter = sessions
+        .read()
+        .await
+        .get(session_id)
+        .ok_or_else(|| AppError::NotFound(format!("Session '{}' not found", session_id)))?
+        .adapter
+        .clone()
+        .ok_or_else(|| {
+            AppError::InvalidOperation(format!(
+                "Session '{}' has no running agent process, resume it instead",
+                session_id
+            ))
+        })?;
+
+    if adapter.lock().await.is_process_alive() {
+        return Ok(Some(adapter));
+    }
+
+    if let Some(entry) = sessions.write().await.get_mut(session_id) {
+        entry.adapter = None;
+        entry.session.status = SessionStatus::Paused;
+    }
+    Ok(None)
+}
+
+/// Take a session out of memory and the database, stopping its agent. Nothing
+/// on disk is touched: the worktree and branch stay where they are.
+async fn forget_entry(
+    sessions: &RwLock<HashMap<String, SessionEntry>>,
+    db: &Database,
+    session_id: &str,
+) -> AppResult<Session> {
+    let entry = sessions
+        .write()
+        .await
+        .remove(session_id)
+        .ok_or_else(|| AppError::NotFound(format!("Session '{}' not found", session_id)))?;
+
+    if let Some(adapter) = entry.adapter {
+        let mut adapter = adapter.lock().await;
+        if let Err(e) = adapter.terminate().await {
+            eprintln!(
+                "[SessionManager] Failed to terminate adapter for forgotten session {}: {}",
+                session_id, e
+            );
+        }
+    }
+
+    db.delete_session(session_id)?;
+    Ok(entry.session)
+}
+
+/// Record a model the agent switched to on its own. Returns the event to emit,
+/// or `None` when the session is unknown or already on that model.
+async fn apply_model_change(
+    sessions: &RwLock<HashMap<String, SessionEntry>>,
+    db: &Database,
+    session_id: &str,
+    model_id: &str,
+) -> Option<ModelChangedEvent> {
+    let previous_model_id = {
+        let mut sessions = sessions.write().await;
+        let entry = sessions.get_mut(session_id)?;
+        if entry.session.model.as_deref() == Some(model_id) {
+            return None;
+        }
+        entry.session.model.replace(model_id.to_string())
+    };
+
+    println!(
+        "[SessionManager] Agent changed model for session {}: {:?} -> {}",
+        session_id, previous_model_id, model_id
+    );
+
+    if let Err(e) = db.update_session_model(session_id, model_id) {
+        eprintln!(
+            "[SessionManager] Failed to update session model in DB: {}",
+            e
+        );
+    }
+
+    Some(ModelChangedEvent {
+        session_id: session_id.to_string(),
+        model_id: model_id.to_string(),
+        previous_model_id,
+    })
+}
+
+/// Bring a project below `max` managed worktrees so one more can be created.
+/// Candidates are terminated sessions whose worktree is still on disk; with
+/// `auto_evict` the oldest of them lose their worktree (the session and its
+/// branch stay), otherwise they are listed in the error. Returns the ids of
+/// the sessions whose worktree was evicted.
+fn make_room_for_worktree(
+    project_path: &Path,
+    terminated: Vec<Session>,
+    max: usize,
+    auto_evict: bool,
+) -> AppResult<Vec<String>> {
+    let mut count = WorktreeManager::count_session_worktrees(project_path)?;
+    if count < max {
+        return Ok(Vec::new());
+    }
+
+    let mut candidates: Vec<Session> = terminated
+        .into_iter()
+        .filter(|s| {
+            !s.is_local
+                && WorktreeManager::worktree_exists(
+                    project_path,
+                    &s.id,
+                    Path::new(&s.worktree_path),
+                )
+        })
+        .collect();
+    candidates.sort_by_key(|s| s.updated_at.unwrap_or(s.created_at));
+
+    if !auto_evict {
+        let names: Vec<String> = candidates
+            .iter()
+            .map(|s| format!("{} ({})", s.name, s.branch_name))
+            .collect();
+        return Err(AppError::InvalidOperation(format!(
+            "Project has {} worktrees (limit {}). Clean up terminated sessions first: {}",
+            count,
+            max,
+            if names.is_empty() {
+                "none available".to_string()
+            } else {
+                names.join(", ")
+            }
+        )));
+    }
+
+    let mut evicted = Vec::new();
+    for candidate in candidates {
+        if count < max {
+            break;
+        }
+        println!(
+            "[SessionManager] Evicting worktree of session {} to stay within limit of {}",
+            candidate.id, max
+        );
+        let leftover = WorktreeManager::evict_worktree(
+            project_path,
+            &candidate.id,
+            Path::new(&candidate.worktree_path),
+        )?;
+        if !leftover.is_empty() {
+            eprintln!(
+                "[SessionManager] Could not delete from evicted worktree {}: {}",
+                candidate.id,
+                leftover.join(", ")
+            );
+        }
+        evicted.push(candidate.id);
+        count -= 1;
+    }
+
+    if count >= max {
+        return Err(AppError::InvalidOperation(format!(
+            "Project has {} worktrees (limit {}) and no terminated sessions to evict",
+            count, max
+        )));
+    }
+    Ok(evicted)
+}
+
+/// Commit a finished turn's changes to the session branch when
+/// `auto_commit_per_turn` is on. Local sessions are never committed. Returns
+/// the new commit's sha, or `None` when nothing was committed.
+fn commit_turn(
+    settings_manager: &SettingsManager,
+    session: &Session,
+    turn: usize,
+) -> AppResult<Option<String>> {
+    if session.is_local {
+        return Ok(None);
+    }
+    let auto_commit = settings_manager
+        .get_effective_settings(Path::new(&session.project_path))
+        .settings
+        .general
+        .and_then(|g| g.auto_commit_per_turn)
+        .unwrap_or(false);
+    if !auto_commit {
+        return Ok(None);
+    }
+    WorktreeManager::commit_worktree(
+        Path::new(&session.worktree_path),
+        &format!("Agent turn {}", turn),
+    )
+}
+
+/// Move a session whose agent died to Error, in memory and in the database.
+/// Returns the updated session and its error, or `None` when the session is
+/// unknown or already terminated or in error.
+async fn crash_entry(
+    sessions: &RwLock<HashMap<String, SessionEntry>>,
+    db: &Database,
+    session_id: &str,
+    message: &str,
+) -> Option<(Session, SessionError)> {
+    let session_error = SessionError::new("agent_crashed", message);
+
+    let updated_session = {
+        let mut sessions = sessions.write().await;
+        let entry = sessions.get_mut(session_id)?;
+        if matches!(
+            entry.session.status,
+            SessionStatus::Terminated | SessionStatus::Error
+        ) {
+            return None;
+        }
+        entry.session.status = SessionStatus::Error;
+        entry.session.error = Some(session_error.clone());
+        entry.session.clone()
+    };
+
+    eprintln!(
+        "[SessionManager] Agent for session {} crashed: {}",
+        session_id, message
+    );
+    if let Err(e) = db.update_session_error(session_id, &SessionStatus::Error, &session_error) {
+        eprintln!(
+            "[SessionManager] Failed to update session error in DB: {}",
+            e
+        );
+    }
+    Some((updated_session, session_error))
+}
+
+/// Directory a resumed agent works in. Sessions created before `acp_cwd` was
+/// recorded were created in their worktree.
+fn agent_cwd(session: &Session) -> String {
+    session
+        .acp_cwd
+        .clone()
+        .unwrap_or_else(|| session.worktree_path.clone())
+}
+
+/// Point a local session at another directory. A running adapter is taken out
+/// and returned so the caller can stop it and resume in the new directory.
+async fn retarget_cwd(
+    sessions: &RwLock<HashMap<String, SessionEntry>>,
+    db: &Database,
+    session_id: &str,
+    path: &str,
+) -> AppResult<(Session, Option<SessionAdapter>)> {
+    let cwd = PathBuf::from(path.trim());
+    if !cwd.is_dir() {
+        return Err(AppError::InvalidOperation(format!(
+            "'{}' is not a directory",
+            cwd.display()
+        )));
+    }
+    let cwd = cwd.canonicalize()?.to_string_lossy().to_string();
+
+    let (session, adapter) = {
+        let mut sessions = sessions.write().await;
+        let entry = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session '{}' not found", session_id)))?;
+
+        if !entry.session.is_local {
+            return Err(AppError::InvalidOperation(
+                "Cannot change the working directory of a worktree session".to_string(),
+            ));
+        }
+        if entry.session.worktree_path == cwd {
+            return Ok((entry.session.clone(), None));
+        }
+
+        entry.session.worktree_path = cwd.clone();
+        // The agent should work in the new directory, not load the old one
+        entry.session.acp_cwd = None;
+        let adapter = entry.adapter.take();
+        if adapter.is_some() {
+            entry.session.status = SessionStatus::Paused;
+        }
+        (entry.session.clone(), adapter)
+    };
+
+    if let Err(e) = db.update_session_worktree_path(session_id, &cwd) {
+        eprintln!(
+            "[SessionManager] Failed to update session worktree path in DB: {}",
+            e
+        );
+    }
+    if let Err(e) = db.update_session_acp_cwd(session_id, None) {
+        eprintln!("[SessionManager] Failed to clear ACP cwd in DB: {}", e);
+    }
+
+    println!(
+        "[SessionManager] Working directory for session {} set to {}",
+        session_id, cwd
+    );
+    Ok((session, adapter))
+}
+
+/// Put the session's system prompt ahead of `content` if this ACP session
+/// hasn't been sent it yet. Returns the content to send and whether the
+/// prompt was added.
+async fn with_system_prompt(
+    sessions: &RwLock<HashMap<String, SessionEntry>>,
+    system_prompt_sent: &RwLock<std::collections::HashSet<String>>,
+    session_id: &str,
+    content: Vec<PromptContent>,
+) -> (Vec<PromptContent>, bool) {
+    let system_prompt = sessions
+        .read()
+        .await
+        .get(session_id)
+        .and_then(|e| e.session.system_prompt.clone());
+    match system_prompt {
+        Some(text) if !system_prompt_sent.read().await.contains(session_id) => {
+            let mut combined = vec![PromptContent::Text { text }];
+            combined.extend(content);
+            (combined, true)
+        }
+        _ => (content, false),
+    }
+}
+
+/// A loaded ACP session already has the system prompt in its history; a
+/// fresh one needs it again with the next prompt
+async fn note_resumed_history(
+    system_prompt_sent: &RwLock<std::collections::HashSet<String>>,
+    session_id: &str,
+    history_loaded: bool,
+) {
+    let mut sent = system_prompt_sent.write().await;
+    if history_loaded {
+        sent.insert(session_id.to_string());
+    } else {
+        sent.remove(session_id);
+    }
+}
+
+/// Replace the session list with the stored sessions. Entries with a live
+/// adapter or listed in `creating` are kept as they are and only pick up the
+/// stored name, pin, branch, system prompt and color.
+async fn reload_entries(
+    sessions: &RwLock<HashMap<String, SessionEntry>>,
+    db: &Database,
+    creating: &HashSet<String>,
+) -> AppResult<()> {
+    let stored = db.load_sessions()?;
+    let mut sessions = sessions.write().await;
+    let mut reloaded: HashMap<String, SessionEntry> = sessions
+        .drain()
+        .filter(|(id, entry)| entry.adapter.is_some() || creating.contains(id))
+        .collect();
+
+    for mut session in stored {
+        match reloaded.get_mut(&session.id) {
+            Some(entry) => {
+                entry.session.name = session.name;
+                entry.session.pinned = session.pinned;
+                entry.session.branch_name = session.branch_name;
+                entry.session.system_prompt = session.system_prompt;
+                entry.session.ui_color = session.ui_color;
+                entry.session.updated_at = session.updated_at;
+            }
+            None => {
+                settle_loaded_status(db, &mut session);
+                reloaded.insert(
+                    session.id.clone(),
+                    SessionEntry {
+                        session,
+                        adapter: None,
+                    },
+                );
+            }
+        }
+    }
+
+    println!(
+        "[SessionManager] Reloaded {} sessions from database",
+        reloaded.len()
+    );
+    *sessions = reloaded;
+    Ok(())
+}
+
+/// Add a Paused local session in `project_path` for an agent session Forkestra
+/// did not create, keyed by its `acp_session_id`
+async fn import_entry(
+    sessions: &RwLock<HashMap<String, SessionEntry>>,
+    db: &Database,
+    project_path: &str,
+    acp_session_id: &str,
+    name: &str,
+    provider: ProviderType,
+) -> AppResult<Session> {
+    let acp_session_id = acp_session_id.trim();
+    if acp_session_id.is_empty() {
+        return Err(AppError::InvalidOperation(
+            "Agent session id cannot be empty".to_string(),
+        ));
+    }
+    let name = sanitize_session_name(name)?;
+
+    let path = PathBuf::from(project_path);
+    if !path.is_dir() {
+        return Err(AppError::NotFound(format!(
+            "Project directory '{}' does not exist",
+            project_path
+        )));
+    }
+    if !WorktreeManager::is_git_repo(&path) {
+        return Err(AppError::InvalidOperation(format!(
+            "Path '{}' is not a git repository",
+            project_path
+        )));
+    }
+
+    let mut sessions = sessions.write().await;
+    if let Some(existing) = sessions
+        .values()
+        .find(|e| e.session.acp_session_id.as_deref() == Some(acp_session_id))
+    {
+        return Err(AppError::InvalidOperation(format!(
+            "Agent session '{}' is already imported as '{}'",
+            acp_session_id, existing.session.name
+        )));
+    }
+
+    let now = Utc::now();
+    let session = Session {
+        id: uuid::Uuid::new_v4().to_string(),
+        name,
+        provider,
+        status: SessionStatus::Paused,
+        worktree_path: project_path.to_string(),
+        branch_name: WorktreeManager::get_current_branch(&path)
+            .unwrap_or_else(|_| "HEAD".to_string()),
+        created_at: now,
+        updated_at: Some(now),
+        project_path: project_path.to_string(),
+        is_local: true,
+        acp_session_id: Some(acp_session_id.to_string()),
+        acp_cwd: Some(project_path.to_string()),
+        model: None,
+        available_models: vec![],
+        mode: None,
+        available_modes: vec![],
+        available_commands: vec![],
+        plan_entries: vec![],
+        config_options: vec![],
+        error: None,
+        pinned: false,
+        system_prompt: None,
+        ui_color: None,
+    };
+
+    db.save_session(&session)?;
+    sessions.insert(
+        session.id.clone(),
+        SessionEntry {
+            session: session.clone(),
+            adapter: None,
+        },
+    );
+    Ok(session)
+}
+
+/// Whether a session is stopped, has an ACP session ID to resume, and no
+/// live adapter
+async fn is_resumable_entry(
+    sessions: &RwLock<HashMap<String, SessionEntry>>,
+    session_id: &str,
+) -> bool {
+    let sessions = sessions.read().await;
+    sessions.get(session_id).is_some_and(|entry| {
+        matches!(
+            entry.session.status,
+            SessionStatus::Paused | SessionStatus::Terminated | SessionStatus::Error
+        ) && entry.session.acp_session_id.is_some()
+            && entry.adapter.is_none()
+    })
+}
+
+/// Run `resume` for the resumable sessions among `session_ids`, at most
+/// `AUTO_RESUME_CONCURRENCY` at a time. A failure only affects its own
+/// session; `report` gets a progress event as each one finishes.
+async fn auto_resume<F, Fut>(
+    sessions: &RwLock<HashMap<String, SessionEntry>>,
+    session_ids: &[String],
+    resume: F,
+    mut report: impl FnMut(AutoResumeProgressEvent),
+) where
+    F: Fn(String) -> Fut,
+    Fut: std::future::Future<Output = AppResult<Session>>,
+{
+    let mut candidates = Vec::new();
+    for session_id in session_ids {
+        if is_resumable_entry(sessions, session_id).await {
+            candidates.push(session_id.clone());
+        }
+    }
+    if candidates.is_empty() {
+        return;
+    }
+
+    let total = candidates.len();
+    println!("[SessionManager] Auto-resuming {} sessions", total);
+
+    let mut results = futures::stream::iter(candidates)
+        .map(|session_id| {
+            let resumed = resume(session_id.clone());
+            async move { (session_id, resumed.await) }
+        })
+        .buffer_unordered(AUTO_RESUME_CONCURRENCY);
+
+    let mut completed = 0;
+    while let Some((session_id, result)) = results.next().await {
+        completed += 1;
+        let error = result.err().map(|e| {
+            eprintln!(
+                "[SessionManager] Failed to auto-resume session {}: {}",
+                session_id, e
+            );
+            e.to_string()
+        });
+        report(AutoResumeProgressEvent {
+            session_id,
+            completed,
+            total,
+            error,
+        });
+    }
+}
+
+/// Stop agents that were taken out of their sessions, logging failures
+async fn terminate_adapters(adapters: Vec<(String, SessionAdapter)>, context: &str) {
+    for (session_id, adapter) in adapters {
+        let mut adapter = adapter.lock().await;
+        if let Err(e) = adapter.terminate().await {
+            eprintln!(
+                "[SessionManager] Failed to terminate adapter for {} {}: {}",
+                session_id, context, e
+            );
+        }
+    }
 }
 
 pub struct SessionManager {
@@ -36,6 +609,423 @@ pub struct SessionManager {
     skills_manager: Arc<SkillsManager>,
     /// Track which sessions have already had skills injected
     skills_injected: Arc<RwLock<std::collections::HashSet<String>>>,
+    /// Sessions whose current ACP session has already received the system prompt
+    system_prompt_sent: Arc<RwLock<std::collections::HashSet<String>>>,
+    /// Creation start and current phase start for sessions still being created
+    creation_progress: Arc<RwLock<HashMap<String, (Instant, Instant)>>>,
+    /// Tool call ids currently running, per session
+    active_tool_calls: ActiveToolCalls,
+    /// Tool call `_meta` per message, held until the message is saved
+    pending_message_meta: PendingMessageMeta,
+    /// Completed prompt turns per session since the app started
+    turn_counts: Arc<RwLock<HashMap<String, usize>>>,
+    /// When the currently running turn of each session was sent
+    turn_started: Arc<RwLock<HashMap<String, DateTime<Utc>>>>,
+    /// Task forwarding each session's stream chunks, one per session
+    stream_forwarders: StreamForwarders,
+    /// Recent prompts, tool calls, errors and stderr lines per session
+    activity_logs: ActivityLogs,
+    /// Stream chunks of each message a `send_message_blocking` call waits on
+    message_waiters: MessageWaiters,
+    /// Cancels the handshake of each session that is being resumed
+    resume_cancellations: Arc<RwLock<HashMap<String, CancellationToken>>>,
+    /// Sessions that were active when the app last closed
+    startup_active_sessions: Vec<String>,
+}
+
+type ActiveToolCalls = Arc<RwLock<HashMap<String, std::collections::HashSet<String>>>>;
+
+/// Keep the per-session set of running tool calls in sync with the stream
+async fn track_tool_call(active_tool_calls: &ActiveToolCalls, chunk: &StreamChunk) {
+    if chunk.is_complete {
+        active_tool_calls.write().await.remove(&chunk.session_id);
+        return;
+    }
+    let Some(tool_call) = &chunk.tool_call else {
+        return;
+    };
+    let mut active = active_tool_calls.write().await;
+    let calls = active.entry(chunk.session_id.clone()).or_default();
+    match tool_call.status.as_str() {
+        "completed" | "error" => {
+            calls.remove(&tool_call.tool_call_id);
+        }
+        _ => {
+            calls.insert(tool_call.tool_call_id.clone());
+        }
+    }
+}
+
+/// Entries kept in each session's activity log; older ones are dropped
+const MAX_ACTIVITY_LOG_ENTRIES: usize = 500;
+
+/// Longest activity log summary, in bytes
+const MAX_ACTIVITY_SUMMARY_BYTES: usize = 500;
+
+type ActivityLogs = Arc<RwLock<HashMap<String, VecDeque<ActivityLogEntry>>>>;
+
+/// Append an entry to a session's activity log
+async fn record_activity(logs: &ActivityLogs, session_id: &str, kind: ActivityKind, summary: &str) {
+    let mut logs = logs.write().await;
+    let log = logs.entry(session_id.to_string()).or_default();
+    if log.len() == MAX_ACTIVITY_LOG_ENTRIES {
+        log.pop_front();
+    }
+    log.push_back(ActivityLogEntry {
+        timestamp: Utc::now(),
+        kind,
+        summary: truncate_str(summary.trim(), MAX_ACTIVITY_SUMMARY_BYTES).to_string(),
+    });
+}
+
+/// Log the start and end of tool calls and the end of turns. Must run before
+/// `track_tool_call`, which it relies on to tell new tool calls from updates.
+async fn record_chunk_activity(
+    logs: &ActivityLogs,
+    active_tool_calls: &ActiveToolCalls,
+    chunk: &StreamChunk,
+) {
+    if chunk.is_complete {
+        record_activity(
+            logs,
+            &chunk.session_id,
+            ActivityKind::TurnComplete,
+            "Turn complete",
+        )
+        .await;
+        return;
+    }
+    let Some(tool_call) = &chunk.tool_call else {
+        return;
+    };
+    let finished = matches!(tool_call.status.as_str(), "completed" | "error" | "failed");
+    let started = !active_tool_calls
+        .read()
+        .await
+        .get(&chunk.session_id)
+        .is_some_and(|calls| calls.contains(&tool_call.tool_call_id));
+    if started || finished {
+        let summary = format!("{} ({})", tool_call.title, tool_call.status);
+        record_activity(logs, &chunk.session_id, ActivityKind::ToolCall, &summary).await;
+    }
+}
+
+/// Largest tool call `_meta` kept per message, in bytes of JSON
+const MAX_MESSAGE_META_BYTES: usize = 64 * 1024;
+
+type StreamForwarders = Arc<RwLock<HashMap<String, JoinHandle<()>>>>;
+
+/// A `send_message_blocking` call waiting on the chunks of its message
+struct MessageWaiter {
+    session_id: String,
+    tx: mpsc::UnboundedSender<StreamChunk>,
+}
+
+type MessageWaiters = Arc<RwLock<HashMap<String, MessageWaiter>>>;
+
+/// Hand a chunk to the `send_message_blocking` call waiting on its message
+async fn notify_message_waiter(waiters: &MessageWaiters, chunk: &StreamChunk) {
+    if let Some(waiter) = waiters.read().await.get(&chunk.message_id) {
+        let _ = waiter.tx.send(chunk.clone());
+    }
+}
+
+/// Close the streams of every call waiting on a session, so they fail at
+/// once instead of running into their timeout
+async fn drop_message_waiters(waiters: &MessageWaiters, session_id: &str) {
+    waiters
+        .write()
+        .await
+        .retain(|_, waiter| waiter.session_id != session_id);
+}
+
+/// Accumulate the text of a turn until its final chunk. Fails when the turn
+/// failed or the stream closed first.
+async fn collect_turn_text(rx: &mut mpsc::UnboundedReceiver<StreamChunk>) -> AppResult<String> {
+    let mut text = String::new();
+    while let Some(chunk) = rx.recv().await {
+        if let Some(error) = chunk.error {
+            return Err(AppError::Provider(error));
+        }
+        if chunk.is_complete {
+            return Ok(text);
+        }
+        if matches!(chunk.chunk_type, None | Some(StreamChunkType::Text)) {
+            text.push_str(&chunk.content);
+        }
+    }
+    Err(AppError::Provider(
+        "Stream ended before the turn completed".to_string(),
+    ))
+}
+
+/// Most sessions resumed at the same time by `auto_resume_sessions`
+const AUTO_RESUME_CONCURRENCY: usize = 3;
+
+/// Record the stream forwarder of a session's new agent process and abort the
+/// one it replaces, so leftovers from earlier attempts can't emit chunks twice
+async fn replace_stream_forwarder(
+    forwarders: &StreamForwarders,
+    session_id: &str,
+    forwarder: JoinHandle<()>,
+) {
+    let previous = forwarders
+        .write()
+        .await
+        .insert(session_id.to_string(), forwarder);
+    if let Some(previous) = previous.filter(|p| !p.is_finished()) {
+        println!(
+            "[SessionManager] Aborting previous stream forwarder for session {}",
+            session_id
+        );
+        previous.abort();
+    }
+}
+
+type PendingMessageMeta =
+    Arc<RwLock<HashMap<String, serde_json::Map<String, serde_json::Value>>>>;
+
+/// Buffer the `_meta` of a tool call chunk under its message id. Updates are
+/// merged into earlier meta of the same tool call; meta that would push the
+/// message past `MAX_MESSAGE_META_BYTES` is dropped.
+async fn record_tool_call_meta(pending: &PendingMessageMeta, chunk: &StreamChunk) {
+    let Some(tool_call) = &chunk.tool_call else {
+        return;
+    };
+    let Some(meta) = &tool_call.meta else {
+        return;
+    };
+    let tool_call_id = tool_call.tool_call_id.clone();
+
+    let mut pending = pending.write().await;
+    let entries = pending.entry(chunk.message_id.clone()).or_default();
+    let merged = match (entries.get(&tool_call_id), meta) {
+        (Some(serde_json::Value::Object(old)), serde_json::Value::Object(new)) => {
+            let mut merged = old.clone();
+            merged.extend(new.clone());
+            serde_json::Value::Object(merged)
+        }
+        _ => meta.clone(),
+    };
+
+    let others: usize = entries
+        .iter()
+        .filter(|(id, _)| **id != tool_call_id)
+        .map(|(_, v)| v.to_string().len())
+        .sum();
+    if others + merged.to_string().len() > MAX_MESSAGE_META_BYTES {
+        eprintln!(
+            "[SessionManager] Dropping _meta of tool call {}: message {} is over {} bytes",
+            tool_call_id, chunk.message_id, MAX_MESSAGE_META_BYTES
+        );
+        return;
+    }
+    entries.insert(tool_call_id, merged);
+}
+
+/// Emit a `session-progress` event for a session that is being created.
+/// Sessions that are not in the creation flow (e.g. resume fallbacks) are ignored.
+async fn emit_progress(
+    app_handle: &AppHandle,
+    creation_progress: &RwLock<HashMap<String, (Instant, Instant)>>,
+    session_id: &str,
+    phase: SessionProgressPhase,
+) {
+    let Some(event) = progress_event(creation_progress, session_id, phase).await else {
+        return;
+    };
+    if let Err(e) = app_handle.emit("session-progress", &event) {
+        eprintln!("[SessionManager] Failed to emit session-progress event: {}", e);
+    }
+}
+
+/// Record that a creating session entered `phase` and build its progress event.
+/// `Ready` ends the creation flow.
+async fn progress_event(
+    creation_progress: &RwLock<HashMap<String, (Instant, Instant)>>,
+    session_id: &str,
+    phase: SessionProgressPhase,
+) -> Option<SessionProgressEvent> {
+    let now = Instant::now();
+    let timing = {
+        let mut progress = creation_progress.write().await;
+        let timing = progress.get(session_id).copied();
+        if phase == SessionProgressPhase::Ready {
+            progress.remove(session_id);
+        } else if let Some((started, _)) = timing {
+            progress.insert(session_id.to_string(), (started, now));
+        }
+        timing
+    };
+
+    let (started, phase_started) = timing?;
+    Some(SessionProgressEvent {
+        session_id: session_id.to_string(),
+        phase,
+        elapsed_ms: now.duration_since(started).as_millis() as u64,
+        previous_phase_ms: now.duration_since(phase_started).as_millis() as u64,
+    })
+}
+
+/// Persist parts of the stream the frontend doesn't save on its own:
+/// full tool call payloads and agent-produced images
+fn persist_stream_chunk(app_handle: &AppHandle, db: &Database, chunk: &StreamChunk) {
+    if let Some(tool_call) = &chunk.tool_call {
+        if let Err(e) = db.upsert_tool_call_detail(&chunk.session_id, &chunk.message_id, tool_call) {
+            eprintln!("[SessionManager] Failed to persist tool call detail: {}", e);
+        }
+    }
+    if let Some(image) = &chunk.image_content {
+        let message = ChatMessage::assistant_image(&chunk.session_id, image.clone());
+        match db.save_message(&message, None) {
+            Ok(()) => emit_message_saved(app_handle, &message),
+            Err(e) => eprintln!("[SessionManager] Failed to persist image message: {}", e),
+        }
+        // The image id is the id of the message carrying it
+        if let Err(e) = db.save_image(&message.id, image) {
+            eprintln!("[SessionManager] Failed to write image to disk: {}", e);
+        }
+    }
+}
+
+/// Largest serialized message sent in a `message-saved` event
+const MAX_MESSAGE_SAVED_EVENT_BYTES: usize = 256 * 1024;
+
+/// Tell every frontend view that a message was saved. Meta is loaded separately
+/// and never sent along.
+fn emit_message_saved(app_handle: &AppHandle, message: &ChatMessage) {
+    let mut message = message.clone();
+    message.meta = None;
+    let fits = serde_json::to_vec(&message)
+        .map(|json| json.len() <= MAX_MESSAGE_SAVED_EVENT_BYTES)
+        .unwrap_or(false);
+    let event = MessageSavedEvent {
+        session_id: message.session_id.clone(),
+        message_id: message.id.clone(),
+        message: fits.then_some(message),
+    };
+    if let Err(e) = app_handle.emit("message-saved", &event) {
+        eprintln!("[SessionManager] Failed to emit message-saved event: {}", e);
+    }
+}
+
+/// Run `SessionManager::on_turn_complete` off the stream forwarder so slow git
+/// work doesn't hold up later chunks
+fn spawn_turn_complete(app_handle: &AppHandle, session_id: &str) {
+    let app_handle = app_handle.clone();
+    let session_id = session_id.to_string();
+    tokio::spawn(async move {
+        if let Some(manager) = app_handle.try_state::<SessionManager>() {
+            manager.on_turn_complete(&session_id).await;
+        }
+    });
+}
+
+/// Reject export destinations whose directory is missing or that are directories
+fn check_export_destination(destination: &Path) -> AppResult<()> {
+    match destination.parent() {
+        Some(parent) if parent.as_os_str().is_empty() || parent.is_dir() => {}
+        _ => {
+            return Err(AppError::InvalidOperation(format!(
+                "Destination directory for '{}' does not exist",
+                destination.display()
+            )));
+        }
+    }
+    if destination.is_dir() {
+        return Err(AppError::InvalidOperation(format!(
+            "Destination '{}' is a directory",
+            destination.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Render a session transcript as Markdown
+fn render_transcript_markdown(session: &Session, messages: &[ChatMessage]) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}\n", session.name);
+    let _ = writeln!(out, "- Provider: {}", session.provider.as_id());
+    let _ = writeln!(out, "- Branch: {}", session.branch_name);
+    let _ = writeln!(out, "- Project: {}", session.project_path);
+    let _ = writeln!(out, "- Created: {}\n", session.created_at.to_rfc3339());
+
+    for message in messages {
+        let role = match message.role {
+            MessageRole::User => "User",
+            MessageRole::Assistant => "Assistant",
+            MessageRole::System => "System",
+        };
+        let _ = writeln!(out, "## {} ({})\n", role, message.timestamp.to_rfc3339());
+
+        match &message.parts {
+            Some(parts) => {
+                for part in parts {
+                    match part {
+                        MessagePart::Text { content } => {
+                            let _ = writeln!(out, "{}\n", content);
+                        }
+                        MessagePart::Image { content } => {
+                            let _ = writeln!(out, "_[image: {}]_\n", content.mime_type);
+                        }
+                        MessagePart::ResourceLink { content } => {
+                            let _ = writeln!(out, "[{}]({})\n", content.name, content.uri);
+                        }
+                        MessagePart::ToolCall { tool_call } => {
+                            let _ = writeln!(
+                                out,
+                                "> Tool: {} ({})\n",
+                                tool_call.title, tool_call.status
+                            );
+                        }
+                    }
+                }
+            }
+            None => {
+                if !message.content.is_empty() {
+                    let _ = writeln!(out, "{}\n", message.content);
+                }
+                for tool_call in message.tool_calls.iter().flatten() {
+                    let _ = writeln!(out, "> Tool: {} ({})\n", tool_call.title, tool_call.status);
+                }
+            }
+        }
+    }
+
+    out
+}
+
+/// Maximum length of a session name, in characters
+const MAX_SESSION_NAME_LEN: usize = 200;
+
+/// Normalize a user-supplied session name: control characters are dropped
+/// (line breaks and tabs become spaces) and surrounding whitespace is trimmed.
+/// Empty or over-long names are rejected.
+fn sanitize_session_name(name: &str) -> AppResult<String> {
+    let cleaned: String = name
+        .chars()
+        .filter_map(|c| match c {
+            '\n' | '\r' | '\t' => Some(' '),
+            c if c.is_control() => None,
+            c => Some(c),
+        })
+        .collect();
+    let cleaned = cleaned.trim();
+
+    if cleaned.is_empty() {
+        return Err(AppError::InvalidOperation(
+            "Session name cannot be empty".to_string(),
+        ));
+    }
+    if cleaned.chars().count() > MAX_SESSION_NAME_LEN {
+        return Err(AppError::InvalidOperation(format!(
+            "Session name cannot exceed {} characters",
+            MAX_SESSION_NAME_LEN
+        )));
+    }
+    Ok(cleaned.to_string())
 }
 
 /// Look up a ProviderDefinition by provider type from the combined list of
@@ -53,6 +1043,101 @@ fn find_definition(
         .cloned()
 }
 
+/// Size limit for stored message content (see `AdvancedSettings`)
+fn message_size_limit(settings_manager: &SettingsManager) -> MessageSizeLimit {
+    let advanced = settings_manager.get_settings().advanced.unwrap_or_default();
+    MessageSizeLimit {
+        max_bytes: advanced
+            .max_message_content_bytes
+            .unwrap_or(DEFAULT_MAX_MESSAGE_CONTENT_BYTES),
+        reject: advanced.oversized_message_policy.as_deref() == Some("reject"),
+    }
+}
+
+/// Whether a new session works in the project directory. Requests that leave
+/// it out follow the project's effective `default_work_mode`.
+fn uses_local_dir(settings_manager: &SettingsManager, request: &CreateSessionRequest) -> bool {
+    request.use_local.unwrap_or_else(|| {
+        settings_manager
+            .get_effective_settings(Path::new(&request.project_path))
+            .settings
+            .general
+            .and_then(|general| general.default_work_mode)
+            .as_deref()
+            == Some("local")
+    })
+}
+
+/// Resident memory in bytes and CPU time in milliseconds of a process, both
+/// `None` when the process can't be inspected
+fn process_usage(pid: u32) -> (Option<u64>, Option<u64>) {
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing().with_memory().with_cpu(),
+    );
+    match system.process(pid) {
+        Some(process) => (Some(process.memory()), Some(process.accumulated_cpu_time())),
+        None => (None, None),
+    }
+}
+
+/// Threads in this process, `None` where sysinfo can't list them (only Linux
+/// exposes per-process tasks)
+fn process_thread_count() -> Option<usize> {
+    let pid = Pid::from_u32(std::process::id());
+    let mut system = System::new();
+    system.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing().with_tasks(),
+    );
+    // The task list leaves out the main thread
+    system.process(pid)?.tasks().map(|tasks| tasks.len() + 1)
+}
+
+/// Resolve a model given by id or by case-insensitive display name against the
+/// session's models. An id match wins over a name match. Without a model list
+/// the value is passed to the agent as an id.
+fn resolve_model_id(available: &[ModelInfo], model: &str) -> AppResult<String> {
+    if available.is_empty() || available.iter().any(|m| m.model_id == model) {
+        return Ok(model.to_string());
+    }
+
+    let matches: Vec<&ModelInfo> = available
+        .iter()
+        .filter(|m| m.display_name.trim().eq_ignore_ascii_case(model.trim()))
+        .collect();
+    match matches.as_slice() {
+        [found] => Ok(found.model_id.clone()),
+        [] => Err(AppError::InvalidOperation(format!(
+            "Model '{}' is not available for this session",
+            model
+        ))),
+        _ => Err(AppError::InvalidOperation(format!(
+            "Model name '{}' is ambiguous, it matches {}",
+            model,
+            matches
+                .iter()
+                .map(|m| m.model_id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ))),
+    }
+}
+
+/// Capacity of a session's stream chunk channel (see `AdvancedSettings`)
+fn stream_channel_capacity(settings_manager: &SettingsManager) -> usize {
+    settings_manager
+        .get_settings()
+        .advanced
+        .and_then(|a| a.stream_channel_capacity)
+        .filter(|&c| c > 0)
+        .unwrap_or(DEFAULT_STREAM_CHANNEL_CAPACITY)
+}
+
 /// Create a provider adapter from a ProviderType and settings.
 fn create_adapter(
     provider: &ProviderType,
@@ -67,38 +1152,103 @@ fn create_adapter(
         ))
     })?;
     let provider_settings = settings.provider_settings.get(provider.as_id());
-    Ok(Box::new(GenericAcpAdapter::new(&def, provider_settings)))
+    let timeouts = AcpTimeouts::from_settings(
+        settings
+            .advanced
+            .as_ref()
+            .and_then(|a| a.acp_timeouts.as_ref()),
+    );
+    Ok(Box::new(
+        GenericAcpAdapter::new(&def, provider_settings).with_timeouts(timeouts),
+    ))
 }
 
-impl SessionManager {
-    pub fn new(
-        app_handle: AppHandle,
-        settings_manager: Arc<SettingsManager>,
-        db: Arc<Database>,
-        mcp_manager: Arc<McpManager>,
-        skills_manager: Arc<SkillsManager>,
-    ) -> Self {
-        // Load persisted sessions from DB on startup
-        let mut initial_sessions = HashMap::new();
+/// Whether an adapter built from the current settings would launch a different
+/// process than `current`, so the running agent has to be respawned to use them
+fn launch_settings_changed(
+    current: &dyn ProviderAdapter,
+    provider: &ProviderType,
+    settings_manager: &SettingsManager,
+) -> AppResult<bool> {
+    let fresh = create_adapter(provider, settings_manager)?;
+    Ok(current.launch_fingerprint() != fresh.launch_fingerprint())
+}
+
+/// A session loaded from the database has no adapter, so a stored Active or
+/// Creating status is stale. Sessions with an ACP session id can be resumed and
+/// become Paused; the rest are unusable and become Error.
+fn settle_loaded_status(db: &Database, session: &mut Session) {
+    if session.status != SessionStatus::Active && session.status != SessionStatus::Creating {
+        return;
+    }
+    if session.acp_session_id.is_none() {
+        println!(
+            "[SessionManager] Marking session {} as error (no ACP session ID)",
+            session.id
+        );
+        session.status = SessionStatus::Error;
+    } else {
+        session.status = SessionStatus::Paused;
+    }
+    let _ = db.update_session_status(&session.id, &session.status);
+}
+
+/// Flag a loaded session whose worktree was deleted outside the app, so it
+/// reports why instead of failing on resume
+fn check_loaded_worktree(db: &Database, session: &mut Session) {
+    if session.is_local || session.status == SessionStatus::Terminated {
+        return;
+    }
+
+    if WorktreeManager::worktree_exists(
+        Path::new(&session.project_path),
+        &session.id,
+        Path::new(&session.worktree_path),
+    ) {
+        return;
+    }
+
+    println!(
+        "[SessionManager] Marking session {} as error (worktree {} is missing)",
+        session.id, session.worktree_path
+    );
+    let session_error = SessionError::new(
+        "worktree_missing",
+        format!(
+            "The worktree at {} no longer exists. It may have been deleted outside Forkestra.",
+            session.worktree_path
+        ),
+    );
+    session.status = SessionStatus::Error;
+    if let Err(e) = db.update_session_error(&session.id, &session.status, &session_error) {
+        eprintln!(
+            "[SessionManager] Failed to update session error in DB: {}",
+            e
+        );
+    }
+    session.error = Some(session_error);
+}
+
+impl SessionManager {
+    pub fn new(
+        app_handle: AppHandle,
+        settings_manager: Arc<SettingsManager>,
+        db: Arc<Database>,
+        mcp_manager: Arc<McpManager>,
+        skills_manager: Arc<SkillsManager>,
+    ) -> Self {
+        // Load persisted sessions from DB on startup
+        let mut initial_sessions = HashMap::new();
+        let mut startup_active_sessions = Vec::new();
         match db.load_sessions() {
             Ok(mut sessions) => {
                 for session in &mut sessions {
-                    // Mark previously-active sessions as paused (adapters are gone after restart, but sessions are resumable)
-                    if session.status == SessionStatus::Active
-                        || session.status == SessionStatus::Creating
-                    {
-                        // Check if session has acp_session_id - sessions without it are invalid
-                        if session.acp_session_id.is_none() {
-                            println!(
-                                "[SessionManager] Marking session {} as error (no ACP session ID)",
-                                session.id
-                            );
-                            session.status = SessionStatus::Error;
-                            let _ = db.update_session_status(&session.id, &SessionStatus::Error);
-                        } else {
-                            session.status = SessionStatus::Paused;
-                            let _ = db.update_session_status(&session.id, &SessionStatus::Paused);
-                        }
+                    let was_active = session.status == SessionStatus::Active;
+                    // Adapters are gone after restart, but sessions are resumable
+                    settle_loaded_status(&db, session);
+                    check_loaded_worktree(&db, session);
+                    if was_active && session.status == SessionStatus::Paused {
+                        startup_active_sessions.push(session.id.clone());
                     }
                     initial_sessions.insert(
                         session.id.clone(),
@@ -129,6 +1279,17 @@ impl SessionManager {
             mcp_manager,
             skills_manager,
             skills_injected: Arc::new(RwLock::new(std::collections::HashSet::new())),
+            system_prompt_sent: Arc::new(RwLock::new(std::collections::HashSet::new())),
+            creation_progress: Arc::new(RwLock::new(HashMap::new())),
+            active_tool_calls: Arc::new(RwLock::new(HashMap::new())),
+            pending_message_meta: Arc::new(RwLock::new(HashMap::new())),
+            turn_counts: Arc::new(RwLock::new(HashMap::new())),
+            turn_started: Arc::new(RwLock::new(HashMap::new())),
+            stream_forwarders: Arc::new(RwLock::new(HashMap::new())),
+            activity_logs: Arc::new(RwLock::new(HashMap::new())),
+            message_waiters: Arc::new(RwLock::new(HashMap::new())),
+            resume_cancellations: Arc::new(RwLock::new(HashMap::new())),
+            startup_active_sessions,
         }
     }
 
@@ -137,34 +1298,158 @@ impl SessionManager {
         &self.db
     }
 
+    /// Add an event to the activity feed shared by all sessions
+    fn record_feed_event(&self, session_id: &str, kind: FeedEventKind, summary: &str) {
+        if let Err(e) = self.db.record_event(session_id, kind, summary) {
+            eprintln!("[SessionManager] Failed to record feed event: {}", e);
+        }
+    }
+
+    /// Emit `message-saved` for a message the frontend saved
+    pub fn notify_message_saved(&self, message: &ChatMessage) {
+        emit_message_saved(&self.app_handle, message);
+    }
+
+    /// Size cap for messages saved from the frontend, from advanced settings
+    pub fn message_size_limit(&self) -> MessageSizeLimit {
+        message_size_limit(&self.settings_manager)
+    }
+
+    /// Archive the database and start fresh. All running agents are stopped and
+    /// in-memory state is cleared so it matches the now-empty database.
+    /// Returns the archive path.
+    pub async fn rotate_database(&self) -> AppResult<String> {
+        // Stopping an agent can take seconds, so only take the adapters out
+        // under the lock and stop them once it is released
+        let stopped: Vec<(String, SessionAdapter)> = {
+            let mut sessions = self.sessions.write().await;
+            sessions
+                .iter_mut()
+                .filter_map(|(id, entry)| entry.adapter.take().map(|a| (id.clone(), a)))
+                .collect()
+        };
+        let stopped_ids: Vec<String> = stopped.iter().map(|(id, _)| id.clone()).collect();
+        terminate_adapters(stopped, "before rotation").await;
+
+        let db = self.db.clone();
+        let rotated = tokio::task::spawn_blocking(move || db.rotate())
+            .await
+            .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))
+            .and_then(|r| r);
+        let archive_path = match rotated {
+            Ok(path) => path,
+            Err(e) => {
+                // The agents are gone but the sessions are not: pause them so
+                // they can be resumed
+                let mut sessions = self.sessions.write().await;
+                for session_id in &stopped_ids {
+                    let Some(entry) = sessions.get_mut(session_id) else {
+                        continue;
+                    };
+                    if matches!(
+                        entry.session.status,
+                        SessionStatus::Active | SessionStatus::Creating
+                    ) {
+                        entry.session.status = SessionStatus::Paused;
+                        if let Err(e) = self
+                            .db
+                            .update_session_status(session_id, &SessionStatus::Paused)
+                        {
+                            eprintln!(
+                                "[SessionManager] Failed to pause session {} after failed rotation: {}",
+                                session_id, e
+                            );
+                        }
+                        let event = SessionStatusEvent {
+                            session_id: session_id.clone(),
+                            status: SessionStatus::Paused,
+                            session: Some(entry.session.clone()),
+                            error: None,
+                        };
+                        let _ = self.app_handle.emit("session-status-changed", &event);
+                    }
+                }
+                return Err(e);
+            }
+        };
+
+        // Sessions started while the old agents were stopping belong to the
+        // archived database too
+        let late: Vec<(String, SessionAdapter)> = {
+            let mut sessions = self.sessions.write().await;
+            let late = sessions
+                .iter_mut()
+                .filter_map(|(id, entry)| entry.adapter.take().map(|a| (id.clone(), a)))
+                .collect();
+            sessions.clear();
+            late
+        };
+        terminate_adapters(late, "after rotation").await;
+
+        self.skills_injected.write().await.clear();
+        self.system_prompt_sent.write().await.clear();
+        self.creation_progress.write().await.clear();
+        self.active_tool_calls.write().await.clear();
+        self.pending_message_meta.write().await.clear();
+        self.turn_counts.write().await.clear();
+        self.turn_started.write().await.clear();
+        self.stream_forwarders.write().await.clear();
+        self.activity_logs.write().await.clear();
+        self.message_waiters.write().await.clear();
+
+        Ok(archive_path.to_string_lossy().to_string())
+    }
+
     /// Create a new session (two-phase: sync worktree creation + async ACP connection)
     pub async fn create_session(&self, request: CreateSessionRequest) -> AppResult<Session> {
         let session_id = uuid::Uuid::new_v4().to_string();
         let project_path = PathBuf::from(&request.project_path);
+        let name = sanitize_session_name(&request.name)?;
+        let use_local = uses_local_dir(&self.settings_manager, &request);
+
+        if !use_local {
+            // Validate project path is a git repository before creating worktree
+            if !WorktreeManager::is_git_repo(&project_path) {
+                return Err(AppError::InvalidOperation(format!(
+                    "Path '{}' is not a git repository",
+                    request.project_path
+                )));
+            }
+            self.enforce_worktree_limit(&request.project_path).await?;
+        }
+
+        let started = Instant::now();
+        self.creation_progress
+            .write()
+            .await
+            .insert(session_id.clone(), (started, started));
+        self.report_progress(&session_id, SessionProgressPhase::CreatingWorktree)
+            .await;
 
         // Phase 1 (sync): Create worktree and session object
 
         // Determine worktree path and branch name based on use_local flag
-        let (worktree_path, branch_name) = if request.use_local {
+        let (worktree_path, branch_name) = if use_local {
             // Use local mode: no worktree, use project path directly
             let branch_name = WorktreeManager::get_current_branch(&project_path)
                 .unwrap_or_else(|_| "HEAD".to_string());
             (project_path.clone(), branch_name)
         } else {
-            // Validate project path is a git repository before creating worktree
-            if !WorktreeManager::is_git_repo(&project_path) {
-                return Err(AppError::InvalidOperation(format!(
-                    "Path '{}' is not a git repository",
-                    request.project_path
-                )));
-            }
             // Create worktree
-            WorktreeManager::create_worktree(
+            let branch_prefix = self.settings_manager.branch_prefix(&project_path);
+            match WorktreeManager::create_worktree(
                 &project_path,
                 &session_id,
                 request.base_branch.as_deref(),
                 request.fetch_first,
-            )?
+                &branch_prefix,
+            ) {
+                Ok(result) => result,
+                Err(e) => {
+                    self.creation_progress.write().await.remove(&session_id);
+                    return Err(e);
+                }
+            }
         };
 
         // Create session with status=Creating (branch_name is already populated)
@@ -173,7 +1458,7 @@ impl SessionManager {
 
         let session = Session {
             id: session_id.clone(),
-            name: request.name,
+            name,
             provider: request.provider.clone(),
             status: SessionStatus::Creating,
             worktree_path: worktree_path.to_string_lossy().to_string(),
@@ -181,8 +1466,9 @@ impl SessionManager {
             created_at: now,
             updated_at: Some(now),
             project_path: project_path.clone(),
-            is_local: request.use_local,
+            is_local: use_local,
             acp_session_id: None,
+            acp_cwd: None,
             model: None,
             available_models: vec![],
             mode: None,
@@ -191,6 +1477,11 @@ impl SessionManager {
             plan_entries: vec![],
             config_options: vec![],
             error: None,
+            pinned: false,
+            system_prompt: request
+                .system_prompt
+                .filter(|p| !p.trim().is_empty()),
+            ui_color: None,
         };
 
         // Store session in memory
@@ -209,6 +1500,11 @@ impl SessionManager {
         if let Err(e) = self.db.save_session(&session) {
             eprintln!("[SessionManager] Failed to persist session to database: {}", e);
         }
+        self.record_feed_event(
+            &session.id,
+            FeedEventKind::SessionCreated,
+            &format!("Created session {}", session.name),
+        );
 
         // Phase 2 (async): Spawn ACP connection in background
         self.spawn_acp_connection(
@@ -222,6 +1518,77 @@ impl SessionManager {
         Ok(session)
     }
 
+    /// Number of Forkestra-managed worktrees of a project, the ones that count
+    /// towards `max_worktrees_per_project`
+    pub fn get_worktree_count(&self, project_path: &str) -> AppResult<usize> {
+        WorktreeManager::count_session_worktrees(&PathBuf::from(project_path))
+    }
+
+    /// All linked worktrees of a project, flagging the ones owned by a session.
+    /// Session worktrees are named after the session id.
+    pub async fn list_worktrees(&self, project_path: &str) -> AppResult<Vec<WorktreeInfo>> {
+        let mut worktrees = WorktreeManager::list_worktrees(&PathBuf::from(project_path))?;
+        let sessions = self.sessions.read().await;
+        for worktree in &mut worktrees {
+            worktree.is_session = sessions
+                .get(&worktree.name)
+                .is_some_and(|entry| !entry.session.is_local);
+        }
+        Ok(worktrees)
+    }
+
+    /// Remove all session worktrees and branches of a project and mark the
+    /// sessions that used them terminated. Returns the removed worktree ids.
+    pub async fn cleanup_project_worktrees(&self, project_path: &str) -> AppResult<Vec<String>> {
+        let project_path = PathBuf::from(project_path);
+        let branch_prefix = self.settings_manager.branch_prefix(&project_path);
+        let removed = WorktreeManager::remove_all_worktrees(&project_path, &branch_prefix)?;
+
+        for session_id in &removed {
+            let known = self.sessions.read().await.contains_key(session_id);
+            if !known {
+                continue;
+            }
+            if let Err(e) = self.terminate_session(session_id, false).await {
+                eprintln!(
+                    "[SessionManager] Failed to terminate session {} after removing its worktree: {}",
+                    session_id, e
+                );
+            }
+        }
+
+        Ok(removed)
+    }
+
+    /// Make room for a new worktree according to `max_worktrees_per_project`.
+    /// With the "auto_evict" policy the worktrees of the oldest terminated
+    /// sessions are removed; otherwise an error listing cleanup candidates is
+    /// returned.
+    async fn enforce_worktree_limit(&self, project_path: &str) -> AppResult<()> {
+        let advanced = self
+            .settings_manager
+            .get_settings()
+            .advanced
+            .unwrap_or_default();
+        let max = match advanced.max_worktrees_per_project {
+            Some(max) if max > 0 => max,
+            _ => return Ok(()),
+        };
+        let auto_evict = advanced.worktree_limit_policy.as_deref() == Some("auto_evict");
+
+        let terminated: Vec<Session> = {
+            let sessions = self.sessions.read().await;
+            sessions
+                .values()
+                .map(|e| &e.session)
+                .filter(|s| s.project_path == project_path && s.status == SessionStatus::Terminated)
+                .cloned()
+                .collect()
+        };
+        make_room_for_worktree(Path::new(project_path), terminated, max, auto_evict)?;
+        Ok(())
+    }
+
     /// Spawn a background task to establish the ACP connection for a creating session
     fn spawn_acp_connection(
         &self,
@@ -235,6 +1602,13 @@ impl SessionManager {
         let db = self.db.clone();
         let app_handle = self.app_handle.clone();
         let settings_manager = self.settings_manager.clone();
+        let creation_progress = self.creation_progress.clone();
+        let active_tool_calls = self.active_tool_calls.clone();
+        let pending_message_meta = self.pending_message_meta.clone();
+        let stream_forwarders = self.stream_forwarders.clone();
+        let activity_logs = self.activity_logs.clone();
+        let message_waiters = self.message_waiters.clone();
+        let turn_started = self.turn_started.clone();
         let mcp_servers = self.mcp_manager.get_enabled_acp_servers_for_directory(
             &project_path,
             &excluded_mcp_ids,
@@ -254,6 +1628,7 @@ impl SessionManager {
                             "[SessionManager] Failed to create adapter for {}: [{}] {}",
                             session_id, session_error.code, session_error.message
                         );
+                        creation_progress.write().await.remove(&session_id);
                         let mut sessions_guard = sessions.write().await;
                         if let Some(entry) = sessions_guard.get_mut(&session_id) {
                             entry.session.status = SessionStatus::Error;
@@ -271,22 +1646,44 @@ impl SessionManager {
                 };
 
             // Create channel for streaming
-            let (tx, mut rx) = mpsc::channel::<StreamChunk>(100);
+            let (tx, mut rx) =
+                mpsc::channel::<StreamChunk>(stream_channel_capacity(&settings_manager));
 
             // Forward stream chunks to frontend via Tauri events
             let app_handle_for_stream = app_handle.clone();
+            let db_for_stream = db.clone();
             let session_id_for_log = session_id.clone();
-            tokio::spawn(async move {
+            let forwarder = tokio::spawn(async move {
                 println!("[SessionManager] Starting stream forwarder for session {}", session_id_for_log);
                 while let Some(chunk) = rx.recv().await {
                     println!("[SessionManager] Forwarding stream chunk: session={}, message_id={}, is_complete={}",
                         chunk.session_id, chunk.message_id, chunk.is_complete);
+                    persist_stream_chunk(&app_handle_for_stream, &db_for_stream, &chunk);
+                    record_chunk_activity(&activity_logs, &active_tool_calls, &chunk).await;
+                    notify_message_waiter(&message_waiters, &chunk).await;
+                    track_tool_call(&active_tool_calls, &chunk).await;
+                    record_tool_call_meta(&pending_message_meta, &chunk).await;
                     if let Err(e) = app_handle_for_stream.emit("stream-chunk", &chunk) {
                         eprintln!("[SessionManager] Failed to emit stream-chunk event: {}", e);
                     }
+                    // A failed prompt has no turn to time
+                    if chunk.error.is_some() {
+                        turn_started.write().await.remove(&chunk.session_id);
+                    } else if chunk.is_complete {
+                        spawn_turn_complete(&app_handle_for_stream, &chunk.session_id);
+                    }
                 }
                 println!("[SessionManager] Stream forwarder ended for session {}", session_id_for_log);
             });
+            replace_stream_forwarder(&stream_forwarders, &session_id, forwarder).await;
+
+            emit_progress(
+                &app_handle,
+                &creation_progress,
+                &session_id,
+                SessionProgressPhase::SpawningProcess,
+            )
+            .await;
 
             // Start the ACP session
             let result = adapter
@@ -310,6 +1707,7 @@ impl SessionManager {
                         entry.session.status = SessionStatus::Active;
                         entry.session.acp_session_id =
                             adapter.acp_session_id().map(|s| s.to_string());
+                        entry.session.acp_cwd = adapter.acp_cwd().map(|s| s.to_string());
                         let available_models = adapter.available_models();
                         println!(
                             "[SessionManager] Create session '{}': available_models from adapter = {:?}",
@@ -349,6 +1747,15 @@ impl SessionManager {
                         }
 
                         println!("[SessionManager] Session {} is now Active", session_id);
+                        drop(sessions_guard);
+
+                        emit_progress(
+                            &app_handle,
+                            &creation_progress,
+                            &session_id,
+                            SessionProgressPhase::Ready,
+                        )
+                        .await;
                     }
                 }
                 Err(e) => {
@@ -357,6 +1764,7 @@ impl SessionManager {
                         "[SessionManager] Failed to start ACP session for {}: [{}] {}",
                         session_id, session_error.code, session_error.message
                     );
+                    creation_progress.write().await.remove(&session_id);
 
                     // Update session to Error state with error info
                     let mut sessions_guard = sessions.write().await;
@@ -396,10 +1804,292 @@ impl SessionManager {
         });
     }
 
-    /// List all sessions
+    /// Report that a creating session entered a new phase (see `emit_progress`)
+    pub async fn report_progress(&self, session_id: &str, phase: SessionProgressPhase) {
+        emit_progress(&self.app_handle, &self.creation_progress, session_id, phase).await;
+    }
+
+    /// List all sessions, pinned first, then most recently active
     pub async fn list_sessions(&self) -> Vec<Session> {
         let sessions = self.sessions.read().await;
-        sessions.values().map(|e| e.session.clone()).collect()
+        let mut list: Vec<Session> = sessions.values().map(|e| e.session.clone()).collect();
+        list.sort_by(|a, b| {
+            b.pinned.cmp(&a.pinned).then_with(|| {
+                b.updated_at
+                    .unwrap_or(b.created_at)
+                    .cmp(&a.updated_at.unwrap_or(a.created_at))
+            })
+        });
+        list
+    }
+
+    /// Re-read sessions from the database, e.g. after an import, recovery or a
+    /// write from another instance. Sessions with a live adapter or still being
+    /// created keep their in-memory state (status, plan, commands, models) and
+    /// only pick up the stored name, pin, branch and system prompt.
+    pub async fn reload_from_db(&self) -> AppResult<Vec<Session>> {
+        let creating: HashSet<String> =
+            self.creation_progress.read().await.keys().cloned().collect();
+        reload_entries(&self.sessions, &self.db, &creating).await?;
+
+        Ok(self.list_sessions().await)
+    }
+
+    /// Git overview (ahead/behind, dirty) for every session of a project
+    pub async fn project_sessions_overview(
+        &self,
+        project_path: &str,
+    ) -> AppResult<Vec<SessionGitOverview>> {
+        let session_ids: Vec<String> = {
+            let sessions = self.sessions.read().await;
+            sessions
+                .values()
+                .filter(|e| e.session.project_path == project_path)
+                .map(|e| e.session.id.clone())
+                .collect()
+        };
+        let project_path = PathBuf::from(project_path);
+        let branch_prefix = self.settings_manager.branch_prefix(&project_path);
+        WorktreeManager::project_sessions_overview(&project_path, &session_ids, &branch_prefix)
+    }
+
+    /// Agent sessions of a project that exist in the agents' own storage but not
+    /// in Forkestra's database, most recently updated first. Only agents whose
+    /// storage can be enumerated contribute results.
+    pub async fn discover_orphan_agent_sessions(
+        &self,
+        project_path: &str,
+    ) -> AppResult<Vec<AgentSessionInfo>> {
+        let project = PathBuf::from(project_path);
+        let mut cwds = vec![project.clone()];
+        if WorktreeManager::is_git_repo(&project) {
+            cwds.push(WorktreeManager::get_worktree_base_path(&project)?);
+        }
+
+        let known: HashSet<String> = self
+            .db
+            .load_sessions()?
+            .into_iter()
+            .filter_map(|s| s.acp_session_id)
+            .collect();
+
+        let custom_providers = self.settings_manager.get_settings().custom_providers;
+        let mut orphans = Vec::new();
+        for def in builtin_definitions().iter().chain(custom_providers.iter()) {
+            let provider = ProviderType::from_id(&def.id);
+            let adapter = create_adapter(&provider, &self.settings_manager)?;
+            match adapter.list_agent_sessions(&cwds) {
+                Ok(found) => orphans.extend(
+                    found
+                        .into_iter()
+                        .filter(|s| !known.contains(&s.acp_session_id)),
+                ),
+                Err(e) => eprintln!(
+                    "[SessionManager] Failed to list {} sessions: {}",
+                    def.id, e
+                ),
+            }
+        }
+
+        orphans.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+        Ok(orphans)
+    }
+
+    /// Bring an agent session that Forkestra did not create into the session
+    /// list. It is added as a Paused local session in the project directory, so
+    /// resuming it loads the agent's history with `session/load`.
+    pub async fn import_agent_session(
+        &self,
+        project_path: &str,
+        acp_session_id: &str,
+        name: &str,
+        provider: ProviderType,
+    ) -> AppResult<Session> {
+        let session = import_entry(
+            &self.sessions,
+            &self.db,
+            project_path,
+            acp_session_id,
+            name,
+            provider,
+        )
+        .await?;
+        self.record_feed_event(
+            &session.id,
+            FeedEventKind::SessionCreated,
+            &format!("Imported session {}", session.name),
+        );
+
+        println!(
+            "[SessionManager] Imported agent session {} as {}",
+            acp_session_id.trim(),
+            session.id
+        );
+        Ok(session)
+    }
+
+    /// Sessions whose adapter is live, with their current model and mode.
+    /// Based on adapter state rather than the stored status.
+    pub async fn list_active_sessions(&self) -> Vec<ActiveSessionInfo> {
+        let candidates: Vec<(Session, Arc<tokio::sync::Mutex<Box<dyn ProviderAdapter>>>)> = {
+            let sessions = self.sessions.read().await;
+            sessions
+                .values()
+                .filter_map(|e| e.adapter.clone().map(|a| (e.session.clone(), a)))
+                .collect()
+        };
+
+        let mut active = Vec::new();
+        for (session, adapter) in candidates {
+            if !adapter.lock().await.is_active() {
+                continue;
+            }
+            active.push(ActiveSessionInfo {
+                last_activity_at: session.updated_at.unwrap_or(session.created_at),
+                session_id: session.id,
+                name: session.name,
+                project_path: session.project_path,
+                provider: session.provider,
+                model: session.model,
+                current_mode: session.mode,
+            });
+        }
+        active.sort_by(|a, b| b.last_activity_at.cmp(&a.last_activity_at));
+        active
+    }
+
+    /// Case-insensitive substring search over session names and project paths,
+    /// most recently active first
+    pub async fn search_sessions(&self, query: &str) -> Vec<Session> {
+        let query = query.trim().to_lowercase();
+        let sessions = self.sessions.read().await;
+        let mut matches: Vec<Session> = sessions
+            .values()
+            .map(|e| &e.session)
+            .filter(|s| {
+                query.is_empty()
+                    || s.name.to_lowercase().contains(&query)
+                    || s.project_path.to_lowercase().contains(&query)
+            })
+            .cloned()
+            .collect();
+        matches.sort_by(|a, b| {
+            b.updated_at
+                .unwrap_or(b.created_at)
+                .cmp(&a.updated_at.unwrap_or(a.created_at))
+        });
+        matches
+    }
+
+    /// Write a session transcript straight to disk, avoiding a round-trip of the
+    /// whole transcript through the IPC bridge. Returns the written path.
+    pub async fn export_session_to_file(
+        &self,
+        session_id: &str,
+        destination: &str,
+        format: ExportFormat,
+    ) -> AppResult<String> {
+        let session = self.get_session(session_id).await?;
+        let destination = PathBuf::from(destination);
+        check_export_destination(&destination)?;
+
+        let db = self.db.clone();
+        let session_id = session_id.to_string();
+        tokio::task::spawn_blocking(move || -> AppResult<String> {
+            let messages = db.get_messages(&session_id)?;
+            let contents = match format {
+                ExportFormat::Markdown => render_transcript_markdown(&session, &messages),
+                ExportFormat::Json => serde_json::to_string_pretty(&serde_json::json!({
+                    "session": session,
+                    "messages": messages,
+                }))
+                .map_err(|e| AppError::Internal(format!("Failed to serialize transcript: {}", e)))?,
+            };
+            std::fs::write(&destination, contents)?;
+            Ok(destination.to_string_lossy().to_string())
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))?
+    }
+
+    /// Write the session branch's commits as a `git format-patch`-style patch file.
+    /// Returns the path written.
+    pub async fn export_session_patch(
+        &self,
+        session_id: &str,
+        destination: &str,
+    ) -> AppResult<String> {
+        let session = self.get_session(session_id).await?;
+        if session.is_local {
+            return Err(AppError::InvalidOperation(
+                "Local sessions do not have their own branch".to_string(),
+            ));
+        }
+        let destination = PathBuf::from(destination);
+        check_export_destination(&destination)?;
+
+        let project_path = PathBuf::from(&session.project_path);
+        let branch_prefix = self.settings_manager.branch_prefix(&project_path);
+        let session_id = session_id.to_string();
+        tokio::task::spawn_blocking(move || -> AppResult<String> {
+            let patch = WorktreeManager::format_patch(&project_path, &session_id, &branch_prefix)?;
+            std::fs::write(&destination, patch)?;
+            Ok(destination.to_string_lossy().to_string())
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("Task join error: {}", e)))?
+    }
+
+    /// Number of sessions in each status, computed from memory only
+    pub async fn status_counts(&self) -> HashMap<SessionStatus, usize> {
+        let sessions = self.sessions.read().await;
+        let mut counts = HashMap::new();
+        for entry in sessions.values() {
+            *counts.entry(entry.session.status.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// List distinct projects with their session counts, most recently active first
+    pub async fn list_projects(&self) -> Vec<ProjectSummary> {
+        let sessions = self.sessions.read().await;
+        let mut projects: HashMap<String, ProjectSummary> = HashMap::new();
+
+        for entry in sessions.values() {
+            let session = &entry.session;
+            let activity = session.updated_at.unwrap_or(session.created_at);
+            projects
+                .entry(session.project_path.clone())
+                .and_modify(|p| {
+                    p.session_count += 1;
+                    if activity > p.last_activity {
+                        p.last_activity = activity;
+                    }
+                })
+                .or_insert_with(|| ProjectSummary {
+                    path: session.project_path.clone(),
+                    name: PathBuf::from(&session.project_path)
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_string())
+                        .unwrap_or_else(|| session.project_path.clone()),
+                    session_count: 1,
+                    last_activity: activity,
+                });
+        }
+
+        let mut result: Vec<ProjectSummary> = projects.into_values().collect();
+        result.sort_by(|a, b| b.last_activity.cmp(&a.last_activity));
+        result
+    }
+
+    /// Distinct project paths, most recently active first, capped at `limit`
+    pub async fn recent_projects(&self, limit: usize) -> Vec<String> {
+        self.list_projects()
+            .await
+            .into_iter()
+            .take(limit)
+            .map(|p| p.path)
+            .collect()
     }
 
     /// Get a session by ID
@@ -411,8 +2101,74 @@ impl SessionManager {
             .ok_or_else(|| AppError::NotFound(format!("Session '{}' not found", session_id)))
     }
 
-    /// Send a message to a session
-    pub async fn send_message(&self, session_id: &str, content: Vec<PromptContent>) -> AppResult<()> {
+    /// Send a message to a session. Returns the message id that the resulting
+    /// stream chunks will carry, so the frontend can create the bubble up front.
+    pub async fn send_message(
+        &self,
+        session_id: &str,
+        content: Vec<PromptContent>,
+    ) -> AppResult<String> {
+        let message_id = uuid::Uuid::new_v4().to_string();
+        self.send_message_with_id(session_id, content, message_id)
+            .await
+    }
+
+    /// Send a text prompt and wait for its turn to complete, returning the
+    /// assistant's text. Unlike `send_message`, this only resolves once the
+    /// turn is done, and fails if that takes longer than `timeout`.
+    pub async fn send_message_blocking(
+        &self,
+        session_id: &str,
+        message: &str,
+        timeout: Duration,
+    ) -> AppResult<String> {
+        // Registered before sending so no chunk of the turn is missed
+        let message_id = uuid::Uuid::new_v4().to_string();
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        self.message_waiters.write().await.insert(
+            message_id.clone(),
+            MessageWaiter {
+                session_id: session_id.to_string(),
+                tx,
+            },
+        );
+
+        let content = vec![PromptContent::Text {
+            text: message.to_string(),
+        }];
+        let result = tokio::time::timeout(timeout, async {
+            self.send_message_with_id(session_id, content, message_id.clone())
+                .await?;
+            collect_turn_text(&mut rx).await
+        })
+        .await;
+        self.message_waiters.write().await.remove(&message_id);
+
+        result.map_err(|_| {
+            AppError::Provider(format!(
+                "Turn did not complete within {}s",
+                timeout.as_secs()
+            ))
+        })?
+    }
+
+    /// `send_message` with a message id chosen by the caller
+    async fn send_message_with_id(
+        &self,
+        session_id: &str,
+        content: Vec<PromptContent>,
+        message_id: String,
+    ) -> AppResult<String> {
+        let prompt_summary = content
+            .iter()
+            .map(|c| match c {
+                PromptContent::Text { text } => text.as_str(),
+                PromptContent::Image(_) => "[image]",
+                PromptContent::ResourceLink(link) => link.name.as_str(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+
         // Get adapter clone
         let adapter = {
             let sessions = self.sessions.read().await;
@@ -451,9 +2207,45 @@ impl SessionManager {
                 content
             };
 
+            let (final_content, prepend_system_prompt) = with_system_prompt(
+                &self.sessions,
+                &self.system_prompt_sent,
+                session_id,
+                final_content,
+            )
+            .await;
+
+            record_activity(
+                &self.activity_logs,
+                session_id,
+                ActivityKind::Prompt,
+                &prompt_summary,
+            )
+            .await;
             let mut adapter = adapter.lock().await;
-            adapter.send_message(final_content).await?;
-            Ok(())
+            if let Err(e) = adapter.send_message(final_content, &message_id).await {
+                let summary = format!("Prompt failed: {}", e);
+                record_activity(
+                    &self.activity_logs,
+                    session_id,
+                    ActivityKind::Error,
+                    &summary,
+                )
+                .await;
+                return Err(e);
+            }
+            self.record_feed_event(session_id, FeedEventKind::Message, &prompt_summary);
+            if prepend_system_prompt {
+                self.system_prompt_sent
+                    .write()
+                    .await
+                    .insert(session_id.to_string());
+            }
+            self.turn_started
+                .write()
+                .await
+                .insert(session_id.to_string(), Utc::now());
+            Ok(message_id)
         } else {
             Err(AppError::NotFound(format!(
                 "Session '{}' not found or not active",
@@ -462,6 +2254,141 @@ impl SessionManager {
         }
     }
 
+    /// Send one of the agent's advertised slash commands. ACP agents parse slash
+    /// commands out of the prompt text, so this sends `/name args` as a message.
+    pub async fn send_command(
+        &self,
+        session_id: &str,
+        command_name: &str,
+        args: Option<&str>,
+    ) -> AppResult<String> {
+        let command_name = command_name.trim().trim_start_matches('/');
+        let known = self
+            .get_session_commands(session_id)
+            .await?
+            .iter()
+            .any(|c| c.name == command_name);
+        if !known {
+            return Err(AppError::InvalidOperation(format!(
+                "Unknown command '/{}' for session '{}'",
+                command_name, session_id
+            )));
+        }
+
+        let text = match args.map(str::trim).filter(|a| !a.is_empty()) {
+            Some(args) => format!("/{} {}", command_name, args),
+            None => format!("/{}", command_name),
+        };
+        self.send_message(session_id, vec![PromptContent::Text { text }])
+            .await
+    }
+
+    /// Persist the duration of the turn that just finished, if its start was seen
+    async fn record_turn_timing(&self, session_id: &str) {
+        let Some(started_at) = self.turn_started.write().await.remove(session_id) else {
+            return;
+        };
+        let ended_at = Utc::now();
+        let timing = TurnTiming {
+            started_at,
+            ended_at,
+            duration_ms: (ended_at - started_at).num_milliseconds().max(0) as u64,
+        };
+        if let Err(e) = self.db.save_turn_timing(session_id, &timing) {
+            eprintln!("[SessionManager] Failed to save turn timing: {}", e);
+        }
+    }
+
+    /// Total lifetime, active agent time and per-turn durations of a session
+    pub async fn get_session_timing(&self, session_id: &str) -> AppResult<SessionTiming> {
+        let session = self.get_session(session_id).await?;
+        let turns = self.db.get_turn_timings(session_id)?;
+
+        Ok(SessionTiming {
+            session_id: session_id.to_string(),
+            elapsed_ms: (Utc::now() - session.created_at).num_milliseconds().max(0) as u64,
+            total_active_ms: turns.iter().map(|t| t.duration_ms).sum(),
+            turns,
+        })
+    }
+
+    /// Local snapshot of session, database and thread load
+    pub async fn get_performance_metrics(&self) -> AppResult<PerformanceMetrics> {
+        let (total_sessions, active_sessions) = {
+            let sessions = self.sessions.read().await;
+            let active = sessions.values().filter(|e| e.adapter.is_some()).count();
+            (sessions.len(), active)
+        };
+        let running_tool_calls = self
+            .active_tool_calls
+            .read()
+            .await
+            .values()
+            .map(|calls| calls.len())
+            .sum();
+
+        Ok(PerformanceMetrics {
+            total_sessions,
+            active_sessions,
+            pending_creations: self.creation_progress.read().await.len(),
+            running_turns: self.turn_started.read().await.len(),
+            running_tool_calls,
+            database: self.db.metrics()?,
+            thread_count: process_thread_count(),
+        })
+    }
+
+    /// Called when a prompt turn finishes. With `auto_commit_per_turn` enabled,
+    /// commits the worktree of non-local sessions and emits `turn-committed`.
+    pub async fn on_turn_complete(&self, session_id: &str) {
+        let turn = {
+            let mut counts = self.turn_counts.write().await;
+            let count = counts.entry(session_id.to_string()).or_insert(0);
+            *count += 1;
+            *count
+        };
+        self.record_turn_timing(session_id).await;
+
+        let session = {
+            let sessions = self.sessions.read().await;
+            sessions.get(session_id).map(|e| e.session.clone())
+        };
+        let Some(session) = session else {
+            return;
+        };
+        let settings_manager = self.settings_manager.clone();
+        let result =
+            tokio::task::spawn_blocking(move || commit_turn(&settings_manager, &session, turn))
+                .await;
+
+        match result {
+            Ok(Ok(Some(sha))) => {
+                println!(
+                    "[SessionManager] Committed turn {} of session {}: {}",
+                    turn, session_id, sha
+                );
+                let event = TurnCommittedEvent {
+                    session_id: session_id.to_string(),
+                    turn,
+                    sha,
+                };
+                if let Err(e) = self.app_handle.emit("turn-committed", &event) {
+                    eprintln!("[SessionManager] Failed to emit turn-committed event: {}", e);
+                }
+            }
+            Ok(Ok(None)) => {}
+            Ok(Err(e)) => {
+                eprintln!(
+                    "[SessionManager] Failed to auto-commit turn {} of session {}: {}",
+                    turn, session_id, e
+                );
+            }
+            Err(e) => {
+                eprintln!("[SessionManager] Auto-commit task failed: {}", e);
+            }
+        }
+    }
+
     /// Refresh skills injection for a session — clears the injected flag so the
     /// next message will re-inject the latest enabled skill contents.
     pub async fn refresh_skills_in_session(&self, session_id: &str) {
@@ -474,7 +2401,7 @@ impl SessionManager {
         &self,
         session_id: &str,
         cleanup_worktree: bool,
-    ) -> AppResult<()> {
+    ) -> AppResult<CommandResult<()>> {
         // Get the entry and update its status in memory
         let entry_data = {
             let mut sessions = self.sessions.write().await;
@@ -489,6 +2416,9 @@ impl SessionManager {
         };
 
         if let Some((session, adapter)) = entry_data {
+            drop_message_waiters(&self.message_waiters, session_id).await;
+            self.turn_started.write().await.remove(session_id);
+
             // Terminate the adapter if it exists
             if let Some(adapter) = adapter {
                 let mut adapter = adapter.lock().await;
@@ -507,10 +2437,23 @@ impl SessionManager {
             }
 
             // Cleanup worktree if requested and not a local session
+            let mut warnings = Vec::new();
             if cleanup_worktree && !session.is_local {
                 let project_path = PathBuf::from(&session.project_path);
                 let worktree_path = PathBuf::from(&session.worktree_path);
-                WorktreeManager::remove_worktree(&project_path, session_id, &worktree_path)?;
+                let branch_prefix = self.settings_manager.branch_prefix(&project_path);
+                let leftover = WorktreeManager::remove_worktree(
+                    &project_path,
+                    session_id,
+                    &worktree_path,
+                    &branch_prefix,
+                )?;
+                if !leftover.is_empty() {
+                    warnings.push(format!(
+                        "Some worktree files could not be removed: {}",
+                        leftover.join(", ")
+                    ));
+                }
             }
 
             // Remove from DB and memory entirely when cleanup is requested
@@ -521,11 +2464,15 @@ impl SessionManager {
                         e
                     );
                 }
+                self.stream_forwarders.write().await.remove(session_id);
                 let mut sessions = self.sessions.write().await;
                 sessions.remove(session_id);
             }
 
-            Ok(())
+            Ok(CommandResult {
+                value: (),
+                warnings,
+            })
         } else {
             Err(AppError::NotFound(format!(
                 "Session '{}' not found",
@@ -534,12 +2481,176 @@ impl SessionManager {
         }
     }
 
+    /// Forget a session: stop its agent and remove it from the database and memory,
+    /// leaving the worktree and branch untouched on disk.
+    pub async fn forget_session(&self, session_id: &str) -> AppResult<()> {
+        let session = forget_entry(&self.sessions, &self.db, session_id).await?;
+
+        self.skills_injected.write().await.remove(session_id);
+        self.system_prompt_sent.write().await.remove(session_id);
+        self.creation_progress.write().await.remove(session_id);
+        self.stream_forwarders.write().await.remove(session_id);
+        self.activity_logs.write().await.remove(session_id);
+        drop_message_waiters(&self.message_waiters, session_id).await;
+        self.turn_started.write().await.remove(session_id);
+
+        println!(
+            "[SessionManager] Forgot session {} (worktree kept at {})",
+            session_id, session.worktree_path
+        );
+        Ok(())
+    }
+
     /// Merge session changes to a branch
     pub async fn merge_session(&self, session_id: &str, target_branch: &str) -> AppResult<()> {
         let session = self.get_session(session_id).await?;
         let project_path = PathBuf::from(&session.project_path);
 
-        WorktreeManager::merge_to_branch(&project_path, session_id, target_branch)
+        let branch_prefix = self.settings_manager.branch_prefix(&project_path);
+
+        WorktreeManager::merge_to_branch(&project_path, session_id, target_branch, &branch_prefix)?;
+        self.record_feed_event(
+            session_id,
+            FeedEventKind::Merged,
+            &format!("Merged into {}", target_branch),
+        );
+        Ok(())
+    }
+
+    /// Commits the session branch is ahead of and behind `base_branch`,
+    /// defaulting to the project's default branch
+    pub async fn session_ahead_behind(
+        &self,
+        session_id: &str,
+        base_branch: Option<&str>,
+    ) -> AppResult<(usize, usize)> {
+        let session = self.get_session(session_id).await?;
+        if session.is_local {
+            return Err(AppError::InvalidOperation(
+                "Local sessions do not have their own branch".to_string(),
+            ));
+        }
+
+        let project_path = PathBuf::from(&session.project_path);
+        let base_branch = match base_branch {
+            Some(branch) => branch.to_string(),
+            None => WorktreeManager::get_default_branch(&project_path)?,
+        };
+
+        let branch_prefix = self.settings_manager.branch_prefix(&project_path);
+        WorktreeManager::ahead_behind(&project_path, session_id, &base_branch, &branch_prefix)
+    }
+
+    /// Unlock the session's worktree and repair its links to the repository
+    pub async fn verify_worktree(&self, session_id: &str) -> AppResult<WorktreeRepairReport> {
+        let session = self.get_session(session_id).await?;
+        if session.is_local {
+            return Err(AppError::InvalidOperation(
+                "Local sessions do not have a worktree".to_string(),
+            ));
+        }
+
+        WorktreeManager::verify_and_unlock(
+            Path::new(&session.project_path),
+            session_id,
+            Path::new(&session.worktree_path),
+        )
+    }
+
+    /// Preview which files merging the session into `target_branch` would change
+    pub async fn preview_session_merge(
+        &self,
+        session_id: &str,
+        target_branch: &str,
+    ) -> AppResult<Vec<FileDiff>> {
+        let session = self.get_session(session_id).await?;
+        let project_path = PathBuf::from(&session.project_path);
+
+        let branch_prefix = self.settings_manager.branch_prefix(&project_path);
+
+        WorktreeManager::preview_merge(&project_path, session_id, target_branch, &branch_prefix)
+    }
+
+    /// Keep a session's work as a regular branch: stop the agent, rename the
+    /// branch out of the session namespace and remove the worktree
+    pub async fn graduate_session(
+        &self,
+        session_id: &str,
+        new_branch_name: &str,
+    ) -> AppResult<String> {
+        let session = self.get_session(session_id).await?;
+        if session.is_local {
+            return Err(AppError::InvalidOperation(
+                "Local sessions do not have their own branch".to_string(),
+            ));
+        }
+
+        // Reject a bad name before the agent is stopped
+        let project_path = PathBuf::from(&session.project_path);
+        let branch_prefix = self.settings_manager.branch_prefix(&project_path);
+        WorktreeManager::check_graduation(
+            &project_path,
+            session_id,
+            new_branch_name,
+            &branch_prefix,
+        )?;
+
+        self.terminate_session(session_id, false).await?;
+
+        let branch_name = WorktreeManager::graduate_branch(
+            &project_path,
+            session_id,
+            new_branch_name,
+            &PathBuf::from(&session.worktree_path),
+            &branch_prefix,
+        )?;
+
+        {
+            let mut sessions = self.sessions.write().await;
+            if let Some(entry) = sessions.get_mut(session_id) {
+                entry.session.branch_name = branch_name.clone();
+            }
+        }
+        if let Err(e) = self.db.update_session_branch(session_id, &branch_name) {
+            eprintln!(
+                "[SessionManager] Failed to update session branch in DB: {}",
+                e
+            );
+        }
+
+        Ok(branch_name)
+    }
+
+    /// Delete a stopped session's branch while leaving its worktree on disk
+    pub async fn delete_session_branch(&self, session_id: &str) -> AppResult<()> {
+        let session = self.get_session(session_id).await?;
+        if session.is_local {
+            return Err(AppError::InvalidOperation(
+                "Local sessions do not have their own branch".to_string(),
+            ));
+        }
+        if matches!(
+            session.status,
+            SessionStatus::Creating | SessionStatus::Active
+        ) {
+            return Err(AppError::InvalidOperation(
+                "Terminate the session before deleting its branch".to_string(),
+            ));
+        }
+
+        let project_path = PathBuf::from(&session.project_path);
+        WorktreeManager::delete_session_branch(
+            &project_path,
+            session_id,
+            &PathBuf::from(&session.worktree_path),
+            &self.settings_manager.branch_prefix(&project_path),
+        )
+    }
+
+    /// Whether `resume_session` can succeed: the session is stopped, has an
+    /// ACP session ID to resume, and no live adapter
+    pub async fn is_resumable(&self, session_id: &str) -> bool {
+        is_resumable_entry(&self.sessions, session_id).await
     }
 
     /// Resume a terminated/paused session by re-establishing the ACP connection
@@ -567,18 +2678,33 @@ impl SessionManager {
         };
 
         let worktree_path = PathBuf::from(&session.worktree_path);
-        let project_path = PathBuf::from(&session.project_path);
+        let acp_cwd = agent_cwd(&session);
 
         // Create provider adapter with settings
         let mut adapter = create_adapter(&session.provider, &self.settings_manager)?;
 
+        // Let `cancel_resume` abort the handshake while it is running
+        let cancel = CancellationToken::new();
+        adapter.set_handshake_cancellation(cancel.clone());
+        self.resume_cancellations
+            .write()
+            .await
+            .insert(session_id.to_string(), cancel.clone());
+
         // Create channel for streaming
-        let (tx, mut rx) = mpsc::channel::<StreamChunk>(100);
+        let (tx, mut rx) =
+            mpsc::channel::<StreamChunk>(stream_channel_capacity(&self.settings_manager));
 
         // Forward stream chunks to frontend via Tauri events
         let app_handle = self.app_handle.clone();
+        let db = self.db.clone();
+        let active_tool_calls = self.active_tool_calls.clone();
+        let pending_message_meta = self.pending_message_meta.clone();
+        let activity_logs = self.activity_logs.clone();
+        let message_waiters = self.message_waiters.clone();
+        let turn_started = self.turn_started.clone();
         let session_id_for_log = session_id.to_string();
-        tokio::spawn(async move {
+        let forwarder = tokio::spawn(async move {
             println!(
                 "[SessionManager] Starting stream forwarder for resumed session {}",
                 session_id_for_log
@@ -588,15 +2714,27 @@ impl SessionManager {
                     "[SessionManager] Forwarding stream chunk: session={}, message_id={}, is_complete={}",
                     chunk.session_id, chunk.message_id, chunk.is_complete
                 );
+                persist_stream_chunk(&app_handle, &db, &chunk);
+                record_chunk_activity(&activity_logs, &active_tool_calls, &chunk).await;
+                notify_message_waiter(&message_waiters, &chunk).await;
+                track_tool_call(&active_tool_calls, &chunk).await;
+                record_tool_call_meta(&pending_message_meta, &chunk).await;
                 if let Err(e) = app_handle.emit("stream-chunk", &chunk) {
                     eprintln!("[SessionManager] Failed to emit stream-chunk event: {}", e);
                 }
+                // A failed prompt has no turn to time
+                if chunk.error.is_some() {
+                    turn_started.write().await.remove(&chunk.session_id);
+                } else if chunk.is_complete {
+                    spawn_turn_complete(&app_handle, &chunk.session_id);
+                }
             }
             println!(
                 "[SessionManager] Stream forwarder ended for resumed session {}",
                 session_id_for_log
             );
         });
+        replace_stream_forwarder(&self.stream_forwarders, session_id, forwarder).await;
 
         // Get enabled MCP servers for this session's project directory
         let mcp_servers = self.mcp_manager.get_enabled_acp_servers_for_directory(
@@ -615,7 +2753,7 @@ impl SessionManager {
                     session_id,
                     acp_session_id,
                     &worktree_path,
-                    &project_path,
+                    &acp_cwd,
                     tx.clone(),
                     self.app_handle.clone(),
                     mcp_servers.clone(),
@@ -627,8 +2765,22 @@ impl SessionManager {
             ))
         };
 
+        // A cancelled resume must not fall back to a new session
+        if cancel.is_cancelled() {
+            self.resume_cancellations.write().await.remove(session_id);
+            println!(
+                "[SessionManager] Resume of session {} was cancelled",
+                session_id
+            );
+            return Err(AppError::InvalidOperation(
+                "Resume was cancelled".to_string(),
+            ));
+        }
+
+        note_resumed_history(&self.system_prompt_sent, session_id, load_result.is_ok()).await;
+
         // If load failed, fall back to creating a new session
-        if let Err(ref e) = load_result {
+        let started = if let Err(ref e) = load_result {
             println!(
                 "[SessionManager] Session {} load failed ({}), creating new session instead",
                 session_id, e
@@ -641,13 +2793,33 @@ impl SessionManager {
                     self.app_handle.clone(),
                     mcp_servers,
                 )
-                .await?;
-        }
+                .await
+        } else {
+            Ok(())
+        };
+        self.resume_cancellations.write().await.remove(session_id);
+        started?;
 
         // Get the (possibly updated) ACP session ID and models from the adapter
         let new_acp_session_id = adapter.acp_session_id().map(|s| s.to_string());
+        let new_acp_cwd = adapter.acp_cwd().map(|s| s.to_string());
         let new_available_models = adapter.available_models();
-        let new_current_model_id = adapter.current_model_id().map(|s| s.to_string());
+        let mut new_current_model_id = adapter.current_model_id().map(|s| s.to_string());
+
+        // The agent starts on its default model; re-select the one the user chose
+        if let Some(model_id) = session.model.as_deref() {
+            let available = new_available_models.is_empty()
+                || new_available_models.iter().any(|m| m.model_id == model_id);
+            if new_current_model_id.as_deref() != Some(model_id) && available {
+                match adapter.set_model(model_id).await {
+                    Ok(()) => new_current_model_id = Some(model_id.to_string()),
+                    Err(e) => eprintln!(
+                        "[SessionManager] Failed to re-apply model '{}' on resume of {}: {}",
+                        model_id, session_id, e
+                    ),
+                }
+            }
+        }
         let new_config_options = adapter.config_options();
         println!(
             "[SessionManager] Resume session '{}': available_models from adapter = {:?}",
@@ -662,9 +2834,12 @@ impl SessionManager {
                 if let Some(ref acp_id) = new_acp_session_id {
                     entry.session.acp_session_id = Some(acp_id.clone());
                 }
+                if new_acp_cwd.is_some() {
+                    entry.session.acp_cwd = new_acp_cwd.clone();
+                }
                 entry.session.available_models = new_available_models;
-                if entry.session.model.is_none() {
-                    entry.session.model = new_current_model_id;
+                if new_current_model_id.is_some() {
+                    entry.session.model = new_current_model_id.clone();
                 }
                 entry.session.available_modes = adapter.available_modes();
                 if entry.session.mode.is_none() {
@@ -701,6 +2876,11 @@ impl SessionManager {
                 );
             }
         }
+        if let Some(ref cwd) = new_acp_cwd {
+            if let Err(e) = self.db.update_session_acp_cwd(session_id, Some(cwd)) {
+                eprintln!("[SessionManager] Failed to update ACP cwd in DB: {}", e);
+            }
+        }
 
         // Emit status event to frontend
         let event = crate::models::SessionStatusEvent {
@@ -716,276 +2896,1703 @@ impl SessionManager {
             );
         }
 
-        println!("[SessionManager] Session {} resumed successfully", session_id);
+        println!("[SessionManager] Session {} resumed successfully", session_id);
+
+        Ok(updated_session)
+    }
+
+    /// Cancel a `resume_session` that is still waiting on the agent handshake.
+    /// The spawned agent is killed and the resume returns an error.
+    /// Returns false if the session is not being resumed.
+    pub async fn cancel_resume(&self, session_id: &str) -> AppResult<bool> {
+        let Some(cancel) = self.resume_cancellations.write().await.remove(session_id) else {
+            return Ok(false);
+        };
+        cancel.cancel();
+        println!(
+            "[SessionManager] Cancelling resume of session {}",
+            session_id
+        );
+        Ok(true)
+    }
+
+    /// With `auto_resume_on_startup` enabled, resume the sessions that were
+    /// active when the app last closed. A few resume at a time, a failure only
+    /// affects its own session, and `auto-resume-progress` is emitted as each
+    /// one finishes.
+    pub async fn auto_resume_sessions(&self) {
+        let enabled = self
+            .settings_manager
+            .get_settings()
+            .general
+            .and_then(|g| g.auto_resume_on_startup)
+            .unwrap_or(false);
+        if !enabled {
+            return;
+        }
+
+        auto_resume(
+            &self.sessions,
+            &self.startup_active_sessions,
+            |session_id| async move { self.resume_session(&session_id).await },
+            |event| {
+                if let Err(e) = self.app_handle.emit("auto-resume-progress", &event) {
+                    eprintln!(
+                        "[SessionManager] Failed to emit auto-resume-progress event: {}",
+                        e
+                    );
+                }
+            },
+        )
+        .await;
+    }
+
+    /// Attach to a session whose agent process is still alive in memory
+    /// (e.g. after a frontend reload).
+    ///
+    /// Unlike `resume_session`, this never spawns a new process or re-runs the
+    /// ACP handshake. The existing stream forwarder emits app-wide events, so
+    /// the reconnected frontend only needs a fresh snapshot of the session.
+    pub async fn attach_session(&self, session_id: &str) -> AppResult<Session> {
+        if live_adapter(&self.sessions, session_id).await?.is_none() {
+            println!(
+                "[SessionManager] Agent process for session {} has exited, marking as paused",
+                session_id
+            );
+            if let Err(e) = self
+                .db
+                .update_session_status(session_id, &SessionStatus::Paused)
+            {
+                eprintln!(
+                    "[SessionManager] Failed to update session status in DB: {}",
+                    e
+                );
+            }
+            return Err(AppError::InvalidOperation(format!(
+                "Agent process for session '{}' has exited, resume it instead",
+                session_id
+            )));
+        }
+
+        let session = self.get_session(session_id).await?;
+
+        let event = SessionStatusEvent {
+            session_id: session_id.to_string(),
+            status: session.status.clone(),
+            session: Some(session.clone()),
+            error: None,
+        };
+        if let Err(e) = self.app_handle.emit("session-status-changed", &event) {
+            eprintln!(
+                "[SessionManager] Failed to emit session-status-changed event: {}",
+                e
+            );
+        }
+
+        println!("[SessionManager] Attached to live session {}", session_id);
+
+        Ok(session)
+    }
+
+    /// Re-read provider settings for a session and apply them.
+    ///
+    /// Settings that change how the agent process is launched require a
+    /// respawn: `custom_cli_path`, `env_vars`, and a custom provider's
+    /// `command`/`args`. In that case the adapter is terminated and the session
+    /// is resumed with the same `acp_session_id`. Other settings (e.g.
+    /// `enabled`) don't affect a running process and are left as-is.
+    pub async fn reload_session_settings(&self, session_id: &str) -> AppResult<Session> {
+        let (session, adapter) = {
+            let sessions = self.sessions.read().await;
+            let entry = sessions
+                .get(session_id)
+                .ok_or_else(|| AppError::NotFound(format!("Session '{}' not found", session_id)))?;
+            (entry.session.clone(), entry.adapter.clone())
+        };
+
+        // Without a running adapter the new settings are picked up on next resume
+        let Some(adapter) = adapter else {
+            return Ok(session);
+        };
+
+        let needs_respawn = launch_settings_changed(
+            adapter.lock().await.as_ref(),
+            &session.provider,
+            &self.settings_manager,
+        )?;
+
+        if !needs_respawn {
+            println!(
+                "[SessionManager] Settings for session {} do not require a respawn",
+                session_id
+            );
+            return Ok(session);
+        }
+
+        println!(
+            "[SessionManager] Launch settings changed for session {}, respawning agent",
+            session_id
+        );
+
+        // Detach and terminate the old adapter, then resume with the same ACP session ID
+        {
+            let mut sessions = self.sessions.write().await;
+            if let Some(entry) = sessions.get_mut(session_id) {
+                entry.adapter = None;
+                entry.session.status = SessionStatus::Paused;
+            }
+        }
+        adapter.lock().await.terminate().await?;
+
+        self.resume_session(session_id).await
+    }
+
+    /// Point a local session's agent at a different working directory. The ACP
+    /// cwd is fixed when the agent session starts, so a running agent is respawned.
+    pub async fn set_session_cwd(&self, session_id: &str, path: &str) -> AppResult<Session> {
+        let (session, adapter) = retarget_cwd(&self.sessions, &self.db, session_id, path).await?;
+
+        // Without a running adapter the new cwd is picked up on next resume
+        let Some(adapter) = adapter else {
+            return Ok(session);
+        };
+        adapter.lock().await.terminate().await?;
+
+        self.resume_session(session_id).await
+    }
+
+    /// Rename a session
+    pub async fn rename_session(&self, session_id: &str, new_name: &str) -> AppResult<Session> {
+        let new_name = sanitize_session_name(new_name)?;
+        let mut sessions = self.sessions.write().await;
+        if let Some(entry) = sessions.get_mut(session_id) {
+            entry.session.name = new_name.clone();
+
+            // Persist name change to database
+            if let Err(e) = self.db.update_session_name(session_id, &new_name) {
+                eprintln!(
+                    "[SessionManager] Failed to update session name in DB: {}",
+                    e
+                );
+            }
+
+            Ok(entry.session.clone())
+        } else {
+            Err(AppError::NotFound(format!(
+                "Session '{}' not found",
+                session_id
+            )))
+        }
+    }
+
+    /// Pin or unpin a session so it sorts to the top of the list
+    pub async fn set_session_pinned(&self, session_id: &str, pinned: bool) -> AppResult<Session> {
+        let mut sessions = self.sessions.write().await;
+        let entry = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session '{}' not found", session_id)))?;
+        entry.session.pinned = pinned;
+
+        if let Err(e) = self.db.update_session_pinned(session_id, pinned) {
+            eprintln!(
+                "[SessionManager] Failed to update session pinned in DB: {}",
+                e
+            );
+        }
+
+        Ok(entry.session.clone())
+    }
+
+    /// Tag a session with a hex color (`#rgb` or `#rrggbb`), or clear it with `None`
+    pub async fn set_session_color(
+        &self,
+        session_id: &str,
+        color: Option<&str>,
+    ) -> AppResult<Session> {
+        let color = match color.map(str::trim).filter(|c| !c.is_empty()) {
+            Some(c) => {
+                let digits = c.strip_prefix('#').unwrap_or("");
+                if !matches!(digits.len(), 3 | 6) || !digits.chars().all(|d| d.is_ascii_hexdigit()) {
+                    return Err(AppError::InvalidOperation(format!(
+                        "Invalid color '{}', expected #rgb or #rrggbb",
+                        c
+                    )));
+                }
+                Some(c.to_ascii_lowercase())
+            }
+            None => None,
+        };
+
+        let mut sessions = self.sessions.write().await;
+        let entry = sessions
+            .get_mut(session_id)
+            .ok_or_else(|| AppError::NotFound(format!("Session '{}' not found", session_id)))?;
+        entry.session.ui_color = color;
+
+        if let Err(e) = self
+            .db
+            .update_session_ui_color(session_id, entry.session.ui_color.as_deref())
+        {
+            eprintln!(
+                "[SessionManager] Failed to update session color in DB: {}",
+                e
+            );
+        }
+
+        Ok(entry.session.clone())
+    }
+
+    /// Update branch name for a session (and all co-located local sessions)
+    pub async fn update_session_branch(
+        &self,
+        session_id: &str,
+        new_branch_name: &str,
+    ) -> AppResult<Vec<Session>> {
+        let mut sessions = self.sessions.write().await;
+
+        let entry = sessions.get(session_id).ok_or_else(|| {
+            AppError::NotFound(format!("Session '{}' not found", session_id))
+        })?;
+        let is_local = entry.session.is_local;
+        let project_path = entry.session.project_path.clone();
+
+        let mut updated = Vec::new();
+
+        if is_local {
+            // Update all local sessions sharing the same project_path
+            for entry in sessions.values_mut() {
+                if entry.session.is_local && entry.session.project_path == project_path {
+                    entry.session.branch_name = new_branch_name.to_string();
+                    if let Err(e) = self
+                        .db
+                        .update_session_branch(&entry.session.id, new_branch_name)
+                    {
+                        eprintln!(
+                            "[SessionManager] Failed to update session branch in DB: {}",
+                            e
+                        );
+                    }
+                    updated.push(entry.session.clone());
+                }
+            }
+        } else {
+            // Only update this single session
+            let entry = sessions.get_mut(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session '{}' not found", session_id))
+            })?;
+            entry.session.branch_name = new_branch_name.to_string();
+            if let Err(e) = self
+                .db
+                .update_session_branch(session_id, new_branch_name)
+            {
+                eprintln!(
+                    "[SessionManager] Failed to update session branch in DB: {}",
+                    e
+                );
+            }
+            updated.push(entry.session.clone());
+        }
+
+        Ok(updated)
+    }
+
+    /// Slash commands the agent last advertised for a session
+    pub async fn get_session_commands(&self, session_id: &str) -> AppResult<Vec<AvailableCommand>> {
+        let sessions = self.sessions.read().await;
+        sessions
+            .get(session_id)
+            .map(|e| e.session.available_commands.clone())
+            .ok_or_else(|| AppError::NotFound(format!("Session '{}' not found", session_id)))
+    }
+
+    /// Update available commands for a session
+    pub async fn update_session_commands(&self, session_id: &str, commands: Vec<AvailableCommand>) {
+        let mut sessions = self.sessions.write().await;
+        if let Some(entry) = sessions.get_mut(session_id) {
+            entry.session.available_commands = commands;
+        }
+    }
+
+    /// Update mode for a session (called when agent pushes a mode change via ACP)
+    pub async fn update_session_mode(&self, session_id: &str, mode_id: &str) {
+        let mut sessions = self.sessions.write().await;
+        if let Some(entry) = sessions.get_mut(session_id) {
+            entry.session.mode = Some(mode_id.to_string());
+        }
+    }
+
+    /// Update model for a session (called when the agent reports a model change via ACP).
+    /// Persists the new model and emits `model-changed` only if it actually differs.
+    pub async fn update_session_model(&self, session_id: &str, model_id: &str) {
+        let Some(event) = apply_model_change(&self.sessions, &self.db, session_id, model_id).await
+        else {
+            return;
+        };
+        if let Err(e) = self.app_handle.emit("model-changed", &event) {
+            eprintln!("[SessionManager] Failed to emit model-changed event: {}", e);
+        }
+    }
+
+    /// Append an entry to a session's activity log
+    pub async fn record_activity(&self, session_id: &str, kind: ActivityKind, summary: &str) {
+        record_activity(&self.activity_logs, session_id, kind, summary).await;
+    }
+
+    /// The last `limit` entries of a session's activity log, oldest first
+    pub async fn get_activity_log(
+        &self,
+        session_id: &str,
+        limit: usize,
+    ) -> AppResult<Vec<ActivityLogEntry>> {
+        if !self.sessions.read().await.contains_key(session_id) {
+            return Err(AppError::NotFound(format!(
+                "Session '{}' not found",
+                session_id
+            )));
+        }
+        let logs = self.activity_logs.read().await;
+        let Some(log) = logs.get(session_id) else {
+            return Ok(vec![]);
+        };
+        Ok(log
+            .iter()
+            .skip(log.len().saturating_sub(limit))
+            .cloned()
+            .collect())
+    }
+
+    /// Move a session to Error after its agent process died, so the UI can
+    /// report the crash instead of letting later prompts fail silently
+    pub async fn mark_session_crashed(&self, session_id: &str, message: &str) {
+        let Some((updated_session, session_error)) =
+            crash_entry(&self.sessions, &self.db, session_id, message).await
+        else {
+            return;
+        };
+
+        drop_message_waiters(&self.message_waiters, session_id).await;
+        self.turn_started.write().await.remove(session_id);
+        self.record_activity(session_id, ActivityKind::Error, message)
+            .await;
+
+        let event = SessionStatusEvent {
+            session_id: session_id.to_string(),
+            status: SessionStatus::Error,
+            session: Some(updated_session),
+            error: Some(session_error),
+        };
+        if let Err(e) = self.app_handle.emit("session-status-changed", &event) {
+            eprintln!(
+                "[SessionManager] Failed to emit session-status-changed event: {}",
+                e
+            );
+        }
+    }
+
+    /// Update plan entries for a session
+    pub async fn update_session_plan(&self, session_id: &str, plan_entries: Vec<PlanEntry>) {
+        let mut sessions = self.sessions.write().await;
+        if let Some(entry) = sessions.get_mut(session_id) {
+            entry.session.plan_entries = plan_entries;
+        }
+    }
+
+    /// Send interaction response (for prompts like "Press Enter to continue")
+    pub async fn send_interaction_response(
+        &self,
+        session_id: &str,
+        response: &str,
+    ) -> AppResult<()> {
+        let adapter = {
+            let sessions = self.sessions.read().await;
+            sessions.get(session_id).and_then(|e| e.adapter.clone())
+        };
+
+        if let Some(adapter) = adapter {
+            let mut adapter = adapter.lock().await;
+            let message_id = uuid::Uuid::new_v4().to_string();
+            adapter
+                .send_message(vec![PromptContent::Text { text: response.to_string() }], &message_id)
+                .await?;
+            Ok(())
+        } else {
+            Err(AppError::NotFound(format!(
+                "Session '{}' not found or not active",
+                session_id
+            )))
+        }
+    }
+
+    /// Deny and discard a permission request the UI never answered (e.g. the
+    /// prompt event was missed), so the next request can be handled
+    pub async fn clear_pending_permission(&self, session_id: &str) -> AppResult<bool> {
+        let adapter = {
+            let sessions = self.sessions.read().await;
+            sessions.get(session_id).and_then(|e| e.adapter.clone())
+        }
+        .ok_or_else(|| {
+            AppError::NotFound(format!("Session '{}' not found or not active", session_id))
+        })?;
+
+        let cleared = adapter.lock().await.clear_pending_permission().await?;
+        if cleared {
+            println!(
+                "[SessionManager] Cleared pending permission for session {}",
+                session_id
+            );
+            let event = PermissionClearedEvent {
+                session_id: session_id.to_string(),
+            };
+            if let Err(e) = self.app_handle.emit("permission-cleared", &event) {
+                eprintln!(
+                    "[SessionManager] Failed to emit permission-cleared event: {}",
+                    e
+                );
+            }
+        }
+        Ok(cleared)
+    }
+
+    /// Unanswered requests between the client and a session's agent
+    pub async fn get_pending_requests(&self, session_id: &str) -> AppResult<Vec<PendingRequest>> {
+        let adapter = {
+            let sessions = self.sessions.read().await;
+            sessions.get(session_id).and_then(|e| e.adapter.clone())
+        }
+        .ok_or_else(|| {
+            AppError::NotFound(format!("Session '{}' not found or not active", session_id))
+        })?;
+
+        let adapter = adapter.lock().await;
+        adapter.pending_requests().await
+    }
+
+    /// PID and resource usage of a session's agent process, `None` when the
+    /// session has no live process
+    pub async fn get_session_process_info(
+        &self,
+        session_id: &str,
+    ) -> AppResult<Option<ProcessInfo>> {
+        let adapter = {
+            let sessions = self.sessions.read().await;
+            let entry = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session '{}' not found", session_id))
+            })?;
+            entry.adapter.clone()
+        };
+        let Some(adapter) = adapter else {
+            return Ok(None);
+        };
+
+        let pid = {
+            let mut adapter = adapter.lock().await;
+            if !adapter.is_process_alive() {
+                return Ok(None);
+            }
+            adapter.process_id()
+        };
+        Ok(pid.map(|pid| {
+            let (rss_bytes, cpu_time_ms) = process_usage(pid);
+            ProcessInfo {
+                pid,
+                rss_bytes,
+                cpu_time_ms,
+            }
+        }))
+    }
+
+    /// Abort a stuck session's unanswered requests so it can take new prompts.
+    /// Returns how many were cleared.
+    pub async fn clear_pending_requests(&self, session_id: &str) -> AppResult<usize> {
+        let adapter = {
+            let sessions = self.sessions.read().await;
+            sessions.get(session_id).and_then(|e| e.adapter.clone())
+        }
+        .ok_or_else(|| {
+            AppError::NotFound(format!("Session '{}' not found or not active", session_id))
+        })?;
+
+        let cleared = adapter.lock().await.clear_pending_requests().await?;
+        if cleared > 0 {
+            println!(
+                "[SessionManager] Cleared {} pending requests for session {}",
+                cleared, session_id
+            );
+            self.turn_started.write().await.remove(session_id);
+            self.active_tool_calls.write().await.remove(session_id);
+        }
+        Ok(cleared)
+    }
+
+    /// Set the model for an active session. `model` is a model id or a
+    /// case-insensitive display name such as "Sonnet 4".
+    pub async fn set_session_model(&self, session_id: &str, model: String) -> AppResult<Session> {
+        // Validate model is available for this session
+        let model_id = {
+            let sessions = self.sessions.read().await;
+            let entry = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session '{}' not found", session_id))
+            })?;
+            resolve_model_id(&entry.session.available_models, &model)?
+        };
+
+        // Get adapter and call set_model
+        let adapter = {
+            let sessions = self.sessions.read().await;
+            sessions.get(session_id).and_then(|e| e.adapter.clone())
+        };
+
+        if let Some(adapter) = adapter {
+            let mut adapter = adapter.lock().await;
+            adapter.set_model(&model_id).await?;
+        } else {
+            return Err(AppError::InvalidOperation(
+                "Session is not active".to_string(),
+            ));
+        }
+
+        // Update session in memory and database
+        {
+            let mut sessions = self.sessions.write().await;
+            if let Some(entry) = sessions.get_mut(session_id) {
+                entry.session.model = Some(model_id.clone());
+
+                // Persist model change to database
+                if let Err(e) = self.db.update_session_model(session_id, &model_id) {
+                    eprintln!(
+                        "[SessionManager] Failed to update session model in DB: {}",
+                        e
+                    );
+                }
+
+                Ok(entry.session.clone())
+            } else {
+                Err(AppError::NotFound(format!("Session '{}' not found", session_id)))
+            }
+        }
+    }
+
+    /// Set the mode for an active session
+    pub async fn set_session_mode(&self, session_id: &str, mode_id: String) -> AppResult<Session> {
+        // Validate mode is available for this session
+        {
+            let sessions = self.sessions.read().await;
+            let entry = sessions.get(session_id).ok_or_else(|| {
+                AppError::NotFound(format!("Session '{}' not found", session_id))
+            })?;
+
+            if !entry.session.available_modes.is_empty()
+                && !entry.session.available_modes.iter().any(|m| m.mode_id == mode_id)
+            {
+                return Err(AppError::InvalidOperation(format!(
+                    "Mode '{}' is not available for this session",
+                    mode_id
+                )));
+            }
+        }
+
+        // Get adapter and call set_mode
+        let adapter = {
+            let sessions = self.sessions.read().await;
+            sessions.get(session_id).and_then(|e| e.adapter.clone())
+        };
+
+        if let Some(adapter) = adapter {
+            let mut adapter = adapter.lock().await;
+            adapter.set_mode(&mode_id).await?;
+        } else {
+            return Err(AppError::InvalidOperation(
+                "Session is not active".to_string(),
+            ));
+        }
+
+        // Update session in memory
+        {
+            let mut sessions = self.sessions.write().await;
+            if let Some(entry) = sessions.get_mut(session_id) {
+                entry.session.mode = Some(mode_id);
+                Ok(entry.session.clone())
+            } else {
+                Err(AppError::NotFound(format!("Session '{}' not found", session_id)))
+            }
+        }
+    }
+
+    /// Set a config option for an active session
+    pub async fn set_session_config_option(
+        &self,
+        session_id: &str,
+        config_id: String,
+        value: String,
+    ) -> AppResult<()> {
+        // Get adapter
+        let adapter = {
+            let sessions = self.sessions.read().await;
+            sessions.get(session_id).and_then(|e| e.adapter.clone())
+        };
+
+        let adapter = adapter.ok_or_else(|| {
+            AppError::InvalidOperation("Session is not active".to_string())
+        })?;
+
+        let mut adapter = adapter.lock().await;
+        adapter.set_config_option(&config_id, &value).await?;
+
+        Ok(())
+    }
+
+    /// Set several config options in one go, in the given order, while holding
+    /// the adapter so no other command runs in between. Stops at the first
+    /// failure, leaving earlier options applied. Emits a single
+    /// `config-options-update` with the final options and returns them.
+    pub async fn set_session_config_options(
+        &self,
+        session_id: &str,
+        options: Vec<(String, String)>,
+    ) -> AppResult<Vec<SessionConfigOption>> {
+        let adapter = {
+            let sessions = self.sessions.read().await;
+            sessions.get(session_id).and_then(|e| e.adapter.clone())
+        };
+
+        let adapter = adapter.ok_or_else(|| {
+            AppError::InvalidOperation("Session is not active".to_string())
+        })?;
+
+        let config_options = {
+            let mut adapter = adapter.lock().await;
+            for (config_id, value) in &options {
+                adapter
+                    .set_config_option(config_id, value)
+                    .await
+                    .map_err(|e| {
+                        AppError::Provider(format!(
+                            "Failed to set config option '{}': {}",
+                            config_id, e
+                        ))
+                    })?;
+            }
+            adapter.config_options()
+        };
+
+        if let Some(entry) = self.sessions.write().await.get_mut(session_id) {
+            entry.session.config_options = config_options.clone();
+        }
+
+        #[derive(Serialize, Clone)]
+        struct ConfigOptionsUpdatePayload<'a> {
+            session_id: &'a str,
+            config_options: &'a [SessionConfigOption],
+        }
+        let payload = ConfigOptionsUpdatePayload {
+            session_id,
+            config_options: &config_options,
+        };
+        if let Err(e) = self.app_handle.emit("config-options-update", &payload) {
+            eprintln!(
+                "[SessionManager] Failed to emit config-options-update event: {}",
+                e
+            );
+        }
+
+        Ok(config_options)
+    }
+
+    /// Cancel the current ongoing generation for a session
+    pub async fn cancel_generation(&self, session_id: &str) -> AppResult<()> {
+        let adapter = {
+            let sessions = self.sessions.read().await;
+            sessions.get(session_id).and_then(|e| e.adapter.clone())
+        };
+
+        if let Some(adapter) = adapter {
+            let mut adapter = adapter.lock().await;
+            adapter.cancel().await?;
+            println!("[SessionManager] Generation cancelled for session {}", session_id);
+            Ok(())
+        } else {
+            Err(AppError::NotFound(format!(
+                "Session '{}' not found or not active",
+                session_id
+            )))
+        }
+    }
+
+    /// Stop the current turn of every session with a live agent, without
+    /// terminating any of them. Returns the sessions whose cancel failed, with
+    /// the error message.
+    pub async fn cancel_all(&self) -> HashMap<String, String> {
+        let adapters: Vec<_> = {
+            let sessions = self.sessions.read().await;
+            sessions
+                .iter()
+                .filter_map(|(id, e)| e.adapter.clone().map(|a| (id.clone(), a)))
+                .collect()
+        };
+
+        let results = futures::future::join_all(adapters.into_iter().map(
+            |(session_id, adapter)| async move {
+                let result = adapter.lock().await.cancel().await;
+                (session_id, result)
+            },
+        ))
+        .await;
+
+        let mut errors = HashMap::new();
+        let mut cancelled = 0;
+        for (session_id, result) in results {
+            match result {
+                Ok(()) => cancelled += 1,
+                Err(e) => {
+                    eprintln!(
+                        "[SessionManager] Failed to cancel generation for session {}: {}",
+                        session_id, e
+                    );
+                    errors.insert(session_id, e.to_string());
+                }
+            }
+        }
+        println!(
+            "[SessionManager] Cancelled generation in {} sessions ({} failed)",
+            cancelled,
+            errors.len()
+        );
+        errors
+    }
+
+    /// Take the tool call `_meta` buffered for a message, to store it with the message
+    pub async fn take_message_meta(&self, message_id: &str) -> Option<serde_json::Value> {
+        self.pending_message_meta
+            .write()
+            .await
+            .remove(message_id)
+            .map(serde_json::Value::Object)
+    }
+
+    /// Tool call ids that are currently running in a session
+    pub async fn get_active_tool_calls(&self, session_id: &str) -> Vec<String> {
+        let active = self.active_tool_calls.read().await;
+        active
+            .get(session_id)
+            .map(|calls| calls.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Interrupt a single running tool call while keeping the prompt turn alive
+    pub async fn cancel_tool_call(&self, session_id: &str, tool_call_id: &str) -> AppResult<()> {
+        let is_running = {
+            let active = self.active_tool_calls.read().await;
+            active
+                .get(session_id)
+                .is_some_and(|calls| calls.contains(tool_call_id))
+        };
+        if !is_running {
+            return Err(AppError::InvalidOperation(format!(
+                "Tool call '{}' is not running",
+                tool_call_id
+            )));
+        }
+
+        let adapter = {
+            let sessions = self.sessions.read().await;
+            sessions.get(session_id).and_then(|e| e.adapter.clone())
+        };
+
+        if let Some(adapter) = adapter {
+            let mut adapter = adapter.lock().await;
+            adapter.cancel_tool_call(tool_call_id).await?;
+            println!(
+                "[SessionManager] Requested cancel of tool call {} in session {}",
+                tool_call_id, session_id
+            );
+            Ok(())
+        } else {
+            Err(AppError::NotFound(format!(
+                "Session '{}' not found or not active",
+                session_id
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(content: &str, chunk_type: Option<StreamChunkType>) -> StreamChunk {
+        StreamChunk {
+            session_id: "s1".to_string(),
+            message_id: "m1".to_string(),
+            content: content.to_string(),
+            is_complete: false,
+            chunk_type,
+            tool_call: None,
+            image_content: None,
+            error: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn collect_turn_text_returns_text_of_completed_turn() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        tx.send(chunk("Hello, ", None)).unwrap();
+        tx.send(chunk("thinking...", Some(StreamChunkType::Thinking)))
+            .unwrap();
+        tx.send(chunk("world", Some(StreamChunkType::Text)))
+            .unwrap();
+        tx.send(StreamChunk {
+            is_complete: true,
+            ..chunk("", None)
+        })
+        .unwrap();
+
+        assert_eq!(collect_turn_text(&mut rx).await.unwrap(), "Hello, world");
+    }
+
+    #[tokio::test]
+    async fn collect_turn_text_fails_on_prompt_error() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        tx.send(chunk("partial", None)).unwrap();
+        tx.send(StreamChunk {
+            is_complete: true,
+            error: Some("prompt failed: rate limited".to_string()),
+            ..chunk("", None)
+        })
+        .unwrap();
+
+        let err = collect_turn_text(&mut rx).await.unwrap_err();
+        assert!(err.to_string().contains("rate limited"));
+    }
+
+    #[test]
+    fn process_usage_reports_the_current_process() {
+        // Burn a little CPU so the accumulated time is measurable
+        let mut x = 0u64;
+        for i in 0..20_000_000u64 {
+            x = std::hint::black_box(x.wrapping_add(i));
+        }
+        assert!(x > 0);
+
+        let (rss_bytes, cpu_time_ms) = process_usage(std::process::id());
+        assert!(rss_bytes.is_some_and(|rss| rss > 0));
+        assert!(cpu_time_ms.is_some());
+    }
+
+    #[test]
+    fn process_usage_of_a_missing_process_is_none() {
+        assert_eq!(process_usage(u32::MAX - 1), (None, None));
+    }
+
+    #[tokio::test]
+    async fn dropping_waiters_ends_the_wait() {
+        let waiters: MessageWaiters = Arc::new(RwLock::new(HashMap::new()));
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        waiters.write().await.insert(
+            "m1".to_string(),
+            MessageWaiter {
+                session_id: "s1".to_string(),
+                tx,
+            },
+        );
+
+        drop_message_waiters(&waiters, "s1").await;
+        assert!(waiters.read().await.is_empty());
+        assert!(collect_turn_text(&mut rx).await.is_err());
+    }
+
+    /// Adapter standing in for a running agent. Counts how often it was asked
+    /// to spawn a process.
+    struct FakeAdapter {
+        alive: bool,
+        spawns: Arc<std::sync::atomic::AtomicUsize>,
+        stream_tx: Option<mpsc::Sender<StreamChunk>>,
+    }
+
+    impl FakeAdapter {
+        fn new(alive: bool) -> Self {
+            Self {
+                alive,
+                spawns: Arc::default(),
+                stream_tx: None,
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl ProviderAdapter for FakeAdapter {
+        fn provider_type(&self) -> ProviderType {
+            ProviderType::Claude
+        }
+
+        fn detect(&self) -> AppResult<crate::models::ProviderInfo> {
+            Err(AppError::Provider("not a real provider".to_string()))
+        }
+
+        async fn start_session(
+            &mut self,
+            _session_id: &str,
+            _worktree_path: &Path,
+            stream_tx: mpsc::Sender<StreamChunk>,
+            _app_handle: AppHandle,
+            _mcp_servers: Vec<agent_client_protocol::McpServer>,
+        ) -> AppResult<()> {
+            self.spawns
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            self.stream_tx = Some(stream_tx);
+            Ok(())
+        }
+
+        /// Answers every prompt with a single completed chunk
+        async fn send_message(
+            &mut self,
+            _content: Vec<PromptContent>,
+            message_id: &str,
+        ) -> AppResult<()> {
+            if let Some(tx) = &self.stream_tx {
+                let reply = StreamChunk {
+                    message_id: message_id.to_string(),
+                    is_complete: true,
+                    ..chunk("reply", None)
+                };
+                let _ = tx.send(reply).await;
+            }
+            Ok(())
+        }
+
+        async fn set_model(&mut self, _model_id: &str) -> AppResult<()> {
+            Ok(())
+        }
+
+        async fn set_mode(&mut self, _mode_id: &str) -> AppResult<()> {
+            Ok(())
+        }
+
+        async fn set_config_option(
+            &mut self,
+            _config_id: &str,
+            _value: &str,
+        ) -> AppResult<Vec<SessionConfigOption>> {
+            Ok(Vec::new())
+        }
+
+        fn is_active(&self) -> bool {
+            self.alive
+        }
+
+        async fn terminate(&mut self) -> AppResult<()> {
+            self.alive = false;
+            Ok(())
+        }
+    }
+
+    fn test_session(id: &str) -> Session {
+        Session {
+            id: id.to_string(),
+            name: "Test session".to_string(),
+            provider: ProviderType::Claude,
+            status: SessionStatus::Active,
+            worktree_path: "/tmp/worktree".to_string(),
+            branch_name: format!("forkestra/session-{}", id),
+            created_at: Utc::now(),
+            updated_at: None,
+            project_path: "/tmp/project".to_string(),
+            is_local: false,
+            acp_session_id: Some(format!("acp-{}", id)),
+            acp_cwd: None,
+            model: None,
+            available_models: vec![],
+            mode: None,
+            available_modes: vec![],
+            available_commands: vec![],
+            plan_entries: vec![],
+            config_options: vec![],
+            error: None,
+            pinned: false,
+            system_prompt: None,
+            ui_color: None,
+        }
+    }
+
+    fn sessions_with(
+        session: Session,
+        adapter: Option<FakeAdapter>,
+    ) -> (
+        RwLock<HashMap<String, SessionEntry>>,
+        Option<SessionAdapter>,
+    ) {
+        let adapter =
+            adapter.map(|a| -> SessionAdapter { Arc::new(tokio::sync::Mutex::new(Box::new(a))) });
+        let entry = SessionEntry {
+            session,
+            adapter: adapter.clone(),
+        };
+        let sessions = HashMap::from([(entry.session.id.clone(), entry)]);
+        (RwLock::new(sessions), adapter)
+    }
+
+    #[tokio::test]
+    async fn attaching_reuses_the_running_agent() {
+        let mut fake = FakeAdapter::new(true);
+        let spawns = fake.spawns.clone();
+        let (stream_tx, mut stream_rx) = mpsc::channel(8);
+        fake.stream_tx = Some(stream_tx);
+        let (sessions, adapter) = sessions_with(test_session("s1"), Some(fake));
+
+        let attached = live_adapter(&sessions, "s1").await.unwrap().unwrap();
+        assert!(Arc::ptr_eq(&attached, &adapter.unwrap()));
+        assert_eq!(spawns.load(std::sync::atomic::Ordering::SeqCst), 0);
+        assert_eq!(
+            sessions.read().await["s1"].session.status,
+            SessionStatus::Active
+        );
+
+        // The agent keeps writing to the channel the existing forwarder reads
+        attached
+            .lock()
+            .await
+            .send_message(Vec::new(), "m2")
+            .await
+            .unwrap();
+        let reply = stream_rx.recv().await.unwrap();
+        assert_eq!(
+            (reply.message_id.as_str(), reply.content.as_str()),
+            ("m2", "reply")
+        );
+    }
+
+    #[tokio::test]
+    async fn attaching_to_an_exited_agent_pauses_the_session() {
+        let (sessions, _) = sessions_with(test_session("s1"), Some(FakeAdapter::new(false)));
+
+        assert!(live_adapter(&sessions, "s1").await.unwrap().is_none());
+        let sessions = sessions.read().await;
+        assert!(sessions["s1"].adapter.is_none());
+        assert_eq!(sessions["s1"].session.status, SessionStatus::Paused);
+    }
+
+    #[tokio::test]
+    async fn attaching_without_an_agent_fails() {
+        let (sessions, _) = sessions_with(test_session("s1"), None);
+        assert!(matches!(
+            live_adapter(&sessions, "s1").await,
+            Err(AppError::InvalidOperation(_))
+        ));
+        assert!(matches!(
+            live_adapter(&sessions, "missing").await,
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    fn test_settings() -> SettingsManager {
+        SettingsManager::in_memory(&AppError::Internal("test".to_string()))
+    }
+
+    #[test]
+    fn env_var_changes_require_a_respawn_but_cosmetic_ones_do_not() {
+        let settings = test_settings();
+        let running = create_adapter(&ProviderType::Claude, &settings).unwrap();
+        let mut provider = settings
+            .get_provider_settings_by_id("claude")
+            .unwrap_or_default();
+
+        provider.enabled = !provider.enabled;
+        settings
+            .update_provider_settings_by_id("claude", provider.clone())
+            .unwrap();
+        let changed =
+            launch_settings_changed(running.as_ref(), &ProviderType::Claude, &settings).unwrap();
+        assert!(!changed);
+
+        provider.env_vars.insert(
+            "ANTHROPIC_BASE_URL".to_string(),
+            "http://localhost:1".to_string(),
+        );
+        settings
+            .update_provider_settings_by_id("claude", provider)
+            .unwrap();
+        let changed =
+            launch_settings_changed(running.as_ref(), &ProviderType::Claude, &settings).unwrap();
+        assert!(changed);
+    }
+
+    #[tokio::test]
+    async fn creation_reports_each_phase_in_order() {
+        let progress = RwLock::new(HashMap::new());
+        let started = Instant::now();
+        progress
+            .write()
+            .await
+            .insert("s1".to_string(), (started, started));
+
+        let phases = [
+            SessionProgressPhase::CreatingWorktree,
+            SessionProgressPhase::SpawningProcess,
+            SessionProgressPhase::Initializing,
+            SessionProgressPhase::CreatingAcpSession,
+            SessionProgressPhase::Ready,
+        ];
+        let mut events = Vec::new();
+        for phase in phases {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+            events.push(progress_event(&progress, "s1", phase).await.unwrap());
+        }
+
+        let reported: Vec<_> = events.iter().map(|e| e.phase).collect();
+        assert_eq!(reported, phases);
+        for pair in events.windows(2) {
+            assert!(pair[1].elapsed_ms >= pair[0].elapsed_ms);
+        }
+        assert!(events.iter().all(|e| e.previous_phase_ms >= 5));
+        assert!(events[4].elapsed_ms >= 25);
+
+        // Ready ends the flow; later phases and unknown sessions report nothing
+        assert!(progress.read().await.is_empty());
+        let late = progress_event(&progress, "s1", SessionProgressPhase::Initializing).await;
+        assert!(late.is_none());
+    }
+
+    fn test_db() -> Arc<Database> {
+        Arc::new(Database::in_memory(&AppError::Internal("test".to_string())).unwrap())
+    }
+
+    /// Git repository with one commit on `main`
+    fn init_repo(dir: &Path) {
+        let repo = git2::Repository::init_opts(
+            dir,
+            git2::RepositoryInitOptions::new().initial_head("main"),
+        )
+        .unwrap();
+        std::fs::write(dir.join("README.md"), "hello\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            "Initial commit",
+            &tree,
+            &[],
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn forgetting_keeps_the_worktree_and_branch() {
+        let project = tempfile::tempdir().unwrap();
+        init_repo(project.path());
+        let (worktree_path, branch_name) = WorktreeManager::create_worktree(
+            project.path(),
+            "s1",
+            Some("main"),
+            false,
+            crate::models::DEFAULT_BRANCH_PREFIX,
+        )
+        .unwrap();
+
+        let mut session = test_session("s1");
+        session.project_path = project.path().to_string_lossy().to_string();
+        session.worktree_path = worktree_path.to_string_lossy().to_string();
+        session.branch_name = branch_name.clone();
+        let db = test_db();
+        db.save_session(&session).unwrap();
+        let (sessions, adapter) = sessions_with(session, Some(FakeAdapter::new(true)));
+
+        forget_entry(&sessions, &db, "s1").await.unwrap();
+
+        assert!(sessions.read().await.is_empty());
+        assert!(db.load_sessions().unwrap().is_empty());
+        assert!(!adapter.unwrap().lock().await.is_active());
+        assert!(worktree_path.join("README.md").is_file());
+        let repo = git2::Repository::open(project.path()).unwrap();
+        assert!(repo
+            .find_branch(&branch_name, git2::BranchType::Local)
+            .is_ok());
+        assert!(repo.find_worktree("s1").is_ok());
+
+        let base = WorktreeManager::get_worktree_base_path(project.path()).unwrap();
+        let _ = std::fs::remove_dir_all(base);
+    }
+
+    #[tokio::test]
+    async fn agent_model_switches_are_recorded_once() {
+        let mut session = test_session("s1");
+        session.model = Some("sonnet".to_string());
+        let db = test_db();
+        db.save_session(&session).unwrap();
+        let (sessions, _) = sessions_with(session, None);
+
+        let event = apply_model_change(&sessions, &db, "s1", "opus")
+            .await
+            .unwrap();
+        assert_eq!(event.model_id, "opus");
+        assert_eq!(event.previous_model_id.as_deref(), Some("sonnet"));
+        assert_eq!(
+            sessions.read().await["s1"].session.model.as_deref(),
+            Some("opus")
+        );
+        assert_eq!(
+            db.load_sessions().unwrap()[0].model.as_deref(),
+            Some("opus")
+        );
 
-        Ok(updated_session)
+        assert!(apply_model_change(&sessions, &db, "s1", "opus")
+            .await
+            .is_none());
+        assert!(apply_model_change(&sessions, &db, "missing", "opus")
+            .await
+            .is_none());
     }
 
-    /// Rename a session
-    pub async fn rename_session(&self, session_id: &str, new_name: &str) -> AppResult<Session> {
-        let mut sessions = self.sessions.write().await;
-        if let Some(entry) = sessions.get_mut(session_id) {
-            entry.session.name = new_name.to_string();
+    #[test]
+    fn worktree_limit_evicts_only_the_oldest_terminated_worktree() {
+        let project = tempfile::tempdir().unwrap();
+        init_repo(project.path());
+        let mut terminated = Vec::new();
+        for (age, id) in [(3, "s1"), (2, "s2"), (1, "s3")] {
+            let (worktree_path, branch_name) = WorktreeManager::create_worktree(
+                project.path(),
+                id,
+                Some("main"),
+                false,
+                crate::models::DEFAULT_BRANCH_PREFIX,
+            )
+            .unwrap();
+            let mut session = test_session(id);
+            session.name = format!("Session {}", id);
+            session.status = SessionStatus::Terminated;
+            session.project_path = project.path().to_string_lossy().to_string();
+            session.worktree_path = worktree_path.to_string_lossy().to_string();
+            session.branch_name = branch_name;
+            session.updated_at = Some(Utc::now() - chrono::Duration::hours(age));
+            terminated.push(session);
+        }
+        // Graduated long ago: terminated, but its worktree is already gone
+        let mut graduated = test_session("g");
+        graduated.status = SessionStatus::Terminated;
+        graduated.project_path = project.path().to_string_lossy().to_string();
+        graduated.worktree_path = project.path().join("gone").to_string_lossy().to_string();
+        graduated.updated_at = Some(Utc::now() - chrono::Duration::days(1));
+        terminated.push(graduated);
 
-            // Persist name change to database
-            if let Err(e) = self.db.update_session_name(session_id, new_name) {
-                eprintln!(
-                    "[SessionManager] Failed to update session name in DB: {}",
-                    e
-                );
-            }
+        assert!(
+            make_room_for_worktree(project.path(), terminated.clone(), 4, false)
+                .unwrap()
+                .is_empty()
+        );
 
-            Ok(entry.session.clone())
-        } else {
-            Err(AppError::NotFound(format!(
-                "Session '{}' not found",
-                session_id
-            )))
-        }
+        let err = make_room_for_worktree(project.path(), terminated.clone(), 3, false).unwrap_err();
+        assert!(err.to_string().contains("Session s1"));
+        assert!(!err.to_string().contains("session-g"));
+        assert!(Path::new(&terminated[0].worktree_path).is_dir());
+
+        let evicted = make_room_for_worktree(project.path(), terminated.clone(), 3, true).unwrap();
+        assert_eq!(evicted, vec!["s1".to_string()]);
+        assert!(!Path::new(&terminated[0].worktree_path).exists());
+        assert!(Path::new(&terminated[1].worktree_path).is_dir());
+        let repo = git2::Repository::open(project.path()).unwrap();
+        assert!(repo
+            .find_branch(&terminated[0].branch_name, git2::BranchType::Local)
+            .is_ok());
+        assert_eq!(
+            WorktreeManager::count_session_worktrees(project.path()).unwrap(),
+            2
+        );
+
+        let base = WorktreeManager::get_worktree_base_path(project.path()).unwrap();
+        let _ = std::fs::remove_dir_all(base);
     }
 
-    /// Update branch name for a session (and all co-located local sessions)
-    pub async fn update_session_branch(
-        &self,
-        session_id: &str,
-        new_branch_name: &str,
-    ) -> AppResult<Vec<Session>> {
-        let mut sessions = self.sessions.write().await;
+    #[test]
+    fn finished_turns_with_changes_are_committed() {
+        let project = tempfile::tempdir().unwrap();
+        init_repo(project.path());
+        let mut session = test_session("s1");
+        session.project_path = project.path().to_string_lossy().to_string();
+        session.worktree_path = session.project_path.clone();
+        let repo = git2::Repository::open(project.path()).unwrap();
+        let mut config = repo.config().unwrap();
+        config.set_str("user.name", "Test").unwrap();
+        config.set_str("user.email", "test@example.com").unwrap();
+        let settings = test_settings();
+        std::fs::write(project.path().join("notes.txt"), "turn one\n").unwrap();
 
-        let entry = sessions.get(session_id).ok_or_else(|| {
-            AppError::NotFound(format!("Session '{}' not found", session_id))
-        })?;
-        let is_local = entry.session.is_local;
-        let project_path = entry.session.project_path.clone();
+        // Off by default
+        assert_eq!(commit_turn(&settings, &session, 1).unwrap(), None);
 
-        let mut updated = Vec::new();
+        let mut app_settings = settings.get_settings();
+        app_settings
+            .general
+            .get_or_insert_with(Default::default)
+            .auto_commit_per_turn = Some(true);
+        settings.update_settings(app_settings).unwrap();
 
-        if is_local {
-            // Update all local sessions sharing the same project_path
-            for entry in sessions.values_mut() {
-                if entry.session.is_local && entry.session.project_path == project_path {
-                    entry.session.branch_name = new_branch_name.to_string();
-                    if let Err(e) = self
-                        .db
-                        .update_session_branch(&entry.session.id, new_branch_name)
-                    {
-                        eprintln!(
-                            "[SessionManager] Failed to update session branch in DB: {}",
-                            e
-                        );
-                    }
-                    updated.push(entry.session.clone());
-                }
-            }
-        } else {
-            // Only update this single session
-            let entry = sessions.get_mut(session_id).ok_or_else(|| {
-                AppError::NotFound(format!("Session '{}' not found", session_id))
-            })?;
-            entry.session.branch_name = new_branch_name.to_string();
-            if let Err(e) = self
-                .db
-                .update_session_branch(session_id, new_branch_name)
-            {
-                eprintln!(
-                    "[SessionManager] Failed to update session branch in DB: {}",
-                    e
-                );
-            }
-            updated.push(entry.session.clone());
-        }
+        session.is_local = true;
+        assert_eq!(commit_turn(&settings, &session, 1).unwrap(), None);
+        session.is_local = false;
 
-        Ok(updated)
+        let sha = commit_turn(&settings, &session, 1).unwrap().unwrap();
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        assert_eq!(head.id().to_string(), sha);
+        assert_eq!(head.summary(), Some("Agent turn 1"));
+        assert!(head.tree().unwrap().get_name("notes.txt").is_some());
+
+        // Nothing changed since
+        assert_eq!(commit_turn(&settings, &session, 2).unwrap(), None);
     }
 
-    /// Update available commands for a session
-    pub async fn update_session_commands(&self, session_id: &str, commands: Vec<AvailableCommand>) {
-        let mut sessions = self.sessions.write().await;
-        if let Some(entry) = sessions.get_mut(session_id) {
-            entry.session.available_commands = commands;
-        }
+    #[tokio::test]
+    async fn cli_exit_line_moves_the_session_to_error() {
+        let db = test_db();
+        db.save_session(&test_session("s1")).unwrap();
+        let (sessions, _) = sessions_with(test_session("s1"), None);
+
+        let line = "[claude] Error: CLI exited with code 1: out of memory";
+        let message = crate::providers::acp_client_sdk::cli_exit_message(line).unwrap();
+        assert_eq!(message, "CLI exited with code 1: out of memory");
+        assert!(crate::providers::acp_client_sdk::cli_exit_message("still working").is_none());
+
+        let (session, error) = crash_entry(&sessions, &db, "s1", message).await.unwrap();
+        assert_eq!(session.status, SessionStatus::Error);
+        assert_eq!(error.code, "agent_crashed");
+        assert_eq!(error.message, message);
+        let stored = db.load_sessions().unwrap().remove(0);
+        assert_eq!(stored.status, SessionStatus::Error);
+        assert_eq!(stored.error.unwrap().message, message);
+
+        // Reported once, even if the agent prints the line again
+        assert!(crash_entry(&sessions, &db, "s1", message).await.is_none());
     }
 
-    /// Update mode for a session (called when agent pushes a mode change via ACP)
-    pub async fn update_session_mode(&self, session_id: &str, mode_id: &str) {
-        let mut sessions = self.sessions.write().await;
-        if let Some(entry) = sessions.get_mut(session_id) {
-            entry.session.mode = Some(mode_id.to_string());
-        }
+    #[tokio::test]
+    async fn setting_the_cwd_restarts_the_agent_in_the_new_directory() {
+        let old_dir = tempfile::tempdir().unwrap();
+        let new_dir = tempfile::tempdir().unwrap();
+        let mut session = test_session("s1");
+        session.is_local = true;
+        session.worktree_path = old_dir.path().to_string_lossy().to_string();
+        session.acp_cwd = Some(session.worktree_path.clone());
+        let db = test_db();
+        db.save_session(&session).unwrap();
+        let (sessions, _) = sessions_with(session, Some(FakeAdapter::new(true)));
+
+        let missing = new_dir.path().join("missing");
+        assert!(
+            retarget_cwd(&sessions, &db, "s1", &missing.to_string_lossy())
+                .await
+                .is_err()
+        );
+
+        let (session, adapter) =
+            retarget_cwd(&sessions, &db, "s1", &new_dir.path().to_string_lossy())
+                .await
+                .unwrap();
+        let expected = new_dir.path().canonicalize().unwrap();
+        assert!(adapter.is_some());
+        assert_eq!(session.status, SessionStatus::Paused);
+        assert_eq!(agent_cwd(&session), expected.to_string_lossy());
+        assert!(sessions.read().await["s1"].adapter.is_none());
+        let stored = db.load_sessions().unwrap().remove(0);
+        assert_eq!(agent_cwd(&stored), expected.to_string_lossy());
+
+        let (worktree_session, _) = sessions_with(test_session("s2"), None);
+        assert!(retarget_cwd(
+            &worktree_session,
+            &db,
+            "s2",
+            &new_dir.path().to_string_lossy()
+        )
+        .await
+        .is_err());
     }
 
-    /// Update plan entries for a session
-    pub async fn update_session_plan(&self, session_id: &str, plan_entries: Vec<PlanEntry>) {
-        let mut sessions = self.sessions.write().await;
-        if let Some(entry) = sessions.get_mut(session_id) {
-            entry.session.plan_entries = plan_entries;
+    #[tokio::test]
+    async fn system_prompt_leads_the_first_prompt_of_each_agent_session() {
+        let mut session = test_session("s1");
+        session.system_prompt = Some("Use tabs".to_string());
+        let db = test_db();
+        db.save_session(&session).unwrap();
+        let (sessions, _) = sessions_with(session, None);
+        let sent = RwLock::new(std::collections::HashSet::new());
+        let prompt = || {
+            vec![PromptContent::Text {
+                text: "hi".to_string(),
+            }]
+        };
+        fn texts(content: &[PromptContent]) -> Vec<String> {
+            content
+                .iter()
+                .filter_map(|c| match c {
+                    PromptContent::Text { text } => Some(text.clone()),
+                    _ => None,
+                })
+                .collect()
         }
+
+        let (content, added) = with_system_prompt(&sessions, &sent, "s1", prompt()).await;
+        assert!(added);
+        assert_eq!(texts(&content), ["Use tabs", "hi"]);
+        sent.write().await.insert("s1".to_string());
+        let (content, added) = with_system_prompt(&sessions, &sent, "s1", prompt()).await;
+        assert!(!added);
+        assert_eq!(texts(&content), ["hi"]);
+
+        // Kept in the database for resumes
+        assert_eq!(
+            db.load_sessions().unwrap()[0].system_prompt.as_deref(),
+            Some("Use tabs")
+        );
+        // A resume that loads the old history doesn't repeat it, a fresh one does
+        note_resumed_history(&sent, "s1", true).await;
+        assert!(!with_system_prompt(&sessions, &sent, "s1", prompt()).await.1);
+        note_resumed_history(&sent, "s1", false).await;
+        assert!(with_system_prompt(&sessions, &sent, "s1", prompt()).await.1);
     }
 
-    /// Send interaction response (for prompts like "Press Enter to continue")
-    pub async fn send_interaction_response(
-        &self,
-        session_id: &str,
-        response: &str,
-    ) -> AppResult<()> {
-        let adapter = {
-            let sessions = self.sessions.read().await;
-            sessions.get(session_id).and_then(|e| e.adapter.clone())
-        };
+    #[test]
+    fn thread_count_includes_running_threads() {
+        let (release, wait) = std::sync::mpsc::channel::<()>();
+        let wait = Arc::new(std::sync::Mutex::new(wait));
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let wait = Arc::clone(&wait);
+                std::thread::spawn(move || {
+                    let _ = wait.lock().unwrap().recv();
+                })
+            })
+            .collect();
 
-        if let Some(adapter) = adapter {
-            let mut adapter = adapter.lock().await;
-            adapter.send_message(vec![PromptContent::Text { text: response.to_string() }]).await?;
-            Ok(())
-        } else {
-            Err(AppError::NotFound(format!(
-                "Session '{}' not found or not active",
-                session_id
-            )))
+        if cfg!(target_os = "linux") {
+            // The four spawned threads plus this one and the main thread
+            assert!(process_thread_count().unwrap() >= 6);
+        }
+
+        drop(release);
+        for thread in threads {
+            thread.join().unwrap();
         }
     }
 
-    /// Set the model for an active session
-    pub async fn set_session_model(&self, session_id: &str, model_id: String) -> AppResult<Session> {
-        // Validate model is available for this session
-        {
-            let sessions = self.sessions.read().await;
-            let entry = sessions.get(session_id).ok_or_else(|| {
-                AppError::NotFound(format!("Session '{}' not found", session_id))
-            })?;
+    #[tokio::test]
+    async fn reload_picks_up_stored_sessions_and_keeps_live_adapters() {
+        let db = test_db();
+        let (sessions, adapter) = sessions_with(test_session("live"), Some(FakeAdapter::new(true)));
+        sessions.write().await.insert(
+            "gone".to_string(),
+            SessionEntry {
+                session: test_session("gone"),
+                adapter: None,
+            },
+        );
 
-            if !entry.session.available_models.is_empty()
-                && !entry.session.available_models.iter().any(|m| m.model_id == model_id)
-            {
-                return Err(AppError::InvalidOperation(format!(
-                    "Model '{}' is not available for this session",
-                    model_id
-                )));
-            }
-        }
+        let mut stored_live = test_session("live");
+        stored_live.name = "Renamed elsewhere".to_string();
+        stored_live.status = SessionStatus::Paused;
+        db.save_session(&stored_live).unwrap();
+        db.save_session(&test_session("imported")).unwrap();
 
-        // Get adapter and call set_model
-        let adapter = {
-            let sessions = self.sessions.read().await;
-            sessions.get(session_id).and_then(|e| e.adapter.clone())
-        };
+        reload_entries(&sessions, &db, &HashSet::new())
+            .await
+            .unwrap();
 
-        if let Some(adapter) = adapter {
-            let mut adapter = adapter.lock().await;
-            adapter.set_model(&model_id).await?;
-        } else {
-            return Err(AppError::InvalidOperation(
-                "Session is not active".to_string(),
-            ));
-        }
+        let sessions = sessions.read().await;
+        let mut ids: Vec<&str> = sessions.keys().map(String::as_str).collect();
+        ids.sort();
+        assert_eq!(ids, ["imported", "live"]);
 
-        // Update session in memory and database
-        {
-            let mut sessions = self.sessions.write().await;
-            if let Some(entry) = sessions.get_mut(session_id) {
-                entry.session.model = Some(model_id.clone());
+        let live = &sessions["live"];
+        assert!(Arc::ptr_eq(
+            live.adapter.as_ref().unwrap(),
+            adapter.as_ref().unwrap()
+        ));
+        assert_eq!(live.session.name, "Renamed elsewhere");
+        assert_eq!(live.session.status, SessionStatus::Active);
 
-                // Persist model change to database
-                if let Err(e) = self.db.update_session_model(session_id, &model_id) {
-                    eprintln!(
-                        "[SessionManager] Failed to update session model in DB: {}",
-                        e
-                    );
-                }
+        let imported = &sessions["imported"];
+        assert!(imported.adapter.is_none());
+        // Stored as Active but without an agent, so it can only be resumed
+        assert_eq!(imported.session.status, SessionStatus::Paused);
+    }
 
-                Ok(entry.session.clone())
-            } else {
-                Err(AppError::NotFound(format!("Session '{}' not found", session_id)))
-            }
+    #[tokio::test]
+    async fn resuming_twice_leaves_one_stream_forwarder() {
+        let forwarders: StreamForwarders = Arc::default();
+        let emitted = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+        // Each resume attempt gets a channel whose sender stays with its adapter,
+        // so a failed attempt's forwarder would otherwise keep running
+        let mut senders = Vec::new();
+        for attempt in 0..2 {
+            let (tx, mut rx) = mpsc::channel::<String>(8);
+            let emitted = emitted.clone();
+            let forwarder = tokio::spawn(async move {
+                while let Some(content) = rx.recv().await {
+                    emitted
+                        .lock()
+                        .unwrap()
+                        .push(format!("{}:{}", attempt, content));
+                }
+            });
+            replace_stream_forwarder(&forwarders, "s1", forwarder).await;
+            senders.push(tx);
         }
+
+        // The aborted forwarder drops its receiver, so the old sender sees a
+        // closed channel
+        senders[0].closed().await;
+        assert!(senders[0].send("chunk".to_string()).await.is_err());
+        senders[1].send("chunk".to_string()).await.unwrap();
+        drop(senders);
+
+        let forwarder = forwarders.write().await.remove("s1").unwrap();
+        forwarder.await.unwrap();
+        assert_eq!(*emitted.lock().unwrap(), ["1:chunk"]);
     }
 
-    /// Set the mode for an active session
-    pub async fn set_session_mode(&self, session_id: &str, mode_id: String) -> AppResult<Session> {
-        // Validate mode is available for this session
-        {
-            let sessions = self.sessions.read().await;
-            let entry = sessions.get(session_id).ok_or_else(|| {
-                AppError::NotFound(format!("Session '{}' not found", session_id))
-            })?;
+    #[tokio::test]
+    async fn imported_agent_session_is_resumable_by_its_id() {
+        let project = tempfile::tempdir().unwrap();
+        let project_path = project.path().to_string_lossy().to_string();
+        let db = test_db();
+        let sessions = RwLock::new(HashMap::new());
 
-            if !entry.session.available_modes.is_empty()
-                && !entry.session.available_modes.iter().any(|m| m.mode_id == mode_id)
-            {
-                return Err(AppError::InvalidOperation(format!(
-                    "Mode '{}' is not available for this session",
-                    mode_id
-                )));
-            }
-        }
+        let not_a_repo = import_entry(
+            &sessions,
+            &db,
+            &project_path,
+            "acp-1",
+            "Imported",
+            ProviderType::Claude,
+        )
+        .await;
+        assert!(matches!(not_a_repo, Err(AppError::InvalidOperation(_))));
 
-        // Get adapter and call set_mode
-        let adapter = {
-            let sessions = self.sessions.read().await;
-            sessions.get(session_id).and_then(|e| e.adapter.clone())
-        };
+        init_repo(project.path());
+        let session = import_entry(
+            &sessions,
+            &db,
+            &project_path,
+            " acp-1 ",
+            "Imported",
+            ProviderType::Claude,
+        )
+        .await
+        .unwrap();
+        assert_eq!(session.status, SessionStatus::Paused);
+        assert!(session.is_local);
+        assert_eq!(session.branch_name, "main");
 
-        if let Some(adapter) = adapter {
-            let mut adapter = adapter.lock().await;
-            adapter.set_mode(&mode_id).await?;
-        } else {
-            return Err(AppError::InvalidOperation(
-                "Session is not active".to_string(),
-            ));
-        }
+        // Resuming sends `session/load` for the stored id in the stored cwd
+        let stored = db.load_sessions().unwrap().remove(0);
+        assert_eq!(stored.acp_session_id.as_deref(), Some("acp-1"));
+        assert_eq!(agent_cwd(&stored), project_path);
+        assert!(sessions.read().await[&session.id].adapter.is_none());
 
-        // Update session in memory
-        {
-            let mut sessions = self.sessions.write().await;
-            if let Some(entry) = sessions.get_mut(session_id) {
-                entry.session.mode = Some(mode_id);
-                Ok(entry.session.clone())
-            } else {
-                Err(AppError::NotFound(format!("Session '{}' not found", session_id)))
+        let again = import_entry(
+            &sessions,
+            &db,
+            &project_path,
+            "acp-1",
+            "Again",
+            ProviderType::Claude,
+        )
+        .await;
+        assert!(matches!(again, Err(AppError::InvalidOperation(_))));
+    }
+
+    #[tokio::test]
+    async fn auto_resume_reconnects_resumable_sessions_only() {
+        let sessions = RwLock::new(HashMap::new());
+        for (id, acp_session_id) in [("s1", true), ("s2", false), ("s3", true)] {
+            let mut session = test_session(id);
+            session.status = SessionStatus::Paused;
+            if !acp_session_id {
+                session.acp_session_id = None;
             }
+            sessions.write().await.insert(
+                id.to_string(),
+                SessionEntry {
+                    session,
+                    adapter: None,
+                },
+            );
         }
-    }
+        let ids: Vec<String> = ["s1", "s2", "s3", "gone"].map(String::from).to_vec();
 
-    /// Set a config option for an active session
-    pub async fn set_session_config_option(
-        &self,
-        session_id: &str,
-        config_id: String,
-        value: String,
-    ) -> AppResult<()> {
-        // Get adapter
-        let adapter = {
-            let sessions = self.sessions.read().await;
-            sessions.get(session_id).and_then(|e| e.adapter.clone())
-        };
+        let attempted = std::sync::Mutex::new(Vec::new());
+        let mut events = Vec::new();
+        auto_resume(
+            &sessions,
+            &ids,
+            |session_id| {
+                attempted.lock().unwrap().push(session_id.clone());
+                let sessions = &sessions;
+                async move {
+                    if session_id == "s3" {
+                        return Err(AppError::Provider("agent not installed".to_string()));
+                    }
+                    let mut sessions = sessions.write().await;
+                    let entry = sessions.get_mut(&session_id).unwrap();
+                    entry.session.status = SessionStatus::Active;
+                    let adapter: SessionAdapter =
+                        Arc::new(tokio::sync::Mutex::new(Box::new(FakeAdapter::new(true))));
+                    entry.adapter = Some(adapter);
+                    Ok(entry.session.clone())
+                }
+            },
+            |event| events.push(event),
+        )
+        .await;
 
-        let adapter = adapter.ok_or_else(|| {
-            AppError::InvalidOperation("Session is not active".to_string())
-        })?;
+        let mut attempted = attempted.into_inner().unwrap();
+        attempted.sort();
+        assert_eq!(attempted, ["s1", "s3"]);
 
-        let mut adapter = adapter.lock().await;
-        adapter.set_config_option(&config_id, &value).await?;
+        events.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+        assert_eq!(events.len(), 2);
+        assert!(events.iter().all(|e| e.total == 2));
+        assert_eq!(events[0].error, None);
+        assert!(events[1]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("agent not installed"));
 
-        Ok(())
+        let sessions = sessions.read().await;
+        assert!(sessions["s1"].adapter.is_some());
+        assert_eq!(sessions["s1"].session.status, SessionStatus::Active);
+        assert!(sessions["s2"].adapter.is_none());
+        assert_eq!(sessions["s2"].session.status, SessionStatus::Paused);
+        assert!(sessions["s3"].adapter.is_none());
     }
 
-    /// Cancel the current ongoing generation for a session
-    pub async fn cancel_generation(&self, session_id: &str) -> AppResult<()> {
-        let adapter = {
-            let sessions = self.sessions.read().await;
-            sessions.get(session_id).and_then(|e| e.adapter.clone())
+    #[test]
+    fn omitted_use_local_follows_the_default_work_mode() {
+        let project = tempfile::tempdir().unwrap();
+        let request = |use_local: Option<bool>| {
+            let mut request: CreateSessionRequest = serde_json::from_value(serde_json::json!({
+                "name": "Session",
+                "provider": "claude",
+                "project_path": project.path().to_string_lossy(),
+            }))
+            .unwrap();
+            assert_eq!(request.use_local, None);
+            request.use_local = use_local;
+            request
         };
+        let settings = test_settings();
+        assert!(!uses_local_dir(&settings, &request(None)));
 
-        if let Some(adapter) = adapter {
-            let mut adapter = adapter.lock().await;
-            adapter.cancel().await?;
-            println!("[SessionManager] Generation cancelled for session {}", session_id);
-            Ok(())
-        } else {
-            Err(AppError::NotFound(format!(
-                "Session '{}' not found or not active",
-                session_id
-            )))
-        }
+        let mut app_settings = settings.get_settings();
+        app_settings
+            .general
+            .get_or_insert_with(Default::default)
+            .default_work_mode = Some("local".to_string());
+        settings.update_settings(app_settings).unwrap();
+        assert!(uses_local_dir(&settings, &request(None)));
+        assert!(!uses_local_dir(&settings, &request(Some(false))));
     }
 }
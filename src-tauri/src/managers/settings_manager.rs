@@ -1,15 +1,39 @@
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
 
 use parking_lot::RwLock;
 use tauri::AppHandle;
 
 use crate::error::{AppError, AppResult};
-use crate::models::{AppSettings, ProviderSettings};
+use crate::managers::WorktreeManager;
+use crate::models::{
+    AppSettings, EffectiveSettings, GeneralSettings, ProjectSettings, ProviderSettings,
+    DEFAULT_BRANCH_PREFIX,
+};
+
+/// Location of the project-scoped config, relative to the project root
+const PROJECT_CONFIG_PATH: &str = ".forkestra/config.json";
+
+/// How long updates are coalesced before the settings file is written
+const PERSIST_DEBOUNCE: Duration = Duration::from_millis(500);
+
+#[derive(Default)]
+struct PersistState {
+    /// In-memory settings changed since the last write
+    dirty: bool,
+    shutdown: bool,
+}
+
+type PersistSignal = Arc<(Mutex<PersistState>, Condvar)>;
 
 pub struct SettingsManager {
     settings: Arc<RwLock<AppSettings>>,
     settings_path: PathBuf,
+    persist_signal: PersistSignal,
+    /// False when the settings directory could not be used; changes then only
+    /// last for this run
+    persistent: bool,
 }
 
 impl SettingsManager {
@@ -61,10 +85,81 @@ impl SettingsManager {
             let _ = std::fs::write(&settings_path, json);
         }
 
-        Ok(Self {
+        Self::apply_runtime_settings(&settings);
+
+        Ok(Self::persisted_at(settings, settings_path))
+    }
+
+    /// Manager whose changes are written to `settings_path` in the background
+    fn persisted_at(settings: AppSettings, settings_path: PathBuf) -> Self {
+        let settings = Arc::new(RwLock::new(settings));
+        let persist_signal: PersistSignal = Arc::default();
+        Self::spawn_persist_thread(settings.clone(), settings_path.clone(), persist_signal.clone());
+
+        Self {
+            settings,
+            settings_path,
+            persist_signal,
+            persistent: true,
+        }
+    }
+
+    /// Default settings that are never written to disk, used when the settings
+    /// directory is unusable (read-only, disk full) so the app can still start
+    pub fn in_memory(error: &AppError) -> Self {
+        eprintln!(
+            "[SettingsManager] Settings will not be saved this run: {}",
+            error
+        );
+        let settings = AppSettings::default();
+        Self::apply_runtime_settings(&settings);
+
+        let settings_path = dirs::home_dir()
+            .unwrap_or_default()
+            .join(".forkestra")
+            .join("settings.json");
+
+        Self {
             settings: Arc::new(RwLock::new(settings)),
             settings_path,
-        })
+            persist_signal: Arc::default(),
+            persistent: false,
+        }
+    }
+
+    /// Background writer: once settings are marked dirty it waits
+    /// `PERSIST_DEBOUNCE` so a burst of updates results in a single write of the
+    /// final state.
+    fn spawn_persist_thread(
+        settings: Arc<RwLock<AppSettings>>,
+        settings_path: PathBuf,
+        signal: PersistSignal,
+    ) {
+        std::thread::spawn(move || {
+            let (lock, cvar) = &*signal;
+            loop {
+                {
+                    let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+                    while !state.dirty && !state.shutdown {
+                        state = cvar.wait(state).unwrap_or_else(|e| e.into_inner());
+                    }
+                    if state.shutdown {
+                        return;
+                    }
+                }
+
+                std::thread::sleep(PERSIST_DEBOUNCE);
+
+                let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+                if state.dirty {
+                    state.dirty = false;
+                    let snapshot = settings.read().clone();
+                    if let Err(e) = Self::write_settings(&settings_path, &snapshot) {
+                        eprintln!("[SettingsManager] Failed to persist settings: {}", e);
+                    }
+                }
+            }
+        });
     }
 
     /// Attempt to migrate old-format settings (tagged enum provider_settings) to new flat format.
@@ -147,6 +242,8 @@ impl SettingsManager {
         let settings: AppSettings = serde_json::from_str(json)
             .map_err(|e| AppError::InvalidOperation(format!("Invalid JSON: {}", e)))?;
 
+        Self::apply_runtime_settings(&settings);
+
         // Update in memory
         *self.settings.write() = settings;
 
@@ -155,6 +252,8 @@ impl SettingsManager {
     }
 
     pub fn update_settings(&self, settings: AppSettings) -> AppResult<()> {
+        Self::apply_runtime_settings(&settings);
+
         // Update in memory
         *self.settings.write() = settings;
 
@@ -187,11 +286,236 @@ impl SettingsManager {
         self.persist()
     }
 
+    /// Resolve settings for a project: the global settings with the project's
+    /// `.forkestra/config.json` layered on top. An unreadable project config is
+    /// logged and ignored.
+    pub fn get_effective_settings(&self, project_path: &Path) -> EffectiveSettings {
+        let mut settings = self.get_settings();
+        let config_path = project_path.join(PROJECT_CONFIG_PATH);
+
+        let project = if config_path.is_file() {
+            match std::fs::read_to_string(&config_path)
+                .map_err(|e| e.to_string())
+                .and_then(|c| serde_json::from_str::<ProjectSettings>(&c).map_err(|e| e.to_string()))
+            {
+                Ok(project) => Some(project),
+                Err(e) => {
+                    eprintln!(
+                        "[SettingsManager] Ignoring invalid project config {}: {}",
+                        config_path.display(),
+                        e
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let Some(project) = project else {
+            return EffectiveSettings {
+                settings,
+                default_provider: None,
+                default_base_branch: None,
+                project_config_path: None,
+            };
+        };
+
+        if let Some(overrides) = project.general {
+            let general = settings.general.get_or_insert_with(GeneralSettings::default);
+            Self::merge_general(general, overrides);
+        }
+        for (provider_id, enabled) in project.enabled_providers {
+            settings
+                .provider_settings
+                .entry(provider_id)
+                .or_default()
+                .enabled = enabled;
+        }
+
+        EffectiveSettings {
+            settings,
+            default_provider: project.default_provider,
+            default_base_branch: project.default_base_branch,
+            project_config_path: Some(config_path.to_string_lossy().to_string()),
+        }
+    }
+
+    /// Session branch prefix in effect for a project
+    pub fn branch_prefix(&self, project_path: &Path) -> String {
+        self.get_effective_settings(project_path)
+            .settings
+            .general
+            .and_then(|g| g.branch_prefix)
+            .unwrap_or_else(|| DEFAULT_BRANCH_PREFIX.to_string())
+    }
+
+    /// Set, or clear with `None`, the session branch prefix in a project's
+    /// `.forkestra/config.json`. The rest of the file is kept as it is.
+    pub fn set_project_branch_prefix(
+        &self,
+        project_path: &Path,
+        prefix: Option<&str>,
+    ) -> AppResult<()> {
+        if let Some(prefix) = prefix {
+            WorktreeManager::validate_branch_prefix(prefix)?;
+        }
+
+        let config_path = project_path.join(PROJECT_CONFIG_PATH);
+        let mut config = if config_path.is_file() {
+            let content = std::fs::read_to_string(&config_path)?;
+            serde_json::from_str(&content).map_err(|e| {
+                AppError::InvalidOperation(format!(
+                    "Invalid project config {}: {}",
+                    config_path.display(),
+                    e
+                ))
+            })?
+        } else {
+            serde_json::json!({})
+        };
+
+        let root = config.as_object_mut().ok_or_else(|| {
+            AppError::InvalidOperation(format!(
+                "Project config {} is not a JSON object",
+                config_path.display()
+            ))
+        })?;
+        let general = root
+            .entry("general")
+            .or_insert_with(|| serde_json::json!({}));
+        if !general.is_object() {
+            *general = serde_json::json!({});
+        }
+        if let Some(general) = general.as_object_mut() {
+            match prefix {
+                Some(prefix) => {
+                    general.insert("branchPrefix".to_string(), prefix.into());
+                }
+                None => {
+                    general.remove("branchPrefix");
+                }
+            }
+        }
+
+        if let Some(dir) = config_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let content = serde_json::to_string_pretty(&config)
+            .map_err(|e| AppError::Io(format!("Failed to serialize project config: {}", e)))?;
+        std::fs::write(&config_path, content)?;
+        Ok(())
+    }
+
+    /// Overwrite every field of `base` that `overrides` sets
+    fn merge_general(base: &mut GeneralSettings, overrides: GeneralSettings) {
+        if overrides.default_project_path.is_some() {
+            base.default_project_path = overrides.default_project_path;
+        }
+        if overrides.default_work_mode.is_some() {
+            base.default_work_mode = overrides.default_work_mode;
+        }
+        if overrides.post_merge_action.is_some() {
+            base.post_merge_action = overrides.post_merge_action;
+        }
+        if overrides.auto_commit_per_turn.is_some() {
+            base.auto_commit_per_turn = overrides.auto_commit_per_turn;
+        }
+        if overrides.quick_new_session_shortcut.is_some() {
+            base.quick_new_session_shortcut = overrides.quick_new_session_shortcut;
+        }
+        if overrides.file_tree_exclude.is_some() {
+            base.file_tree_exclude = overrides.file_tree_exclude;
+        }
+        if overrides.auto_resume_on_startup.is_some() {
+            base.auto_resume_on_startup = overrides.auto_resume_on_startup;
+        }
+        if overrides.branch_prefix.is_some() {
+            base.branch_prefix = overrides.branch_prefix;
+        }
+    }
+
+    /// Push settings that are read from global state (outside of the manager) into effect.
+    fn apply_runtime_settings(settings: &AppSettings) {
+        let advanced = settings.advanced.clone().unwrap_or_default();
+        crate::utils::set_max_log_line_length(
+            advanced
+                .max_log_line_length
+                .unwrap_or(crate::utils::DEFAULT_MAX_LOG_LINE_LENGTH),
+        );
+    }
+
+    /// Schedule a write of the current settings. The in-memory value is already
+    /// up to date; the file follows within `PERSIST_DEBOUNCE`.
     fn persist(&self) -> AppResult<()> {
-        let settings = self.settings.read().clone();
-        let content = serde_json::to_string_pretty(&settings)
+        if !self.persistent {
+            return Ok(());
+        }
+        let (lock, cvar) = &*self.persist_signal;
+        lock.lock().unwrap_or_else(|e| e.into_inner()).dirty = true;
+        cvar.notify_one();
+        Ok(())
+    }
+
+    /// Write pending settings to disk immediately. Called on app exit.
+    pub fn flush(&self) -> AppResult<()> {
+        let (lock, _) = &*self.persist_signal;
+        let mut state = lock.lock().unwrap_or_else(|e| e.into_inner());
+        if !state.dirty {
+            return Ok(());
+        }
+        state.dirty = false;
+        let snapshot = self.settings.read().clone();
+        Self::write_settings(&self.settings_path, &snapshot)
+    }
+
+    fn write_settings(path: &Path, settings: &AppSettings) -> AppResult<()> {
+        let content = serde_json::to_string_pretty(settings)
             .map_err(|e| AppError::Io(format!("Failed to serialize settings: {}", e)))?;
-        std::fs::write(&self.settings_path, content)?;
+        std::fs::write(path, content)?;
         Ok(())
     }
 }
+
+impl Drop for SettingsManager {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            eprintln!("[SettingsManager] Failed to flush settings: {}", e);
+        }
+        let (lock, cvar) = &*self.persist_signal;
+        lock.lock().unwrap_or_else(|e| e.into_inner()).shutdown = true;
+        cvar.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_burst_of_updates_is_written_once_in_its_final_state() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let manager = SettingsManager::persisted_at(AppSettings::default(), path.clone());
+
+        for i in 0..20 {
+            let mut provider = manager
+                .get_provider_settings_by_id("claude")
+                .unwrap_or_default();
+            provider.env_vars.insert("BURST".to_string(), i.to_string());
+            manager
+                .update_provider_settings_by_id("claude", provider)
+                .unwrap();
+        }
+        // Nothing is written while updates keep coming in
+        assert!(!path.exists());
+
+        std::thread::sleep(PERSIST_DEBOUNCE * 3);
+        let written: AppSettings =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(
+            written.provider_settings["claude"].env_vars.get("BURST"),
+            Some(&"19".to_string())
+        );
+    }
+}
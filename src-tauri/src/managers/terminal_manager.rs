@@ -13,6 +13,9 @@ use crate::error::{AppError, AppResult};
 /// Maximum bytes to keep in the scrollback buffer per terminal
 const SCROLLBACK_BUFFER_CAP: usize = 256 * 1024; // 256 KB
 
+/// Ctrl+C to interrupt the foreground process, then Ctrl+L to clear the screen
+const INTERRUPT_AND_CLEAR_SEQUENCE: &[u8] = b"\x03\x0c";
+
 /// A ring buffer that keeps the most recent bytes up to a capacity.
 struct ScrollbackBuffer {
     buf: VecDeque<u8>,
@@ -36,6 +39,10 @@ impl ScrollbackBuffer {
         }
     }
 
+    fn clear(&mut self) {
+        self.buf.clear();
+    }
+
     fn as_bytes(&self) -> Vec<u8> {
         self.buf.iter().copied().collect()
     }
@@ -51,6 +58,15 @@ pub struct TerminalInfo {
     pub scrollback: String,
 }
 
+/// Lightweight view of a live terminal, without its scrollback
+#[derive(serde::Serialize, Clone)]
+pub struct TerminalSummary {
+    pub id: String,
+    pub session_id: String,
+    pub name: String,
+    pub cwd: String,
+}
+
 pub struct TerminalInstance {
     pub id: String,
     pub session_id: String,
@@ -242,6 +258,38 @@ impl TerminalManager {
         }
     }
 
+    /// Interrupt the foreground process, clear the screen and drop the
+    /// scrollback buffer, then emit `terminal:cleared`
+    pub async fn clear_terminal(&self, terminal_id: &str) -> AppResult<()> {
+        let terminals = self.terminals.lock().await;
+
+        if let Some(terminal) = terminals.get(terminal_id) {
+            let writer = terminal.writer.clone();
+            let mut writer = writer.lock().await;
+            writer
+                .write_all(INTERRUPT_AND_CLEAR_SEQUENCE)
+                .map_err(|e| AppError::Internal(format!("Failed to write to PTY: {}", e)))?;
+            writer
+                .flush()
+                .map_err(|e| AppError::Internal(format!("Failed to flush PTY: {}", e)))?;
+
+            if let Ok(mut sb) = terminal.scrollback.lock() {
+                sb.clear();
+            }
+
+            let _ = self.app_handle.emit(
+                "terminal:cleared",
+                serde_json::json!({ "terminalId": terminal_id }),
+            );
+            Ok(())
+        } else {
+            Err(AppError::NotFound(format!(
+                "Terminal '{}' not found",
+                terminal_id
+            )))
+        }
+    }
+
     /// Resize a terminal
     pub async fn resize_terminal(
         &self,
@@ -279,6 +327,22 @@ impl TerminalManager {
             .collect()
     }
 
+    /// Every live terminal across all sessions, grouped by session
+    pub async fn list_all(&self) -> Vec<TerminalSummary> {
+        let terminals = self.terminals.lock().await;
+        let mut all: Vec<TerminalSummary> = terminals
+            .values()
+            .map(|t| TerminalSummary {
+                id: t.id.clone(),
+                session_id: t.session_id.clone(),
+                name: t.name.clone(),
+                cwd: t.cwd.clone(),
+            })
+            .collect();
+        all.sort_by(|a, b| a.session_id.cmp(&b.session_id).then_with(|| a.name.cmp(&b.name)));
+        all
+    }
+
     /// Get all terminals for a session
     pub async fn get_session_terminals(&self, session_id: &str) -> Vec<String> {
         let terminals = self.terminals.lock().await;
@@ -1,12 +1,24 @@
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 use git2::{BranchType, Repository, RepositoryState, StatusOptions};
 use sha2::{Digest, Sha256};
 
 use crate::error::{AppError, AppResult};
 use crate::models::session::{
-    ConflictContent, GitFileStatus, GitFileStatusKind, GitScmStatus, MergeRebaseResult,
+    BlameLine, ConflictContent, FileDiff, GitFileStatus, GitFileStatusKind, GitScmStatus,
+    MergeRebaseResult, ProjectInfo, SessionFileDiff, SessionGitOverview, WorktreeInfo,
+    WorktreeRepairReport,
 };
+use crate::models::DEFAULT_BRANCH_PREFIX;
+use crate::utils::is_within_dir;
+
+/// Attempts at deleting a worktree directory before giving up
+const REMOVE_ATTEMPTS: u32 = 4;
+/// Delay before the first retry, doubled after each failed attempt
+const REMOVE_RETRY_DELAY: Duration = Duration::from_millis(100);
+/// Most leftover files reported after a failed worktree removal
+const MAX_LEFTOVER_FILES: usize = 50;
 
 pub struct WorktreeManager;
 
@@ -17,7 +29,9 @@ impl WorktreeManager {
         session_id: &str,
         base_branch: Option<&str>,
         fetch_first: bool,
+        branch_prefix: &str,
     ) -> AppResult<(PathBuf, String)> {
+        Self::validate_branch_prefix(branch_prefix)?;
         let repo = Repository::open(project_path)?;
 
         // Fetch all remotes if requested (to ensure remote branches are up to date)
@@ -29,7 +43,7 @@ impl WorktreeManager {
         let base = base_branch.unwrap_or("main");
 
         // Create branch name for the session
-        let branch_name = format!("forkestra/session-{}", session_id);
+        let branch_name = format!("{}{}", branch_prefix, session_id);
 
         // Get the base commit
         let base_commit = {
@@ -62,75 +76,648 @@ impl WorktreeManager {
         Ok((worktree_path, branch_name))
     }
 
-    /// List all worktrees for a project
-    pub fn list_worktrees(project_path: &Path) -> AppResult<Vec<String>> {
+    /// Check that branches named `<prefix><session id>` are valid git refs
+    pub fn validate_branch_prefix(prefix: &str) -> AppResult<()> {
+        if prefix.trim().is_empty()
+            || !git2::Reference::is_valid_name(&format!("refs/heads/{}session", prefix))
+        {
+            return Err(AppError::InvalidOperation(format!(
+                "Invalid branch prefix '{}'",
+                prefix
+            )));
+        }
+        Ok(())
+    }
+
+    /// Name of a session's branch: `<branch_prefix><session id>`, or the name
+    /// under the default prefix for sessions created before the prefix was
+    /// changed. Falls back to the configured name when neither branch exists.
+    fn session_branch_name(repo: &Repository, session_id: &str, branch_prefix: &str) -> String {
+        let configured = format!("{}{}", branch_prefix, session_id);
+        let legacy = format!("{}{}", DEFAULT_BRANCH_PREFIX, session_id);
+        if repo.find_branch(&configured, BranchType::Local).is_err()
+            && repo.find_branch(&legacy, BranchType::Local).is_ok()
+        {
+            return legacy;
+        }
+        configured
+    }
+
+    /// List all linked worktrees of a project with their checked-out branch.
+    /// `is_session` is left false; the caller knows which ones are sessions.
+    pub fn list_worktrees(project_path: &Path) -> AppResult<Vec<WorktreeInfo>> {
         let repo = Repository::open(project_path)?;
-        let worktrees = repo.worktrees()?;
+        let names = repo.worktrees()?;
 
-        Ok(worktrees
+        let mut worktrees = Vec::new();
+        for name in names.iter().flatten() {
+            let Ok(worktree) = repo.find_worktree(name) else {
+                continue;
+            };
+            let branch = Repository::open_from_worktree(&worktree)
+                .ok()
+                .and_then(|wt_repo| {
+                    let head = wt_repo.head().ok()?;
+                    head.is_branch()
+                        .then(|| head.shorthand().map(str::to_string))
+                        .flatten()
+                });
+            worktrees.push(WorktreeInfo {
+                name: name.to_string(),
+                path: worktree.path().to_string_lossy().to_string(),
+                branch,
+                is_session: false,
+            });
+        }
+        Ok(worktrees)
+    }
+
+    /// Number of worktrees of a project that live in Forkestra's worktree
+    /// directory. Worktrees the user added elsewhere are not counted.
+    pub fn count_session_worktrees(project_path: &Path) -> AppResult<usize> {
+        let worktree_base = Self::get_worktree_base_path(project_path)?;
+        Ok(Self::list_worktrees(project_path)?
             .iter()
-            .flatten()
-            .map(|s| s.to_string())
-            .collect())
+            .filter(|w| is_within_dir(Path::new(&w.path), &worktree_base))
+            .count())
     }
 
     /// Remove a worktree
     ///
     /// `worktree_path` is the actual filesystem path of the worktree (stored in
-    /// the session record) so we don't need to re-derive it.
+    /// the session record) so we don't need to re-derive it from the current
+    /// worktree base setting. The directory is only deleted when it is
+    /// recognisably this session's worktree, never the project itself.
+    ///
+    /// Deleting the directory is retried because files can stay locked for a
+    /// moment after the agent exits. If it keeps failing, git's worktree
+    /// metadata is pruned anyway and the files left behind are returned.
     pub fn remove_worktree(
         project_path: &Path,
         session_id: &str,
         worktree_path: &Path,
-    ) -> AppResult<()> {
+        branch_prefix: &str,
+    ) -> AppResult<Vec<String>> {
+        let repo = Repository::open(project_path)?;
+
+        // Resolve the branch while the worktree still points at it
+        let branch_name = Self::session_branch_name(&repo, session_id, branch_prefix);
+        let leftover = Self::remove_worktree_dir(&repo, project_path, session_id, worktree_path)?;
+
+        // Also delete the branch
+        if let Ok(mut branch) = repo.find_branch(&branch_name, BranchType::Local) {
+            branch.delete()?;
+        }
+
+        if !leftover.is_empty() {
+            eprintln!(
+                "[WorktreeManager] Worktree for session {} removed with {} files left behind",
+                session_id,
+                leftover.len()
+            );
+        }
+        Ok(leftover)
+    }
+
+    /// Remove a session's worktree but keep its branch, so the work is still
+    /// there to check out or merge later. Returns the files left behind.
+    pub fn evict_worktree(
+        project_path: &Path,
+        session_id: &str,
+        worktree_path: &Path,
+    ) -> AppResult<Vec<String>> {
         let repo = Repository::open(project_path)?;
+        let leftover = Self::remove_worktree_dir(&repo, project_path, session_id, worktree_path)?;
+        println!(
+            "[WorktreeManager] Evicted worktree of session {} ({} files left behind)",
+            session_id,
+            leftover.len()
+        );
+        Ok(leftover)
+    }
+
+    /// Delete a session's worktree directory and prune it from git, leaving
+    /// the branch alone
+    fn remove_worktree_dir(
+        repo: &Repository,
+        project_path: &Path,
+        session_id: &str,
+        worktree_path: &Path,
+    ) -> AppResult<Vec<String>> {
+        if Self::same_path(worktree_path, project_path) {
+            return Err(AppError::InvalidOperation(format!(
+                "Refusing to remove '{}': it is the project directory",
+                worktree_path.display()
+            )));
+        }
+
+        let mut leftover = Vec::new();
 
         // Find and prune the worktree
         if let Ok(worktree) = repo.find_worktree(session_id) {
-            // Check if worktree is valid and prune if needed
-            if worktree.validate().is_err() {
-                worktree.prune(Some(
-                    git2::WorktreePruneOptions::new()
-                        .valid(true)
-                        .working_tree(true),
-                ))?;
-            } else {
-                // Remove the worktree directory first
-                if worktree_path.exists() {
-                    std::fs::remove_dir_all(worktree_path)?;
+            // A valid worktree still has its directory: remove it first, but
+            // only where git agrees it lives
+            if worktree.validate().is_ok() {
+                let dir = if Self::same_path(worktree.path(), worktree_path) {
+                    Some(worktree_path)
+                } else {
+                    eprintln!(
+                        "[WorktreeManager] Stored path {} differs from git's {} for session {}, removing git's path",
+                        worktree_path.display(),
+                        worktree.path().display(),
+                        session_id
+                    );
+                    Some(worktree.path()).filter(|p| !Self::same_path(p, project_path))
+                };
+                if let Some(dir) = dir {
+                    leftover = Self::remove_dir_with_retry(dir);
                 }
-
-                // Then prune
-                worktree.prune(Some(
-                    git2::WorktreePruneOptions::new()
-                        .valid(true)
-                        .working_tree(true),
-                ))?;
             }
+
+            // Then prune. With files left behind only git's metadata is dropped,
+            // so the worktree stops being listed while the leftovers are reported
+            worktree.prune(Some(
+                git2::WorktreePruneOptions::new()
+                    .valid(true)
+                    .working_tree(leftover.is_empty()),
+            ))?;
         } else {
-            // Worktree not found in git, but directory may still exist – clean it up
-            if worktree_path.exists() {
-                std::fs::remove_dir_all(worktree_path)?;
+            // Worktree not found in git, but directory may still exist – clean it
+            // up if it still looks like a linked worktree (`.git` is a file there)
+            if worktree_path.join(".git").is_file() {
+                leftover = Self::remove_dir_with_retry(worktree_path);
+            } else if worktree_path.exists() {
+                eprintln!(
+                    "[WorktreeManager] Not removing {}: not a git worktree",
+                    worktree_path.display()
+                );
             }
         }
+        Ok(leftover)
+    }
 
-        // Also delete the branch
-        let branch_name = format!("forkestra/session-{}", session_id);
-        if let Ok(mut branch) = repo.find_branch(&branch_name, BranchType::Local) {
-            branch.delete()?;
+    /// Delete a directory, retrying with backoff while it fails. Returns the
+    /// files still present after the last attempt, or the directory itself
+    /// when none can be listed.
+    fn remove_dir_with_retry(path: &Path) -> Vec<String> {
+        let mut delay = REMOVE_RETRY_DELAY;
+        for attempt in 1..=REMOVE_ATTEMPTS {
+            match std::fs::remove_dir_all(path) {
+                Ok(()) => return Vec::new(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+                Err(e) => {
+                    eprintln!(
+                        "[WorktreeManager] Failed to remove {} (attempt {}/{}): {}",
+                        path.display(),
+                        attempt,
+                        REMOVE_ATTEMPTS,
+                        e
+                    );
+                    if attempt < REMOVE_ATTEMPTS {
+                        std::thread::sleep(delay);
+                        delay *= 2;
+                    }
+                }
+            }
+        }
+
+        let mut leftover = Vec::new();
+        Self::collect_files(path, &mut leftover);
+        if leftover.is_empty() && path.exists() {
+            leftover.push(path.display().to_string());
+        }
+        leftover
+    }
+
+    /// Collect up to `MAX_LEFTOVER_FILES` file paths below `dir`
+    fn collect_files(dir: &Path, out: &mut Vec<String>) {
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            if out.len() >= MAX_LEFTOVER_FILES {
+                return;
+            }
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                Self::collect_files(&path, out);
+            } else {
+                out.push(path.display().to_string());
+            }
+        }
+    }
+
+    /// Whether a session's worktree directory is still there. A worktree git
+    /// still lists but can no longer validate counts as gone.
+    pub fn worktree_exists(project_path: &Path, session_id: &str, worktree_path: &Path) -> bool {
+        if !worktree_path.is_dir() {
+            return false;
         }
+        Repository::open(project_path)
+            .ok()
+            .and_then(|repo| repo.find_worktree(session_id).ok())
+            .map_or(true, |worktree| worktree.validate().is_ok())
+    }
 
+    /// Unlock a session worktree and repair the links between it and the
+    /// repository after either of them moved. Returns the repairs made.
+    pub fn verify_and_unlock(
+        project_path: &Path,
+        session_id: &str,
+        worktree_path: &Path,
+    ) -> AppResult<WorktreeRepairReport> {
+        let repo = Repository::open(project_path)?;
+        let worktree = repo.find_worktree(session_id).map_err(|_| {
+            AppError::NotFound(format!("Worktree for session '{}' not found", session_id))
+        })?;
+        let mut actions = Vec::new();
+
+        if let git2::WorktreeLockStatus::Locked(reason) = worktree.is_locked()? {
+            worktree.unlock()?;
+            actions.push(match reason.filter(|r| !r.trim().is_empty()) {
+                Some(reason) => format!("Unlocked worktree (locked: {})", reason.trim()),
+                None => "Unlocked worktree".to_string(),
+            });
+        }
+
+        if worktree_path.is_dir() {
+            // The worktree's `.git` file points at its admin dir in the repository
+            // and the admin dir's `gitdir` file points back; relative paths are
+            // relative to the file holding them
+            let admin_dir = repo.path().join("worktrees").join(session_id);
+            let dot_git = worktree_path.join(".git");
+            let links_to = |file: &Path, target: &Path, prefix: &str| {
+                std::fs::read_to_string(file)
+                    .ok()
+                    .and_then(|c| c.trim().strip_prefix(prefix).map(|p| p.trim().to_string()))
+                    .is_some_and(|p| {
+                        let dir = file.parent().unwrap_or(Path::new(""));
+                        Self::same_path(&dir.join(p), target)
+                    })
+            };
+
+            if !links_to(&dot_git, &admin_dir, "gitdir:") {
+                std::fs::write(&dot_git, format!("gitdir: {}\n", admin_dir.display()))?;
+                actions.push(format!(
+                    "Pointed {} at {}",
+                    dot_git.display(),
+                    admin_dir.display()
+                ));
+            }
+            let gitdir_file = admin_dir.join("gitdir");
+            if !links_to(&gitdir_file, &dot_git, "") {
+                std::fs::write(&gitdir_file, format!("{}\n", dot_git.display()))?;
+                actions.push(format!(
+                    "Pointed {} at {}",
+                    gitdir_file.display(),
+                    dot_git.display()
+                ));
+            }
+        }
+
+        let is_valid = repo
+            .find_worktree(session_id)
+            .is_ok_and(|worktree| worktree.validate().is_ok());
+        println!(
+            "[WorktreeManager] Verified worktree {}: {} repairs, valid={}",
+            session_id,
+            actions.len(),
+            is_valid
+        );
+        Ok(WorktreeRepairReport {
+            session_id: session_id.to_string(),
+            actions,
+            is_valid,
+        })
+    }
+
+    /// Remove every Forkestra session worktree of a project along with its
+    /// session branch. Worktrees not created by Forkestra are left alone.
+    /// Returns the ids of the removed worktrees.
+    pub fn remove_all_worktrees(
+        project_path: &Path,
+        branch_prefix: &str,
+    ) -> AppResult<Vec<String>> {
+        let repo = Repository::open(project_path)?;
+        let worktree_base = Self::get_worktree_base_path(project_path)?;
+        let names: Vec<String> = repo
+            .worktrees()?
+            .iter()
+            .flatten()
+            .map(|s| s.to_string())
+            .collect();
+
+        let mut removed = Vec::new();
+        for name in names {
+            let Ok(worktree) = repo.find_worktree(&name) else {
+                continue;
+            };
+            // A session worktree still has its session branch, or lives in
+            // Forkestra's worktree directory if that branch was deleted
+            let branch_name = Self::session_branch_name(&repo, &name, branch_prefix);
+            let is_session_worktree = repo.find_branch(&branch_name, BranchType::Local).is_ok()
+                || is_within_dir(worktree.path(), &worktree_base);
+            if !is_session_worktree {
+                continue;
+            }
+
+            match Self::remove_worktree(project_path, &name, worktree.path(), branch_prefix) {
+                Ok(leftover) => {
+                    if !leftover.is_empty() {
+                        eprintln!(
+                            "[WorktreeManager] Could not delete from worktree {}: {}",
+                            name,
+                            leftover.join(", ")
+                        );
+                    }
+                    removed.push(name);
+                }
+                Err(e) => eprintln!(
+                    "[WorktreeManager] Failed to remove worktree {}: {}",
+                    name, e
+                ),
+            }
+        }
+
+        println!(
+            "[WorktreeManager] Removed {} session worktrees from {}",
+            removed.len(),
+            project_path.display()
+        );
+        Ok(removed)
+    }
+
+    /// Check that a session branch can graduate to `new_branch_name`: the name
+    /// is a valid branch outside the session namespace that doesn't exist yet,
+    /// and the session branch is still there. Nothing is changed. Returns the
+    /// trimmed name.
+    pub fn check_graduation(
+        project_path: &Path,
+        session_id: &str,
+        new_branch_name: &str,
+        branch_prefix: &str,
+    ) -> AppResult<String> {
+        let new_branch_name = new_branch_name.trim();
+        if new_branch_name.starts_with("forkestra/") || new_branch_name.starts_with(branch_prefix) {
+            return Err(AppError::InvalidOperation(format!(
+                "Branch name '{}' is inside the session namespace",
+                new_branch_name
+            )));
+        }
+        if !git2::Reference::is_valid_name(&format!("refs/heads/{}", new_branch_name)) {
+            return Err(AppError::InvalidOperation(format!(
+                "Invalid branch name '{}'",
+                new_branch_name
+            )));
+        }
+
+        let repo = Repository::open(project_path)?;
+        if repo.find_branch(new_branch_name, BranchType::Local).is_ok() {
+            return Err(AppError::InvalidOperation(format!(
+                "Branch '{}' already exists",
+                new_branch_name
+            )));
+        }
+        let branch_name = Self::session_branch_name(&repo, session_id, branch_prefix);
+        repo.find_branch(&branch_name, BranchType::Local)
+            .map_err(|_| AppError::NotFound(format!("Branch '{}' not found", branch_name)))?;
+        Ok(new_branch_name.to_string())
+    }
+
+    /// Turn a session branch into a regular branch: rename it out of the
+    /// `forkestra/` namespace, then remove the worktree. Returns the new name.
+    pub fn graduate_branch(
+        project_path: &Path,
+        session_id: &str,
+        new_branch_name: &str,
+        worktree_path: &Path,
+        branch_prefix: &str,
+    ) -> AppResult<String> {
+        let new_branch_name =
+            Self::check_graduation(project_path, session_id, new_branch_name, branch_prefix)?;
+
+        let repo = Repository::open(project_path)?;
+        let branch_name = Self::session_branch_name(&repo, session_id, branch_prefix);
+        let mut branch = repo.find_branch(&branch_name, BranchType::Local)?;
+        branch.rename(&new_branch_name, false)?;
+
+        // The session branch no longer exists, so this only removes the worktree
+        let leftover =
+            Self::remove_worktree(project_path, session_id, worktree_path, branch_prefix)?;
+        if !leftover.is_empty() {
+            eprintln!(
+                "[WorktreeManager] Could not delete from graduated worktree: {}",
+                leftover.join(", ")
+            );
+        }
+
+        println!(
+            "[WorktreeManager] Graduated {} to {}",
+            branch_name, new_branch_name
+        );
+        Ok(new_branch_name)
+    }
+
+    /// Delete a session's branch but keep its worktree directory. The worktree
+    /// HEAD is detached at the branch tip first so git allows the deletion.
+    pub fn delete_session_branch(
+        project_path: &Path,
+        session_id: &str,
+        worktree_path: &Path,
+        branch_prefix: &str,
+    ) -> AppResult<()> {
+        let repo = Repository::open(project_path)?;
+        let branch_name = Self::session_branch_name(&repo, session_id, branch_prefix);
+
+        if worktree_path.exists() {
+            let worktree_repo = Repository::open(worktree_path)?;
+            let head = worktree_repo.head()?;
+            if head.shorthand() == Some(branch_name.as_str()) {
+                let oid = head.peel_to_commit()?.id();
+                worktree_repo.set_head_detached(oid)?;
+            }
+        }
+
+        let mut branch = repo
+            .find_branch(&branch_name, BranchType::Local)
+            .map_err(|_| {
+                AppError::NotFound(format!("Branch '{}' not found", branch_name))
+            })?;
+        branch.delete()?;
+
+        println!(
+            "[WorktreeManager] Deleted branch {} (worktree kept at {})",
+            branch_name,
+            worktree_path.display()
+        );
         Ok(())
     }
 
+    /// Reject paths that are absolute or could climb out of the worktree
+    fn check_relative_path(relative_path: &str) -> AppResult<&Path> {
+        let relative = Path::new(relative_path);
+        if relative.is_absolute()
+            || relative
+                .components()
+                .any(|c| !matches!(c, std::path::Component::Normal(_)))
+        {
+            return Err(AppError::InvalidOperation(format!(
+                "Path '{}' must be relative to the worktree",
+                relative_path
+            )));
+        }
+        Ok(relative)
+    }
+
+    /// Diff one file of a session's worktree, including uncommitted edits,
+    /// against the point where the session branch forked from the project's
+    /// default branch. An unchanged file has no status and an empty patch.
+    pub fn diff_file(
+        project_path: &Path,
+        session_id: &str,
+        relative_path: &str,
+    ) -> AppResult<SessionFileDiff> {
+        Self::check_relative_path(relative_path)?;
+
+        let repo = Repository::open(project_path)?;
+        let worktree = repo.find_worktree(session_id).map_err(|_| {
+            AppError::NotFound(format!("Worktree for session '{}' not found", session_id))
+        })?;
+        let worktree_repo = Repository::open_from_worktree(&worktree)?;
+
+        let base_branch = Self::get_default_branch(project_path)?;
+        let head = worktree_repo.head()?.peel_to_commit()?;
+        let base = worktree_repo
+            .find_branch(&base_branch, BranchType::Local)
+            .map_err(|_| AppError::NotFound(format!("Base branch '{}' not found", base_branch)))?
+            .get()
+            .peel_to_commit()?;
+        let fork_point =
+            worktree_repo.find_commit(worktree_repo.merge_base(head.id(), base.id())?)?;
+
+        let mut options = git2::DiffOptions::new();
+        options
+            .pathspec(relative_path)
+            .disable_pathspec_match(true)
+            .include_untracked(true)
+            .show_untracked_content(true);
+        let diff = worktree_repo
+            .diff_tree_to_workdir_with_index(Some(&fork_point.tree()?), Some(&mut options))?;
+
+        let status = diff.deltas().next().map(|delta| match delta.status() {
+            git2::Delta::Added | git2::Delta::Untracked => GitFileStatusKind::Added,
+            git2::Delta::Deleted => GitFileStatusKind::Deleted,
+            git2::Delta::Conflicted => GitFileStatusKind::Conflicted,
+            _ => GitFileStatusKind::Modified,
+        });
+
+        let mut patch = String::new();
+        diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+            let origin = line.origin();
+            if origin == '+' || origin == '-' || origin == ' ' {
+                patch.push(origin);
+            }
+            patch.push_str(std::str::from_utf8(line.content()).unwrap_or(""));
+            true
+        })?;
+
+        Ok(SessionFileDiff {
+            path: relative_path.to_string(),
+            status,
+            patch,
+        })
+    }
+
+    /// Blame a file in a session's worktree, including uncommitted edits.
+    /// Lines committed after the session branch forked from the project's
+    /// default branch, or not committed at all, are marked as session changes.
+    pub fn blame_file(
+        project_path: &Path,
+        session_id: &str,
+        relative_path: &str,
+    ) -> AppResult<Vec<BlameLine>> {
+        let relative = Self::check_relative_path(relative_path)?;
+
+        let repo = Repository::open(project_path)?;
+        let worktree = repo.find_worktree(session_id).map_err(|_| {
+            AppError::NotFound(format!("Worktree for session '{}' not found", session_id))
+        })?;
+        let worktree_repo = Repository::open_from_worktree(&worktree)?;
+        let workdir = worktree_repo
+            .workdir()
+            .ok_or_else(|| AppError::Git("Worktree has no working directory".to_string()))?
+            .to_path_buf();
+        let contents = std::fs::read(workdir.join(relative))?;
+
+        // Commits reachable from the fork point predate the session
+        let base_branch = Self::get_default_branch(project_path)?;
+        let fork_point = match (
+            worktree_repo.head().and_then(|h| h.peel_to_commit()),
+            worktree_repo.find_branch(&base_branch, BranchType::Local),
+        ) {
+            (Ok(head), Ok(base)) => worktree_repo
+                .merge_base(head.id(), base.get().peel_to_commit()?.id())
+                .ok(),
+            _ => None,
+        };
+
+        let committed = worktree_repo.blame_file(relative, None)?;
+        let blame = committed.blame_buffer(&contents)?;
+
+        let mut lines = Vec::new();
+        for hunk in blame.iter() {
+            let oid = hunk.final_commit_id();
+            let signature = hunk.final_signature();
+            let (sha, author, author_email, is_session_change) = if oid.is_zero() {
+                (None, None, None, true)
+            } else {
+                let is_session_change = match fork_point {
+                    Some(fork_point) => {
+                        oid != fork_point
+                            && !worktree_repo.graph_descendant_of(fork_point, oid)?
+                    }
+                    None => false,
+                };
+                (
+                    Some(oid.to_string()),
+                    signature.name().map(str::to_string),
+                    signature.email().map(str::to_string),
+                    is_session_change,
+                )
+            };
+
+            let start = hunk.final_start_line();
+            for line_number in start..start + hunk.lines_in_hunk() {
+                lines.push(BlameLine {
+                    line_number,
+                    sha: sha.clone(),
+                    author: author.clone(),
+                    author_email: author_email.clone(),
+                    is_session_change,
+                });
+            }
+        }
+
+        Ok(lines)
+    }
+
+    /// Compare two paths after resolving symlinks, falling back to a plain comparison
+    fn same_path(a: &Path, b: &Path) -> bool {
+        match (a.canonicalize(), b.canonicalize()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => a == b,
+        }
+    }
+
     /// Merge worktree changes to a target branch
     pub fn merge_to_branch(
         project_path: &Path,
         session_id: &str,
         target_branch: &str,
+        branch_prefix: &str,
     ) -> AppResult<()> {
         let repo = Repository::open(project_path)?;
-        let branch_name = format!("forkestra/session-{}", session_id);
+        let branch_name = Self::session_branch_name(&repo, session_id, branch_prefix);
 
         // Get the session branch
         let session_branch = repo.find_branch(&branch_name, BranchType::Local)?;
@@ -184,6 +771,194 @@ impl WorktreeManager {
         Ok(())
     }
 
+    /// List the files that merging the session branch into `target_branch` would
+    /// change, flagging conflicts. The merge happens in memory only; neither the
+    /// working directory nor any ref is touched.
+    pub fn preview_merge(
+        project_path: &Path,
+        session_id: &str,
+        target_branch: &str,
+        branch_prefix: &str,
+    ) -> AppResult<Vec<FileDiff>> {
+        let repo = Repository::open(project_path)?;
+        let branch_name = Self::session_branch_name(&repo, session_id, branch_prefix);
+
+        let session_commit = repo
+            .find_branch(&branch_name, BranchType::Local)?
+            .get()
+            .peel_to_commit()?;
+        let target_commit = repo
+            .find_branch(target_branch, BranchType::Local)?
+            .get()
+            .peel_to_commit()?;
+        let target_tree = target_commit.tree()?;
+
+        let index = repo.merge_commits(&target_commit, &session_commit, None)?;
+
+        let conflict_paths: Vec<String> = if index.has_conflicts() {
+            index
+                .conflicts()?
+                .filter_map(|c| c.ok())
+                .filter_map(|c| {
+                    c.our
+                        .as_ref()
+                        .or(c.their.as_ref())
+                        .and_then(|e| String::from_utf8(e.path.clone()).ok())
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        let diff = repo.diff_tree_to_index(Some(&target_tree), Some(&index), None)?;
+        let mut files: Vec<FileDiff> = diff
+            .deltas()
+            .filter_map(|delta| {
+                let path = delta
+                    .new_file()
+                    .path()
+                    .or_else(|| delta.old_file().path())?
+                    .to_string_lossy()
+                    .to_string();
+                let status = match delta.status() {
+                    git2::Delta::Added => GitFileStatusKind::Added,
+                    git2::Delta::Deleted => GitFileStatusKind::Deleted,
+                    git2::Delta::Renamed => GitFileStatusKind::Renamed,
+                    git2::Delta::Copied => GitFileStatusKind::Copied,
+                    git2::Delta::Conflicted => GitFileStatusKind::Conflicted,
+                    _ => GitFileStatusKind::Modified,
+                };
+                let old_path = match status {
+                    GitFileStatusKind::Renamed | GitFileStatusKind::Copied => delta
+                        .old_file()
+                        .path()
+                        .map(|p| p.to_string_lossy().to_string()),
+                    _ => None,
+                };
+                let is_conflicted = conflict_paths.contains(&path);
+                Some(FileDiff {
+                    path,
+                    status: if is_conflicted {
+                        GitFileStatusKind::Conflicted
+                    } else {
+                        status
+                    },
+                    old_path,
+                    is_conflicted,
+                })
+            })
+            .collect();
+
+        for path in conflict_paths {
+            if !files.iter().any(|f| f.path == path) {
+                files.push(FileDiff {
+                    path,
+                    status: GitFileStatusKind::Conflicted,
+                    old_path: None,
+                    is_conflicted: true,
+                });
+            }
+        }
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+
+        Ok(files)
+    }
+
+    // ========== Worktree Snapshots ==========
+
+    /// Write a `.tar.gz` of a session's worktree to `destination`, uncommitted
+    /// and untracked files included. `.git` is never archived; with
+    /// `respect_gitignore`, files git ignores are left out too.
+    /// Returns the number of files archived.
+    pub fn snapshot_worktree(
+        project_path: &Path,
+        session_id: &str,
+        destination: &Path,
+        respect_gitignore: bool,
+    ) -> AppResult<usize> {
+        let repo = Repository::open(project_path)?;
+        let worktree = repo.find_worktree(session_id).map_err(|_| {
+            AppError::NotFound(format!("Worktree for session '{}' not found", session_id))
+        })?;
+        let worktree_repo = Repository::open_from_worktree(&worktree)?;
+        let workdir = worktree_repo
+            .workdir()
+            .ok_or_else(|| AppError::Git("Worktree has no working directory".to_string()))?
+            .to_path_buf();
+
+        // The archive would end up containing a partial copy of itself
+        if is_within_dir(destination, &workdir) {
+            return Err(AppError::InvalidOperation(
+                "The snapshot cannot be written inside the worktree".to_string(),
+            ));
+        }
+
+        let file = std::fs::File::create(destination)?;
+        let encoder = flate2::write::GzEncoder::new(file, flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        builder.follow_symlinks(false);
+
+        let ignore_repo = respect_gitignore.then_some(&worktree_repo);
+        let mut count = 0;
+        let result = Self::append_snapshot_dir(
+            &mut builder,
+            &workdir,
+            Path::new(""),
+            ignore_repo,
+            &mut count,
+        )
+        .and_then(|()| {
+            builder.into_inner()?.finish()?;
+            Ok(())
+        });
+        if let Err(e) = result {
+            let _ = std::fs::remove_file(destination);
+            return Err(e);
+        }
+
+        println!(
+            "[WorktreeManager] Snapshot of session {} written to {} ({} files)",
+            session_id,
+            destination.display(),
+            count
+        );
+        Ok(count)
+    }
+
+    /// Add the entries of `root/rel_dir` to a snapshot, in name order, skipping
+    /// `.git` and, when `ignore_repo` is given, paths git ignores
+    fn append_snapshot_dir<W: std::io::Write>(
+        builder: &mut tar::Builder<W>,
+        root: &Path,
+        rel_dir: &Path,
+        ignore_repo: Option<&Repository>,
+        count: &mut usize,
+    ) -> AppResult<()> {
+        let mut entries = std::fs::read_dir(root.join(rel_dir))?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|e| e.file_name());
+
+        for entry in entries {
+            if entry.file_name() == ".git" {
+                continue;
+            }
+            let rel_path = rel_dir.join(entry.file_name());
+            if let Some(repo) = ignore_repo {
+                if repo.is_path_ignored(&rel_path).unwrap_or(false) {
+                    continue;
+                }
+            }
+
+            if entry.file_type()?.is_dir() {
+                builder.append_dir(&rel_path, entry.path())?;
+                Self::append_snapshot_dir(builder, root, &rel_path, ignore_repo, count)?;
+            } else {
+                builder.append_path_with_name(entry.path(), &rel_path)?;
+                *count += 1;
+            }
+        }
+        Ok(())
+    }
+
     // ========== Worktree Agent Config Isolation ==========
 
     /// Copy agent config directories from the main repo into the worktree and
@@ -216,6 +991,14 @@ impl WorktreeManager {
             return;
         }
         let dst = dst_root.join(dir_name);
+        // Copying a directory into its own subtree would never finish
+        if is_within_dir(&dst, &src) {
+            eprintln!(
+                "[WorktreeManager] Warning: not copying {} into itself",
+                dir_name
+            );
+            return;
+        }
         if let Err(e) = Self::copy_dir_recursive(&src, &dst) {
             eprintln!(
                 "[WorktreeManager] Warning: failed to copy {} to worktree: {}",
@@ -225,6 +1008,7 @@ impl WorktreeManager {
     }
 
     /// Simple recursive directory copy. Creates `dst` if it does not exist.
+    /// Symlinks are skipped, so a symlink cycle cannot make it recurse forever.
     fn copy_dir_recursive(src: &Path, dst: &Path) -> std::io::Result<()> {
         if !dst.exists() {
             std::fs::create_dir_all(dst)?;
@@ -420,7 +1204,7 @@ impl WorktreeManager {
     /// - Physical isolation from the original project (agents cannot `../` out)
     /// - Short, collision-resistant directory names
     /// - Human-readable project name prefix
-    fn get_worktree_base_path(project_path: &Path) -> AppResult<PathBuf> {
+    pub fn get_worktree_base_path(project_path: &Path) -> AppResult<PathBuf> {
         let home = dirs::home_dir().ok_or_else(|| {
             AppError::InvalidOperation("Could not determine home directory".to_string())
         })?;
@@ -443,14 +1227,18 @@ impl WorktreeManager {
             .unwrap_or_else(|| "project".to_string());
 
         let dir_name = format!("{}-{}", project_name, short_hash);
-        let worktree_base = home
-            .join(".forkestra")
-            .join("worktrees")
-            .join(dir_name);
+        let forkestra_dir = home.join(".forkestra");
+        let worktree_base = forkestra_dir.join("worktrees").join(dir_name);
 
         if !worktree_base.exists() {
             std::fs::create_dir_all(&worktree_base)?;
         }
+
+        // When the project itself contains the base (e.g. the home directory was
+        // opened as a project), keep the worktrees out of its git status.
+        if forkestra_dir.starts_with(&canonical) {
+            Self::ensure_gitignore_entry(&forkestra_dir, "*");
+        }
         Ok(worktree_base)
     }
 
@@ -459,6 +1247,33 @@ impl WorktreeManager {
         Repository::open(path).is_ok()
     }
 
+    /// Inspect a directory before creating a session in it.
+    /// A non-repository path is reported as such rather than returned as an error.
+    pub fn inspect_project(path: &Path) -> AppResult<ProjectInfo> {
+        if !Self::is_git_repo(path) {
+            return Ok(ProjectInfo {
+                is_git_repo: false,
+                default_branch: None,
+                branches: Vec::new(),
+                is_dirty: false,
+            });
+        }
+
+        let status = Self::get_scm_status(path)?;
+        let is_dirty = !status.staged.is_empty()
+            || !status.unstaged.is_empty()
+            || !status.untracked.is_empty()
+            || !status.conflicts.is_empty();
+
+        Ok(ProjectInfo {
+            is_git_repo: true,
+            // An empty repository has no branches yet
+            default_branch: Self::get_default_branch(path).ok(),
+            branches: Self::list_branches(path, false)?,
+            is_dirty,
+        })
+    }
+
     /// List all branches in a repository
     pub fn list_branches(project_path: &Path, include_remote: bool) -> AppResult<Vec<String>> {
         let repo = Repository::open(project_path)?;
@@ -606,6 +1421,171 @@ impl WorktreeManager {
         Ok((ahead, behind))
     }
 
+    /// Commits the session branch is ahead of and behind `base_branch`
+    pub fn ahead_behind(
+        project_path: &Path,
+        session_id: &str,
+        base_branch: &str,
+        branch_prefix: &str,
+    ) -> AppResult<(usize, usize)> {
+        let repo = Repository::open(project_path)?;
+        let branch_name = Self::session_branch_name(&repo, session_id, branch_prefix);
+
+        let session_tip = repo
+            .find_branch(&branch_name, BranchType::Local)
+            .map_err(|_| AppError::NotFound(format!("Branch '{}' not found", branch_name)))?
+            .get()
+            .peel_to_commit()?
+            .id();
+        let base_tip = repo
+            .find_branch(base_branch, BranchType::Local)
+            .or_else(|_| repo.find_branch(base_branch, BranchType::Remote))
+            .map_err(|_| AppError::NotFound(format!("Base branch '{}' not found", base_branch)))?
+            .get()
+            .peel_to_commit()?
+            .id();
+
+        Ok(repo.graph_ahead_behind(session_tip, base_tip)?)
+    }
+
+    /// `git format-patch`-style mbox of the session branch's commits since it
+    /// forked from the project's default branch, oldest first. Binary changes are
+    /// written as full binary patches so the series applies with `git am`.
+    /// Merge commits are skipped, as format-patch does.
+    pub fn format_patch(
+        project_path: &Path,
+        session_id: &str,
+        branch_prefix: &str,
+    ) -> AppResult<String> {
+        let repo = Repository::open(project_path)?;
+        let branch_name = Self::session_branch_name(&repo, session_id, branch_prefix);
+
+        let session_tip = repo
+            .find_branch(&branch_name, BranchType::Local)
+            .map_err(|_| AppError::NotFound(format!("Branch '{}' not found", branch_name)))?
+            .get()
+            .peel_to_commit()?
+            .id();
+        let base_branch = Self::get_default_branch(project_path)?;
+        let base_tip = repo
+            .find_branch(&base_branch, BranchType::Local)
+            .map_err(|_| AppError::NotFound(format!("Base branch '{}' not found", base_branch)))?
+            .get()
+            .peel_to_commit()?
+            .id();
+        let fork_point = repo.merge_base(session_tip, base_tip)?;
+
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push(session_tip)?;
+        revwalk.hide(fork_point)?;
+        revwalk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)?;
+        let mut commits = Vec::new();
+        for oid in revwalk {
+            let commit = repo.find_commit(oid?)?;
+            if commit.parent_count() <= 1 {
+                commits.push(commit);
+            }
+        }
+        if commits.is_empty() {
+            return Err(AppError::InvalidOperation(format!(
+                "Branch '{}' has no commits since it forked from '{}'",
+                branch_name, base_branch
+            )));
+        }
+
+        let total = commits.len();
+        let mut patch = String::new();
+        for (index, commit) in commits.iter().enumerate() {
+            let parent_tree = match commit.parent(0) {
+                Ok(parent) => Some(parent.tree()?),
+                Err(_) => None,
+            };
+            let mut diff_opts = git2::DiffOptions::new();
+            diff_opts.show_binary(true);
+            let diff = repo.diff_tree_to_tree(
+                parent_tree.as_ref(),
+                Some(&commit.tree()?),
+                Some(&mut diff_opts),
+            )?;
+
+            let mut email_opts = git2::EmailCreateOptions::new();
+            email_opts.diff_options().show_binary(true);
+            let email = git2::Email::from_diff(
+                &diff,
+                index + 1,
+                total,
+                &commit.id(),
+                commit.summary().unwrap_or(""),
+                commit.body().unwrap_or(""),
+                &commit.author(),
+                &mut email_opts,
+            )?;
+            patch.push_str(&String::from_utf8_lossy(email.as_slice()));
+        }
+
+        Ok(patch)
+    }
+
+    /// Ahead/behind and dirty state of each session branch against the project's
+    /// default branch. Sessions without a session branch are treated as
+    /// local-mode sessions and report the project's current branch instead.
+    pub fn project_sessions_overview(
+        project_path: &Path,
+        session_ids: &[String],
+        branch_prefix: &str,
+    ) -> AppResult<Vec<SessionGitOverview>> {
+        let repo = Repository::open(project_path)?;
+        let base_branch = Self::get_default_branch(project_path)?;
+        let base_oid = repo
+            .find_branch(&base_branch, BranchType::Local)?
+            .get()
+            .peel_to_commit()?
+            .id();
+
+        let is_dirty = |repo: &Repository| -> AppResult<bool> {
+            let mut opts = StatusOptions::new();
+            opts.include_untracked(true).recurse_untracked_dirs(false);
+            Ok(!repo.statuses(Some(&mut opts))?.is_empty())
+        };
+
+        let mut overview = Vec::with_capacity(session_ids.len());
+        for session_id in session_ids {
+            let worktree_branch = Self::session_branch_name(&repo, session_id, branch_prefix);
+            let (branch_name, tip, dirty, is_local) =
+                match repo.find_branch(&worktree_branch, BranchType::Local) {
+                    Ok(branch) => {
+                        let tip = branch.get().peel_to_commit()?.id();
+                        let dirty = match repo.find_worktree(session_id) {
+                            Ok(worktree) => Repository::open_from_worktree(&worktree)
+                                .map(|wt_repo| is_dirty(&wt_repo))
+                                .unwrap_or(Ok(false))?,
+                            Err(_) => false,
+                        };
+                        (worktree_branch, tip, dirty, false)
+                    }
+                    Err(_) => {
+                        let head = repo.head()?;
+                        let name = head.shorthand().unwrap_or("HEAD").to_string();
+                        let tip = head.peel_to_commit()?.id();
+                        (name, tip, is_dirty(&repo)?, true)
+                    }
+                };
+
+            let (ahead, behind) = repo.graph_ahead_behind(tip, base_oid)?;
+            overview.push(SessionGitOverview {
+                session_id: session_id.clone(),
+                branch_name,
+                base_branch: base_branch.clone(),
+                ahead,
+                behind,
+                is_dirty: dirty,
+                is_local,
+            });
+        }
+
+        Ok(overview)
+    }
+
     /// Pull from upstream (fast-forward only)
     pub fn pull_repository(project_path: &Path) -> AppResult<String> {
         let repo = Repository::open(project_path)?;
@@ -982,6 +1962,22 @@ impl WorktreeManager {
         Ok(oid.to_string())
     }
 
+    /// Stage everything in a worktree and commit it. Returns `None` when the
+    /// worktree has no changes relative to HEAD.
+    pub fn commit_worktree(worktree_path: &Path, message: &str) -> AppResult<Option<String>> {
+        Self::stage_all(worktree_path)?;
+
+        let repo = Repository::open(worktree_path)?;
+        let tree_id = repo.index()?.write_tree()?;
+        if let Ok(head) = repo.head() {
+            if head.peel_to_tree()?.id() == tree_id {
+                return Ok(None);
+            }
+        }
+
+        Self::commit(worktree_path, message).map(Some)
+    }
+
     /// Discard changes to a file (checkout from HEAD)
     pub fn discard_file(repo_path: &Path, file_path: &str) -> AppResult<()> {
         let repo = Repository::open(repo_path)?;
@@ -1134,9 +2130,10 @@ impl WorktreeManager {
         project_path: &Path,
         session_id: &str,
         target_branch: &str,
+        branch_prefix: &str,
     ) -> AppResult<MergeRebaseResult> {
         let repo = Repository::open(project_path)?;
-        let branch_name = format!("forkestra/session-{}", session_id);
+        let branch_name = Self::session_branch_name(&repo, session_id, branch_prefix);
 
         // Get the session branch
         let session_branch = repo.find_branch(&branch_name, BranchType::Local)?;
@@ -1471,3 +2468,332 @@ impl WorktreeManager {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git2::{RepositoryInitOptions, Signature};
+
+    /// Repository with one commit on `main`
+    fn init_repo(dir: &Path) -> Repository {
+        let repo =
+            Repository::init_opts(dir, RepositoryInitOptions::new().initial_head("main")).unwrap();
+        std::fs::write(dir.join("README.md"), "hello\n").unwrap();
+        commit_all(&repo, "Initial commit");
+        repo
+    }
+
+    fn commit_all(repo: &Repository, message: &str) {
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let parent = repo.head().ok().and_then(|h| h.peel_to_commit().ok());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(
+            Some("HEAD"),
+            &signature,
+            &signature,
+            message,
+            &tree,
+            &parents,
+        )
+        .unwrap();
+    }
+
+    /// Drop the directory under `~/.forkestra/worktrees` the tests created
+    fn remove_worktree_base(project_path: &Path) {
+        if let Ok(base) = WorktreeManager::get_worktree_base_path(project_path) {
+            let _ = std::fs::remove_dir_all(base);
+        }
+    }
+
+    #[test]
+    fn validate_branch_prefix_rejects_invalid_refs() {
+        assert!(WorktreeManager::validate_branch_prefix("team/ai-").is_ok());
+        assert!(WorktreeManager::validate_branch_prefix(DEFAULT_BRANCH_PREFIX).is_ok());
+        assert!(WorktreeManager::validate_branch_prefix("").is_err());
+        assert!(WorktreeManager::validate_branch_prefix("bad..prefix").is_err());
+        assert!(WorktreeManager::validate_branch_prefix("with space").is_err());
+    }
+
+    #[test]
+    fn create_and_remove_worktree_with_custom_prefix() {
+        let project = tempfile::tempdir().unwrap();
+        let repo = init_repo(project.path());
+
+        let (worktree_path, branch_name) = WorktreeManager::create_worktree(
+            project.path(),
+            "session-1",
+            Some("main"),
+            false,
+            "team/ai-",
+        )
+        .unwrap();
+        assert_eq!(branch_name, "team/ai-session-1");
+        assert!(worktree_path.join("README.md").is_file());
+        assert!(repo.find_branch(&branch_name, BranchType::Local).is_ok());
+
+        let leftover = WorktreeManager::remove_worktree(
+            project.path(),
+            "session-1",
+            &worktree_path,
+            "team/ai-",
+        )
+        .unwrap();
+        assert!(leftover.is_empty());
+        assert!(!worktree_path.exists());
+        assert!(repo.find_branch(&branch_name, BranchType::Local).is_err());
+
+        remove_worktree_base(project.path());
+    }
+
+    #[test]
+    fn session_branch_name_falls_back_to_default_prefix_only() {
+        let project = tempfile::tempdir().unwrap();
+        let repo = init_repo(project.path());
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("forkestra/session-old", &head, false).unwrap();
+        repo.branch("hotfix", &head, false).unwrap();
+
+        assert_eq!(
+            WorktreeManager::session_branch_name(&repo, "old", "team/ai-"),
+            "forkestra/session-old"
+        );
+        // A branch merely ending in the id is not the session's
+        assert_eq!(
+            WorktreeManager::session_branch_name(&repo, "fix", "team/ai-"),
+            "team/ai-fix"
+        );
+    }
+
+    #[test]
+    fn remove_all_worktrees_keeps_non_forkestra_worktrees() {
+        let project = tempfile::tempdir().unwrap();
+        let repo = init_repo(project.path());
+
+        // A worktree the user created themselves, on a branch ending in its name
+        let other = tempfile::tempdir().unwrap();
+        let user_path = other.path().join("fix");
+        let head = repo.head().unwrap().peel_to_commit().unwrap();
+        let user_branch = repo
+            .branch("hotfix", &head, false)
+            .unwrap()
+            .into_reference();
+        repo.worktree(
+            "fix",
+            &user_path,
+            Some(git2::WorktreeAddOptions::new().reference(Some(&user_branch))),
+        )
+        .unwrap();
+
+        let (session_path, _) = WorktreeManager::create_worktree(
+            project.path(),
+            "session-2",
+            Some("main"),
+            false,
+            "team/ai-",
+        )
+        .unwrap();
+
+        let removed = WorktreeManager::remove_all_worktrees(project.path(), "team/ai-").unwrap();
+        assert_eq!(removed, vec!["session-2".to_string()]);
+        assert!(!session_path.exists());
+        assert!(user_path.join("README.md").is_file());
+        assert!(repo.find_worktree("fix").is_ok());
+        assert!(repo.find_branch("hotfix", BranchType::Local).is_ok());
+
+        remove_worktree_base(project.path());
+    }
+
+    /// Project with a `team/ai-` session worktree holding one commit that adds
+    /// `feature.txt`
+    fn project_with_session(session_id: &str) -> (tempfile::TempDir, PathBuf) {
+        let project = tempfile::tempdir().unwrap();
+        init_repo(project.path());
+        let (worktree_path, _) = WorktreeManager::create_worktree(
+            project.path(),
+            session_id,
+            Some("main"),
+            false,
+            "team/ai-",
+        )
+        .unwrap();
+        std::fs::write(worktree_path.join("feature.txt"), "feature\n").unwrap();
+        commit_all(&Repository::open(&worktree_path).unwrap(), "Add feature");
+        (project, worktree_path)
+    }
+
+    #[test]
+    fn preview_merge_lists_changes_and_flags_conflicts() {
+        let (project, worktree_path) = project_with_session("session-4");
+        let path = project.path();
+
+        let files = WorktreeManager::preview_merge(path, "session-4", "main", "team/ai-").unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "feature.txt");
+        assert!(matches!(files[0].status, GitFileStatusKind::Added));
+        assert!(!files[0].is_conflicted);
+
+        // Both sides change the same line of README.md
+        std::fs::write(worktree_path.join("README.md"), "session\n").unwrap();
+        commit_all(&Repository::open(&worktree_path).unwrap(), "Session edit");
+        std::fs::write(path.join("README.md"), "main\n").unwrap();
+        commit_all(&Repository::open(path).unwrap(), "Main edit");
+
+        let files = WorktreeManager::preview_merge(path, "session-4", "main", "team/ai-").unwrap();
+        let readme = files.iter().find(|f| f.path == "README.md").unwrap();
+        assert!(readme.is_conflicted);
+        assert!(matches!(readme.status, GitFileStatusKind::Conflicted));
+        assert!(files
+            .iter()
+            .any(|f| f.path == "feature.txt" && !f.is_conflicted));
+        // Nothing was merged
+        assert_eq!(
+            std::fs::read_to_string(path.join("README.md")).unwrap(),
+            "main\n"
+        );
+
+        remove_worktree_base(path);
+    }
+
+    #[test]
+    fn graduate_branch_renames_out_of_the_session_namespace() {
+        let (project, worktree_path) = project_with_session("session-5");
+        let path = project.path();
+
+        // Rejected names leave the session branch and worktree untouched
+        for name in ["team/ai-other", "forkestra/other", "bad..name", "main"] {
+            assert!(
+                WorktreeManager::check_graduation(path, "session-5", name, "team/ai-").is_err()
+            );
+            assert!(WorktreeManager::graduate_branch(
+                path,
+                "session-5",
+                name,
+                &worktree_path,
+                "team/ai-"
+            )
+            .is_err());
+        }
+        let repo = Repository::open(path).unwrap();
+        assert!(repo
+            .find_branch("team/ai-session-5", BranchType::Local)
+            .is_ok());
+        assert!(worktree_path.join("feature.txt").is_file());
+        assert!(
+            WorktreeManager::check_graduation(path, "missing", "feature/x", "team/ai-").is_err()
+        );
+
+        let graduated = WorktreeManager::graduate_branch(
+            path,
+            "session-5",
+            " feature/x ",
+            &worktree_path,
+            "team/ai-",
+        )
+        .unwrap();
+        assert_eq!(graduated, "feature/x");
+        assert!(repo.find_branch("feature/x", BranchType::Local).is_ok());
+        assert!(repo
+            .find_branch("team/ai-session-5", BranchType::Local)
+            .is_err());
+        assert!(!worktree_path.exists());
+
+        remove_worktree_base(path);
+    }
+
+    #[test]
+    fn ahead_behind_counts_session_commits_against_main() {
+        let (project, _) = project_with_session("session-7");
+        let path = project.path();
+
+        assert_eq!(
+            WorktreeManager::ahead_behind(path, "session-7", "main", "team/ai-").unwrap(),
+            (1, 0)
+        );
+        std::fs::write(path.join("main.txt"), "main\n").unwrap();
+        commit_all(&Repository::open(path).unwrap(), "Main work");
+        assert_eq!(
+            WorktreeManager::ahead_behind(path, "session-7", "main", "team/ai-").unwrap(),
+            (1, 1)
+        );
+        assert!(WorktreeManager::ahead_behind(path, "missing", "main", "team/ai-").is_err());
+
+        remove_worktree_base(path);
+    }
+
+    #[test]
+    fn format_patch_exports_the_session_commits() {
+        let (project, _) = project_with_session("session-8");
+        let path = project.path();
+
+        let patch = WorktreeManager::format_patch(path, "session-8", "team/ai-").unwrap();
+        assert!(patch.starts_with("From "));
+        assert!(patch.contains("Subject: [PATCH] Add feature"));
+        assert!(patch.contains("+feature"));
+        assert!(!patch.contains("Initial commit"));
+        assert!(WorktreeManager::format_patch(path, "missing", "team/ai-").is_err());
+
+        remove_worktree_base(path);
+    }
+
+    #[test]
+    fn copy_agent_config_dir_skips_copying_into_itself() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(root.path().join(".claude/commands")).unwrap();
+        std::fs::write(root.path().join(".claude/commands/a.md"), "a").unwrap();
+
+        // Destination nested in the source: nothing is copied
+        let nested = root.path().join(".claude/commands");
+        WorktreeManager::copy_agent_config_dir(root.path(), &nested, ".claude");
+        assert!(!nested.join(".claude").exists());
+
+        let other = tempfile::tempdir().unwrap();
+        WorktreeManager::copy_agent_config_dir(root.path(), other.path(), ".claude");
+        assert!(other.path().join(".claude/commands/a.md").is_file());
+
+        // Missing source directories are ignored
+        WorktreeManager::copy_agent_config_dir(root.path(), other.path(), ".gemini");
+        assert!(!other.path().join(".gemini").exists());
+    }
+
+    #[test]
+    fn snapshot_is_never_written_inside_the_worktree() {
+        let (project, worktree_path) = project_with_session("session-6");
+        let path = project.path();
+
+        let inside = worktree_path.join("snapshot.tar.gz");
+        assert!(WorktreeManager::snapshot_worktree(path, "session-6", &inside, false).is_err());
+        assert!(!inside.exists());
+
+        let out = tempfile::tempdir().unwrap();
+        let destination = out.path().join("snapshot.tar.gz");
+        let count =
+            WorktreeManager::snapshot_worktree(path, "session-6", &destination, false).unwrap();
+        assert!(count >= 2);
+        assert!(destination.is_file());
+
+        remove_worktree_base(path);
+    }
+
+    #[test]
+    fn list_worktrees_reports_checked_out_branch() {
+        let (project, worktree_path) = project_with_session("session-3");
+
+        let worktrees = WorktreeManager::list_worktrees(project.path()).unwrap();
+        assert_eq!(worktrees.len(), 1);
+        assert_eq!(worktrees[0].name, "session-3");
+        assert_eq!(worktrees[0].branch.as_deref(), Some("team/ai-session-3"));
+        assert!(!worktrees[0].is_session);
+        assert_eq!(
+            Path::new(&worktrees[0].path).canonicalize().unwrap(),
+            worktree_path.canonicalize().unwrap()
+        );
+
+        remove_worktree_base(project.path());
+    }
+}
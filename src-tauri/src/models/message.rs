@@ -50,6 +50,7 @@ pub enum MessageContentType {
     ToolUse,
     ToolResult,
     Error,
+    Image,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -74,6 +75,10 @@ pub struct ChatMessage {
     pub parts: Option<Vec<MessagePart>>,
     pub timestamp: DateTime<Utc>,
     pub is_streaming: bool,
+    /// Provider-specific `_meta` of the message's tool calls, keyed by tool call id.
+    /// Only written on save; loaded separately through `get_message_meta`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Value>,
 }
 
 impl ChatMessage {
@@ -89,6 +94,7 @@ impl ChatMessage {
             parts: None,
             timestamp: Utc::now(),
             is_streaming: false,
+            meta: None,
         }
     }
 
@@ -104,6 +110,24 @@ impl ChatMessage {
             parts: None,
             timestamp: Utc::now(),
             is_streaming: false,
+            meta: None,
+        }
+    }
+
+    /// An agent-produced image, stored inline as an image part
+    pub fn assistant_image(session_id: &str, image: ImageContent) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id: session_id.to_string(),
+            role: MessageRole::Assistant,
+            content: String::new(),
+            content_type: MessageContentType::Image,
+            tool_use: None,
+            tool_calls: None,
+            parts: Some(vec![MessagePart::Image { content: image }]),
+            timestamp: Utc::now(),
+            is_streaming: false,
+            meta: None,
         }
     }
 
@@ -119,6 +143,7 @@ impl ChatMessage {
             parts: None,
             timestamp: Utc::now(),
             is_streaming: true,
+            meta: None,
         }
     }
 }
@@ -135,6 +160,9 @@ pub struct StreamChunk {
     pub tool_call: Option<ToolCallInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub image_content: Option<ImageContent>,
+    /// Why the turn failed, set on the final chunk of a failed prompt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -171,6 +199,42 @@ pub struct ToolCallInfo {
     pub raw_input: Option<serde_json::Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub raw_output: Option<serde_json::Value>,
+    /// The agent's `_meta` for this tool call. Kept in the backend, never sent
+    /// to the frontend.
+    #[serde(skip)]
+    pub meta: Option<serde_json::Value>,
+}
+
+/// Full, untruncated record of a tool call, merged across all of its updates
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolCallDetail {
+    pub session_id: String,
+    pub message_id: String,
+    pub tool_call_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_name: Option<String>,
+    pub status: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content: Option<Vec<ToolCallContentItem>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_input: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_output: Option<serde_json::Value>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A message the user bookmarked, with an optional note
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageBookmark {
+    pub id: String,
+    pub session_id: String,
+    pub message_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -232,6 +296,21 @@ pub struct InteractionPrompt {
     pub options: Option<Vec<PermissionOptionInfo>>,
 }
 
+/// Event emitted when a pending permission request was dropped without an answer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PermissionClearedEvent {
+    pub session_id: String,
+}
+
+/// Event emitted after a message was saved to the database. `message` is left
+/// out when it is too large for an event; fetch it by id instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageSavedEvent {
+    pub session_id: String,
+    pub message_id: String,
+    pub message: Option<ChatMessage>,
+}
+
 /// Event emitted when available slash commands are updated for a session
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AvailableCommandsEvent {
@@ -90,6 +90,36 @@ pub struct ProviderDefinition {
     /// Whether this is a built-in provider (cannot be deleted by user)
     #[serde(default)]
     pub builtin: bool,
+    /// How prompt content is laid out in `session/prompt` for this agent
+    #[serde(default)]
+    pub prompt_shape: PromptShape,
+    /// Arguments for the CLI's auth status subcommand, e.g. ["auth", "status"].
+    /// It must exit 0 when logged in and non-zero when logged out. None when the
+    /// CLI has no such subcommand.
+    #[serde(default)]
+    pub auth_status_args: Option<Vec<String>>,
+}
+
+/// Whether the user is logged into a provider's CLI
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthStatus {
+    LoggedIn,
+    LoggedOut,
+    /// The CLI is missing, has no status subcommand, or the check failed
+    Unknown,
+}
+
+/// Layout of the content blocks sent in `session/prompt`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptShape {
+    /// One ACP content block per prompt part
+    #[default]
+    Standard,
+    /// All text and resource links merged into a single leading text block, for
+    /// agents that only read the first text block of a prompt. Images follow as-is.
+    SingleText,
 }
 
 /// Returns the 7 built-in provider definitions with their launch configurations.
@@ -103,6 +133,8 @@ pub fn builtin_definitions() -> Vec<ProviderDefinition> {
             cli_command: Some("claude".to_string()),
             env: HashMap::new(),
             builtin: true,
+            prompt_shape: PromptShape::Standard,
+            auth_status_args: Some(vec!["auth".to_string(), "status".to_string()]),
         },
         ProviderDefinition {
             id: "codex".to_string(),
@@ -112,6 +144,8 @@ pub fn builtin_definitions() -> Vec<ProviderDefinition> {
             cli_command: None, // npx-based, always available
             env: HashMap::new(),
             builtin: true,
+            prompt_shape: PromptShape::Standard,
+            auth_status_args: None,
         },
         ProviderDefinition {
             id: "gemini".to_string(),
@@ -121,6 +155,8 @@ pub fn builtin_definitions() -> Vec<ProviderDefinition> {
             cli_command: Some("gemini".to_string()),
             env: HashMap::new(),
             builtin: true,
+            prompt_shape: PromptShape::Standard,
+            auth_status_args: None,
         },
         ProviderDefinition {
             id: "open_code".to_string(),
@@ -130,6 +166,8 @@ pub fn builtin_definitions() -> Vec<ProviderDefinition> {
             cli_command: Some("opencode".to_string()),
             env: HashMap::new(),
             builtin: true,
+            prompt_shape: PromptShape::Standard,
+            auth_status_args: None,
         },
         ProviderDefinition {
             id: "kimi".to_string(),
@@ -139,6 +177,8 @@ pub fn builtin_definitions() -> Vec<ProviderDefinition> {
             cli_command: Some("kimi".to_string()),
             env: HashMap::new(),
             builtin: true,
+            prompt_shape: PromptShape::Standard,
+            auth_status_args: None,
         },
         ProviderDefinition {
             id: "qoder".to_string(),
@@ -148,6 +188,8 @@ pub fn builtin_definitions() -> Vec<ProviderDefinition> {
             cli_command: Some("qodercli".to_string()),
             env: HashMap::new(),
             builtin: true,
+            prompt_shape: PromptShape::Standard,
+            auth_status_args: None,
         },
         ProviderDefinition {
             id: "qwen_code".to_string(),
@@ -157,6 +199,8 @@ pub fn builtin_definitions() -> Vec<ProviderDefinition> {
             cli_command: Some("qwen".to_string()),
             env: HashMap::new(),
             builtin: true,
+            prompt_shape: PromptShape::Standard,
+            auth_status_args: None,
         },
     ]
 }
@@ -201,6 +245,10 @@ pub struct ProviderSettings {
     pub custom_cli_path: Option<String>,
     #[serde(default)]
     pub env_vars: HashMap<String, String>,
+    /// Niceness the agent process runs at (-20 to 19, higher yields more CPU to
+    /// other programs). Only applied on Unix; `None` keeps the app's priority.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nice: Option<i32>,
 }
 
 fn default_true() -> bool {
@@ -213,6 +261,7 @@ impl Default for ProviderSettings {
             enabled: true,
             custom_cli_path: None,
             env_vars: HashMap::new(),
+            nice: None,
         }
     }
 }
@@ -27,6 +27,86 @@ pub struct GitFileStatus {
     pub old_path: Option<String>,
 }
 
+/// A file that merging a session branch would change in the target branch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileDiff {
+    pub path: String,
+    pub status: GitFileStatusKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    /// The merge could not be resolved automatically for this file
+    pub is_conflicted: bool,
+}
+
+/// One file of a session worktree compared with the session's fork point
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionFileDiff {
+    pub path: String,
+    /// `None` when the file is unchanged
+    pub status: Option<GitFileStatusKind>,
+    /// Unified diff, empty when the file is unchanged
+    pub patch: String,
+}
+
+/// Blame information for one line of a file in a session worktree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlameLine {
+    /// 1-based line number in the working copy of the file
+    pub line_number: usize,
+    /// Commit that last changed the line, `None` when the change is uncommitted
+    pub sha: Option<String>,
+    pub author: Option<String>,
+    pub author_email: Option<String>,
+    /// The line was written on the session branch (after it forked from the
+    /// base branch) or is an uncommitted change in the worktree
+    pub is_session_change: bool,
+}
+
+/// Per-session branch state relative to the project's default branch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionGitOverview {
+    pub session_id: String,
+    pub branch_name: String,
+    pub base_branch: String,
+    /// Commits on the session branch that are not on the base branch
+    pub ahead: usize,
+    /// Commits on the base branch that are not on the session branch
+    pub behind: usize,
+    pub is_dirty: bool,
+    /// True when the session has no worktree and works in the project directory
+    pub is_local: bool,
+}
+
+/// What `verify_worktree` found wrong with a session worktree and fixed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorktreeRepairReport {
+    pub session_id: String,
+    /// Repairs made, in the order they were done; empty when nothing was wrong
+    pub actions: Vec<String>,
+    /// git considers the worktree valid after the repairs
+    pub is_valid: bool,
+}
+
+/// A linked git worktree of a project, including ones created outside Forkestra
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorktreeInfo {
+    pub name: String,
+    pub path: String,
+    /// Checked-out branch, `None` for a detached HEAD or an unreadable worktree
+    pub branch: Option<String>,
+    /// The worktree belongs to a Forkestra session
+    pub is_session: bool,
+}
+
+/// Pre-creation info about a candidate project directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectInfo {
+    pub is_git_repo: bool,
+    pub default_branch: Option<String>,
+    pub branches: Vec<String>,
+    pub is_dirty: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GitScmStatus {
     pub staged: Vec<GitFileStatus>,
@@ -73,7 +153,7 @@ pub struct ModeInfo {
     pub description: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "snake_case")]
 pub enum SessionStatus {
     Creating,
@@ -99,6 +179,9 @@ pub struct Session {
     pub is_local: bool,
     #[serde(default)]
     pub acp_session_id: Option<String>,
+    /// Exact cwd the ACP session was created with, sent again in session/load on resume
+    #[serde(default)]
+    pub acp_cwd: Option<String>,
     #[serde(default)]
     pub model: Option<String>,
     #[serde(default)]
@@ -115,6 +198,72 @@ pub struct Session {
     pub config_options: Vec<SessionConfigOption>,
     #[serde(default)]
     pub error: Option<SessionError>,
+    #[serde(default)]
+    pub pinned: bool,
+    /// Project conventions sent ahead of the first prompt of each ACP session
+    #[serde(default)]
+    pub system_prompt: Option<String>,
+    /// Hex color (`#rrggbb`) the user tagged the session with
+    #[serde(default)]
+    pub ui_color: Option<String>,
+}
+
+/// File format for exported session transcripts
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Json,
+}
+
+/// Lightweight view of a session with a live agent process
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveSessionInfo {
+    pub session_id: String,
+    pub name: String,
+    pub project_path: String,
+    pub provider: ProviderType,
+    pub model: Option<String>,
+    pub current_mode: Option<String>,
+    pub last_activity_at: DateTime<Utc>,
+}
+
+/// OS process of a session's agent, with usage where the platform exposes it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// Resident memory in bytes
+    pub rss_bytes: Option<u64>,
+    /// CPU time (user + system) used since the process started, in milliseconds
+    pub cpu_time_ms: Option<u64>,
+}
+
+/// A session found in an agent's own storage
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentSessionInfo {
+    pub provider: ProviderType,
+    /// Id the agent knows the session by, usable with session/load
+    pub acp_session_id: String,
+    /// Directory the session was started in, when the agent records it
+    pub cwd: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// Value of a command that succeeded with problems the user should know about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandResult<T> {
+    pub value: T,
+    /// Non-fatal problems, e.g. files that could not be deleted
+    pub warnings: Vec<String>,
+}
+
+/// Aggregated view of all sessions that share a project path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProjectSummary {
+    pub path: String,
+    pub name: String,
+    pub session_count: usize,
+    pub last_activity: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -123,13 +272,17 @@ pub struct CreateSessionRequest {
     pub provider: ProviderType,
     pub project_path: String,
     pub base_branch: Option<String>,
+    /// Work in the project directory instead of a worktree. Falls back to the
+    /// `defaultWorkMode` setting when omitted.
     #[serde(default)]
-    pub use_local: bool,
+    pub use_local: Option<bool>,
     #[serde(default = "default_fetch_first")]
     pub fetch_first: bool,
     /// MCP server IDs to exclude from this session
     #[serde(default)]
     pub excluded_mcp_ids: Vec<String>,
+    #[serde(default)]
+    pub system_prompt: Option<String>,
 }
 
 fn default_fetch_first() -> bool {
@@ -140,6 +293,211 @@ fn default_fetch_first() -> bool {
 pub struct SessionError {
     pub code: String,
     pub message: String,
+    /// What went wrong, when the error is a JSON-RPC error from the agent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ProviderErrorKind>,
+}
+
+impl SessionError {
+    pub fn new(code: impl Into<String>, message: impl Into<String>) -> Self {
+        let code = code.into();
+        let message = message.into();
+        let kind = ProviderErrorKind::classify(&code, &message);
+        Self {
+            code,
+            message,
+            kind,
+        }
+    }
+}
+
+/// Meaning of a JSON-RPC error returned by an agent, so the UI can react to it
+/// (e.g. ask the user to log in, or back off when rate limited)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderErrorKind {
+    AuthRequired,
+    RateLimited,
+    InvalidParams,
+    MethodNotFound,
+    ResourceNotFound,
+    InternalError,
+    Other,
+}
+
+impl ProviderErrorKind {
+    /// Classify an error whose code is a JSON-RPC code, optionally followed by
+    /// a label (`-32000` or `-32000: Authentication required`). Other codes are
+    /// not agent errors and give `None`.
+    pub fn classify(code: &str, message: &str) -> Option<Self> {
+        let numeric = code.split(':').next()?.trim().parse::<i64>().ok()?;
+
+        // Rate limits have no standard code; agents report them as generic errors
+        let lower = message.to_lowercase();
+        if numeric == 429
+            || lower.contains("rate limit")
+            || lower.contains("rate_limit")
+            || lower.contains("too many requests")
+        {
+            return Some(Self::RateLimited);
+        }
+
+        Some(match numeric {
+            -32000 => Self::AuthRequired,
+            -32002 => Self::ResourceNotFound,
+            -32601 => Self::MethodNotFound,
+            -32602 => Self::InvalidParams,
+            -32603 => Self::InternalError,
+            _ => Self::Other,
+        })
+    }
+}
+
+/// Phases a session goes through while being created
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionProgressPhase {
+    CreatingWorktree,
+    SpawningProcess,
+    Initializing,
+    CreatingAcpSession,
+    Ready,
+}
+
+/// Event emitted when a creating session enters a new phase
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionProgressEvent {
+    pub session_id: String,
+    pub phase: SessionProgressPhase,
+    /// Milliseconds since session creation started
+    pub elapsed_ms: u64,
+    /// Milliseconds spent in the previous phase
+    pub previous_phase_ms: u64,
+}
+
+/// Start and end of one agent turn, from prompt send to completion
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnTiming {
+    pub started_at: DateTime<Utc>,
+    pub ended_at: DateTime<Utc>,
+    pub duration_ms: u64,
+}
+
+/// How long a session has existed and how long the agent spent working
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionTiming {
+    pub session_id: String,
+    /// Milliseconds since the session was created
+    pub elapsed_ms: u64,
+    /// Sum of all turn durations
+    pub total_active_ms: u64,
+    /// Turns in the order they started
+    pub turns: Vec<TurnTiming>,
+}
+
+/// Row counts and turn latency read from the local database
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DatabaseMetrics {
+    pub session_rows: u64,
+    pub message_rows: u64,
+    pub tool_call_detail_rows: u64,
+    pub turn_timing_rows: u64,
+    /// Mean duration of all recorded turns, from prompt send to completion
+    pub average_turn_ms: Option<u64>,
+}
+
+/// Local-only snapshot of app load, for diagnosing slowness. Never reported anywhere.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PerformanceMetrics {
+    pub total_sessions: usize,
+    /// Sessions with a live agent process
+    pub active_sessions: usize,
+    /// Sessions still being created
+    pub pending_creations: usize,
+    /// Prompts sent that have not completed yet
+    pub running_turns: usize,
+    /// Tool calls currently running across all sessions
+    pub running_tool_calls: usize,
+    pub database: DatabaseMetrics,
+    /// Threads in this process, when the platform exposes it
+    pub thread_count: Option<usize>,
+}
+
+/// What an activity log entry records
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Prompt,
+    ToolCall,
+    TurnComplete,
+    Error,
+    Stderr,
+}
+
+/// One entry of a session's activity log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityLogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub kind: ActivityKind,
+    pub summary: String,
+}
+
+/// What an activity feed event records
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedEventKind {
+    SessionCreated,
+    Message,
+    StatusChanged,
+    Merged,
+}
+
+/// One event of the activity feed shared by all sessions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedEvent {
+    pub id: i64,
+    pub session_id: String,
+    pub kind: FeedEventKind,
+    pub summary: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A request to the agent that has not been answered yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingRequest {
+    /// Message id for prompts, the tool call id for permission requests
+    pub id: String,
+    /// ACP method, e.g. `session/prompt`
+    pub method: String,
+    /// Milliseconds since the request was sent or received
+    pub elapsed_ms: u64,
+}
+
+/// Event emitted after a completed turn was committed to the session branch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TurnCommittedEvent {
+    pub session_id: String,
+    pub turn: usize,
+    pub sha: String,
+}
+
+/// Event emitted as each previously active session finishes auto-resuming
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoResumeProgressEvent {
+    pub session_id: String,
+    /// Sessions finished so far, including this one
+    pub completed: usize,
+    pub total: usize,
+    /// Why the resume failed, or None if the session is active again
+    pub error: Option<String>,
+}
+
+/// Event emitted when the agent switches the session's model on its own
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelChangedEvent {
+    pub session_id: String,
+    pub model_id: String,
+    pub previous_model_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -149,3 +507,58 @@ pub struct SessionStatusEvent {
     pub session: Option<Session>,
     pub error: Option<SessionError>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_maps_json_rpc_codes() {
+        let classify = ProviderErrorKind::classify;
+        assert_eq!(
+            classify("-32000", "Authentication required"),
+            Some(ProviderErrorKind::AuthRequired)
+        );
+        assert_eq!(
+            classify("-32602: Invalid params", ""),
+            Some(ProviderErrorKind::InvalidParams)
+        );
+        assert_eq!(
+            classify("-32601", ""),
+            Some(ProviderErrorKind::MethodNotFound)
+        );
+        assert_eq!(
+            classify("-32002", ""),
+            Some(ProviderErrorKind::ResourceNotFound)
+        );
+        assert_eq!(
+            classify("-32603", ""),
+            Some(ProviderErrorKind::InternalError)
+        );
+        assert_eq!(classify("-1", ""), Some(ProviderErrorKind::Other));
+    }
+
+    #[test]
+    fn classify_detects_rate_limits_from_message() {
+        let classify = ProviderErrorKind::classify;
+        assert_eq!(classify("429", ""), Some(ProviderErrorKind::RateLimited));
+        assert_eq!(
+            classify("-32603", "Rate limit exceeded"),
+            Some(ProviderErrorKind::RateLimited)
+        );
+        assert_eq!(
+            classify("-32000", "429 Too Many Requests"),
+            Some(ProviderErrorKind::RateLimited)
+        );
+    }
+
+    #[test]
+    fn classify_ignores_non_json_rpc_codes() {
+        assert_eq!(
+            ProviderErrorKind::classify("SPAWN_FAILED", "rate limit"),
+            None
+        );
+        assert_eq!(ProviderErrorKind::classify("", ""), None);
+        assert_eq!(SessionError::new("WORKTREE", "boom").kind, None);
+    }
+}
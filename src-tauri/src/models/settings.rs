@@ -14,14 +14,44 @@ pub struct GeneralSettings {
     pub default_work_mode: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub post_merge_action: Option<String>,
+    /// Commit the worktree after every completed agent turn
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_commit_per_turn: Option<bool>,
+    /// Global shortcut that opens the quick-create dialog (empty string disables it)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quick_new_session_shortcut: Option<String>,
+    /// Glob patterns hidden from the file tree on top of `.gitignore`. Patterns
+    /// without a `/` match entry names, others match project-relative paths.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_tree_exclude: Option<Vec<String>>,
+    /// Resume the sessions that were active when the app last closed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_resume_on_startup: Option<bool>,
+    /// Prepended to the session id to name each session's branch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch_prefix: Option<String>,
 }
 
+pub const DEFAULT_QUICK_NEW_SESSION_SHORTCUT: &str = "CmdOrCtrl+Shift+N";
+
+pub const DEFAULT_BRANCH_PREFIX: &str = "forkestra/session-";
+
+pub const DEFAULT_FILE_TREE_EXCLUDE: [&str; 5] =
+    ["target", "node_modules", "dist", "build", "__pycache__"];
+
 impl Default for GeneralSettings {
     fn default() -> Self {
         Self {
             default_project_path: None,
             default_work_mode: Some("worktree".to_string()),
             post_merge_action: Some("ask".to_string()),
+            auto_commit_per_turn: Some(false),
+            quick_new_session_shortcut: Some(DEFAULT_QUICK_NEW_SESSION_SHORTCUT.to_string()),
+            file_tree_exclude: Some(
+                DEFAULT_FILE_TREE_EXCLUDE.iter().map(|p| p.to_string()).collect(),
+            ),
+            auto_resume_on_startup: Some(false),
+            branch_prefix: Some(DEFAULT_BRANCH_PREFIX.to_string()),
         }
     }
 }
@@ -62,6 +92,80 @@ impl Default for NotificationSettings {
     }
 }
 
+/// Backend-only tuning knobs that are not exposed in the regular settings UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdvancedSettings {
+    /// Maximum bytes of a single agent line/value written to the logs (0 = unlimited)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_log_line_length: Option<usize>,
+    /// Maximum number of worktrees per project (None or 0 = unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_worktrees_per_project: Option<usize>,
+    /// What to do when the limit is reached: "refuse" or "auto_evict"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub worktree_limit_policy: Option<String>,
+    /// Buffered stream chunks per session. When the buffer is full the ACP
+    /// connection waits for the forwarder instead of dropping chunks, so a small
+    /// value slows a stalled session down and a large one costs memory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_channel_capacity: Option<usize>,
+    /// Largest message content stored in the database, in bytes (0 = unlimited)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_message_content_bytes: Option<usize>,
+    /// What to do with larger messages: "truncate" (store the full content in a
+    /// side file) or "reject"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oversized_message_policy: Option<String>,
+    /// How long to wait for the agent to answer each ACP request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acp_timeouts: Option<AcpTimeoutSettings>,
+    /// Seconds between background WAL checkpoints of the session database
+    /// (0 = never). Read at startup.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wal_checkpoint_interval_secs: Option<u64>,
+}
+
+/// Per-method ACP request timeouts in seconds. Unset methods use the built-in
+/// defaults; prompts are never timed out since a turn can run for a long time.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AcpTimeoutSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub initialize_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_session_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub load_session_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set_model_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set_mode_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub set_config_option_secs: Option<u64>,
+}
+
+pub const DEFAULT_STREAM_CHANNEL_CAPACITY: usize = 100;
+
+pub const DEFAULT_MAX_MESSAGE_CONTENT_BYTES: usize = 1024 * 1024;
+
+pub const DEFAULT_WAL_CHECKPOINT_INTERVAL_SECS: u64 = 300;
+
+impl Default for AdvancedSettings {
+    fn default() -> Self {
+        Self {
+            max_log_line_length: Some(crate::utils::DEFAULT_MAX_LOG_LINE_LENGTH),
+            max_worktrees_per_project: None,
+            worktree_limit_policy: Some("refuse".to_string()),
+            stream_channel_capacity: Some(DEFAULT_STREAM_CHANNEL_CAPACITY),
+            max_message_content_bytes: Some(DEFAULT_MAX_MESSAGE_CONTENT_BYTES),
+            oversized_message_policy: Some("truncate".to_string()),
+            acp_timeouts: None,
+            wal_checkpoint_interval_secs: Some(DEFAULT_WAL_CHECKPOINT_INTERVAL_SECS),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppSettings {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -79,6 +183,36 @@ pub struct AppSettings {
     /// User-defined custom ACP providers
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub custom_providers: Vec<ProviderDefinition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub advanced: Option<AdvancedSettings>,
+}
+
+/// Per-project overrides read from `<project>/.forkestra/config.json`, meant to
+/// be checked into the repository. Secrets such as provider env vars and CLI
+/// paths are deliberately not part of it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProjectSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_base_branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub general: Option<GeneralSettings>,
+    /// Provider id -> whether the provider is enabled for this project
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub enabled_providers: HashMap<String, bool>,
+}
+
+/// Global settings with a project's overrides applied
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveSettings {
+    pub settings: AppSettings,
+    pub default_provider: Option<String>,
+    pub default_base_branch: Option<String>,
+    /// Project config file that was applied, if one was found
+    pub project_config_path: Option<String>,
 }
 
 impl Default for AppSettings {
@@ -96,6 +230,7 @@ impl Default for AppSettings {
             skills: None,
             notification: None,
             custom_providers: Vec::new(),
+            advanced: None,
         }
     }
 }
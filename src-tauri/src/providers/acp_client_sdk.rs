@@ -1,5 +1,8 @@
 use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
 use agent_client_protocol::{
     self as acp, Agent, CancelNotification, ContentBlock, InitializeRequest, InitializeResponse,
@@ -11,18 +14,89 @@ use agent_client_protocol::{
 };
 use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio_util::sync::CancellationToken;
 
 use crate::managers::SessionManager;
+use crate::utils::truncate_for_log;
+use crate::providers::models_catalog;
 use crate::models::{
-    AvailableCommand, AvailableCommandInput, AvailableCommandsEvent, ImageContent,
-    InteractionPrompt, ModeInfo, ModelInfo, PermissionOptionInfo, PlanEntry, PlanEntryPriority,
-    PlanEntryStatus, PlanUpdateEvent, StreamChunk, StreamChunkType, ToolCallInfo,
+    AcpTimeoutSettings, ActivityKind, AvailableCommand, AvailableCommandInput,
+    AvailableCommandsEvent, ImageContent, InteractionPrompt, ModeInfo, ModelInfo,
+    PermissionOptionInfo, PlanEntry, PlanEntryPriority, PendingRequest, PlanEntryStatus,
+    PlanUpdateEvent, PromptContent, PromptShape, SessionProgressPhase, StreamChunk, StreamChunkType,
+    ToolCallInfo, ToolCallLocation,
 };
 
+/// Error returned when a handshake is cancelled through its token
+pub const HANDSHAKE_CANCELLED: &str = "Handshake cancelled";
+
+/// Extension method used to ask the agent to interrupt a single tool call
+const CANCEL_TOOL_CALL_METHOD: &str = "forkestra/cancel_tool_call";
+
+/// How long to wait for the agent to answer each ACP request.
+/// `initialize` is short so a dead process fails fast between retries, while
+/// `session/load` replays the whole history and can be slow on big sessions.
+#[derive(Debug, Clone, Copy)]
+pub struct AcpTimeouts {
+    pub initialize: Duration,
+    pub new_session: Duration,
+    pub load_session: Duration,
+    pub set_model: Duration,
+    pub set_mode: Duration,
+    pub set_config_option: Duration,
+}
+
+impl Default for AcpTimeouts {
+    fn default() -> Self {
+        Self {
+            initialize: Duration::from_secs(5),
+            new_session: Duration::from_secs(60),
+            load_session: Duration::from_secs(180),
+            set_model: Duration::from_secs(30),
+            set_mode: Duration::from_secs(30),
+            set_config_option: Duration::from_secs(30),
+        }
+    }
+}
+
+impl AcpTimeouts {
+    /// Defaults overridden by the non-zero values in the user's settings
+    pub fn from_settings(settings: Option<&AcpTimeoutSettings>) -> Self {
+        let defaults = Self::default();
+        let Some(s) = settings else {
+            return defaults;
+        };
+        let pick = |secs: Option<u64>, default: Duration| {
+            secs.filter(|&s| s > 0).map(Duration::from_secs).unwrap_or(default)
+        };
+        Self {
+            initialize: pick(s.initialize_secs, defaults.initialize),
+            new_session: pick(s.new_session_secs, defaults.new_session),
+            load_session: pick(s.load_session_secs, defaults.load_session),
+            set_model: pick(s.set_model_secs, defaults.set_model),
+            set_mode: pick(s.set_mode_secs, defaults.set_mode),
+            set_config_option: pick(s.set_config_option_secs, defaults.set_config_option),
+        }
+    }
+}
+
+/// Await an ACP request, giving up after `limit`
+async fn with_timeout<F: Future>(
+    method: &str,
+    limit: Duration,
+    request: F,
+) -> Result<F::Output, String> {
+    tokio::time::timeout(limit, request)
+        .await
+        .map_err(|_| format!("{} timed out after {}s", method, limit.as_secs()))
+}
+
 /// Commands that can be sent to the ACP connection running on a LocalSet.
 pub enum AcpCommand {
     Prompt {
         session_id: String,
+        /// Id that all stream chunks of this turn will carry
+        message_id: String,
         content: Vec<ContentBlock>,
         reply: oneshot::Sender<Result<(), String>>,
     },
@@ -30,6 +104,13 @@ pub enum AcpCommand {
         session_id: String,
         reply: oneshot::Sender<Result<(), String>>,
     },
+    /// ACP has no per-tool-call cancellation, so this is sent as an extension
+    /// notification that agents may honor or ignore.
+    CancelToolCall {
+        session_id: String,
+        tool_call_id: String,
+        reply: oneshot::Sender<Result<(), String>>,
+    },
     SetModel {
         session_id: String,
         model_id: String,
@@ -40,15 +121,31 @@ pub enum AcpCommand {
         mode_id: String,
         reply: oneshot::Sender<Result<(), String>>,
     },
+    /// Replies with the session's config options after the change
     SetConfigOption {
         config_id: String,
         value: String,
-        reply: oneshot::Sender<Result<(), String>>,
+        reply: oneshot::Sender<Result<Vec<acp::SessionConfigOption>, String>>,
     },
     PermissionResponse {
         option_id: String,
         reply: oneshot::Sender<Result<(), String>>,
     },
+    /// Answer a pending permission request as cancelled (a deny). Replies with
+    /// whether a request was pending.
+    ClearPermission {
+        reply: oneshot::Sender<bool>,
+    },
+    /// List prompts in flight and the pending permission request, if any
+    PendingRequests {
+        reply: oneshot::Sender<Vec<PendingRequest>>,
+    },
+    /// Abort every in-flight prompt and deny the pending permission request.
+    /// Callers waiting on an aborted prompt get an error. Replies with the
+    /// number of requests cleared.
+    ClearPendingRequests {
+        reply: oneshot::Sender<usize>,
+    },
     Shutdown,
 }
 
@@ -75,9 +172,19 @@ struct ClientContext {
 
 /// Info about a pending permission request.
 struct PendingPermissionInfo {
-    _tool_call_update: acp::ToolCallUpdate,
+    tool_call_update: acp::ToolCallUpdate,
     _options: Vec<acp::PermissionOption>,
     reply: oneshot::Sender<RequestPermissionResponse>,
+    received_at: std::time::Instant,
+}
+
+/// A `session/prompt` request the agent has not answered yet
+struct InFlightPrompt {
+    acp_session_id: String,
+    started_at: std::time::Instant,
+    /// Taken by whoever finishes the prompt: the prompt task or a clear
+    reply: Option<oneshot::Sender<Result<(), String>>>,
+    handle: Option<tokio::task::JoinHandle<()>>,
 }
 
 /// Our Client trait implementation.
@@ -141,9 +248,10 @@ impl acp::Client for ForkClient {
         let (reply_tx, reply_rx) = oneshot::channel();
 
         let perm_info = PendingPermissionInfo {
-            _tool_call_update: args.tool_call,
+            tool_call_update: args.tool_call,
             _options: args.options,
             reply: reply_tx,
+            received_at: std::time::Instant::now(),
         };
 
         self.ctx
@@ -277,12 +385,19 @@ async fn handle_session_update(
                 content_items
             };
 
+            // Edits with no content at all may still carry the diff in their input
+            let content_items = content_items.or_else(|| {
+                tool_call
+                    .raw_input
+                    .as_ref()
+                    .and_then(diff_from_json)
+                    .map(|diff| vec![diff])
+            });
+
             // Extract kind from metadata (if available in this ACP version)
             let kind: Option<String> = None; // TODO: Extract when ACP SDK exposes kind field
 
-            // Extract locations (if available in this ACP version)
-            let locations: Option<Vec<crate::models::ToolCallLocation>> = None; // TODO: Extract when ACP SDK exposes locations field
-
+            let locations = extract_tool_call_locations(&tool_call.locations);
 
             let _ = stream_tx
                 .send(StreamChunk {
@@ -301,8 +416,10 @@ async fn handle_session_update(
                         locations,
                         raw_input: tool_call.raw_input.clone(),
                         raw_output: tool_call.raw_output.clone(),
+                        meta: tool_call.meta.clone().map(serde_json::Value::Object),
                     }),
                     image_content: None,
+                    error: None,
                 })
                 .await;
         }
@@ -324,9 +441,11 @@ async fn handle_session_update(
             // Extract kind from update (if available in this ACP version)
             let kind: Option<String> = None; // TODO: Extract when ACP SDK exposes kind field
 
-            // Extract locations from update (if available in this ACP version)
-            let locations: Option<Vec<crate::models::ToolCallLocation>> = None; // TODO: Extract when ACP SDK exposes locations field
-
+            let locations = tool_call_update
+                .fields
+                .locations
+                .as_ref()
+                .and_then(|l| extract_tool_call_locations(l));
 
             let _ = stream_tx
                 .send(StreamChunk {
@@ -345,8 +464,10 @@ async fn handle_session_update(
                         locations,
                         raw_input: tool_call_update.fields.raw_input.clone(),
                         raw_output: tool_call_update.fields.raw_output.clone(),
+                        meta: tool_call_update.meta.clone().map(serde_json::Value::Object),
                     }),
                     image_content: None,
+                    error: None,
                 })
                 .await;
         }
@@ -488,6 +609,14 @@ async fn handle_session_update(
                 eprintln!("[ACP] Failed to emit config-options-update event: {}", e);
             }
 
+            // The agent may have switched models on its own; keep the session in sync
+            let (_, current_model_id) = extract_models(None, Some(&config_update.config_options));
+            if let Some(model_id) = current_model_id {
+                if let Some(manager) = app_handle.try_state::<SessionManager>() {
+                    manager.update_session_model(session_id, &model_id).await;
+                }
+            }
+
             // TODO: 可选 - 更新 SessionManager 中的 session.config_options 并持久化到数据库
             // 这需要在 SessionManager 中添加一个方法 update_session_config_options
         }
@@ -515,6 +644,7 @@ async fn handle_content_chunk(
                     chunk_type: Some(chunk_type),
                     tool_call: None,
                     image_content: None,
+                    error: None,
                 })
                 .await;
         }
@@ -528,6 +658,7 @@ async fn handle_content_chunk(
                     chunk_type: Some(StreamChunkType::Image),
                     tool_call: None,
                     image_content: Some(ImageContent {
+                    error: None,
                         data: img.data.clone(),
                         mime_type: img.mime_type.clone(),
                         uri: img.uri.clone(),
@@ -539,6 +670,24 @@ async fn handle_content_chunk(
     }
 }
 
+/// Files (and optional lines) a tool call touched; `None` when there are none
+fn extract_tool_call_locations(
+    locations: &[acp::ToolCallLocation],
+) -> Option<Vec<ToolCallLocation>> {
+    if locations.is_empty() {
+        return None;
+    }
+    Some(
+        locations
+            .iter()
+            .map(|l| ToolCallLocation {
+                path: l.path.to_string_lossy().to_string(),
+                line: l.line,
+            })
+            .collect(),
+    )
+}
+
 fn extract_tool_call_content(
     content: &[acp::ToolCallContent],
 ) -> Option<Vec<crate::models::ToolCallContentItem>> {
@@ -546,9 +695,20 @@ fn extract_tool_call_content(
         .iter()
         .filter_map(|c| match c {
             acp::ToolCallContent::Content(content_wrapper) => {
-                Some(crate::models::ToolCallContentItem::Content {
-                    content: convert_acp_content_block(&content_wrapper.content),
-                })
+                // Some agents send an edit as a JSON diff object in a text block
+                let diff = match &content_wrapper.content {
+                    ContentBlock::Text(t) if t.text.trim_start().starts_with('{') => {
+                        serde_json::from_str::<serde_json::Value>(&t.text)
+                            .ok()
+                            .and_then(|v| diff_from_json(&v))
+                    }
+                    _ => None,
+                };
+                Some(
+                    diff.unwrap_or_else(|| crate::models::ToolCallContentItem::Content {
+                        content: convert_acp_content_block(&content_wrapper.content),
+                    }),
+                )
             }
             acp::ToolCallContent::Diff(diff) => Some(crate::models::ToolCallContentItem::Diff {
                 path: diff.path.to_string_lossy().to_string(),
@@ -571,6 +731,21 @@ fn extract_tool_call_content(
     }
 }
 
+/// Read a diff given as a JSON object with a path and old/new text, which some
+/// agents use for edits instead of an ACP diff block
+fn diff_from_json(value: &serde_json::Value) -> Option<crate::models::ToolCallContentItem> {
+    let field = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|k| value.get(*k)?.as_str())
+            .map(str::to_string)
+    };
+    Some(crate::models::ToolCallContentItem::Diff {
+        path: field(&["path", "file_path", "filePath"])?,
+        old_text: field(&["oldText", "old_text", "old_string"]),
+        new_text: field(&["newText", "new_text", "new_string"])?,
+    })
+}
+
 fn convert_acp_content_block(block: &ContentBlock) -> crate::models::ContentBlock {
     match block {
         ContentBlock::Text(t) => crate::models::ContentBlock::Text {
@@ -712,6 +887,7 @@ fn expand_tilde(path: &str) -> String {
 // ========================
 
 /// Spawn an ACP connection on a dedicated LocalSet thread.
+/// The returned handle should be joined once the connection is shut down.
 pub fn spawn_acp_connection(
     stdin: tokio::process::ChildStdin,
     stdout: tokio::process::ChildStdout,
@@ -721,42 +897,75 @@ pub fn spawn_acp_connection(
     app_handle: AppHandle,
     current_message_id: Arc<Mutex<String>>,
     mcp_servers: Vec<agent_client_protocol::McpServer>,
+    timeouts: AcpTimeouts,
+    cancel: CancellationToken,
 ) -> (
     mpsc::Sender<AcpCommand>,
     oneshot::Receiver<Result<AcpHandshakeResult, String>>,
+    std::thread::JoinHandle<()>,
 ) {
     let (cmd_tx, cmd_rx) = mpsc::channel::<AcpCommand>(32);
     let (handshake_tx, handshake_rx) = oneshot::channel();
     let (perm_tx, perm_rx) = mpsc::channel::<PendingPermissionInfo>(4);
 
+    let thread = spawn_connection_thread(move || {
+        run_acp_connection(
+            stdin,
+            stdout,
+            session_id,
+            cwd,
+            stream_tx,
+            app_handle,
+            current_message_id,
+            cmd_rx,
+            handshake_tx,
+            perm_tx,
+            perm_rx,
+            mcp_servers,
+            timeouts,
+            cancel,
+        )
+    });
+
+    (cmd_tx, handshake_rx, thread)
+}
+
+/// ACP connection threads that have not exited yet
+static LIVE_CONNECTION_THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of ACP connection threads still running. Each session's agent has
+/// one until its adapter is terminated.
+pub fn live_connection_threads() -> usize {
+    LIVE_CONNECTION_THREADS.load(Ordering::SeqCst)
+}
+
+/// Run an ACP connection on a dedicated thread with its own current-thread
+/// runtime and LocalSet, as the SDK's futures are not `Send`
+pub(crate) fn spawn_connection_thread<F, Fut>(connection: F) -> std::thread::JoinHandle<()>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + 'static,
+{
+    /// Counts the thread as gone however it exits
+    struct Live;
+    impl Drop for Live {
+        fn drop(&mut self) {
+            LIVE_CONNECTION_THREADS.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+
+    LIVE_CONNECTION_THREADS.fetch_add(1, Ordering::SeqCst);
+    let live = Live;
     std::thread::spawn(move || {
+        let _live = live;
         let rt = tokio::runtime::Builder::new_current_thread()
             .enable_all()
             .build()
             .expect("failed to create tokio runtime for ACP connection");
 
         let local = tokio::task::LocalSet::new();
-
-        local.block_on(&rt, async move {
-            run_acp_connection(
-                stdin,
-                stdout,
-                session_id,
-                cwd,
-                stream_tx,
-                app_handle,
-                current_message_id,
-                cmd_rx,
-                handshake_tx,
-                perm_tx,
-                perm_rx,
-                mcp_servers,
-            )
-            .await;
-        });
-    });
-
-    (cmd_tx, handshake_rx)
+        local.block_on(&rt, connection());
+    })
 }
 
 /// Spawn an ACP connection for resuming a session.
@@ -770,43 +979,38 @@ pub fn spawn_acp_resume_connection(
     app_handle: AppHandle,
     current_message_id: Arc<Mutex<String>>,
     mcp_servers: Vec<agent_client_protocol::McpServer>,
+    timeouts: AcpTimeouts,
+    cancel: CancellationToken,
 ) -> (
     mpsc::Sender<AcpCommand>,
     oneshot::Receiver<Result<AcpHandshakeResult, String>>,
+    std::thread::JoinHandle<()>,
 ) {
     let (cmd_tx, cmd_rx) = mpsc::channel::<AcpCommand>(32);
     let (handshake_tx, handshake_rx) = oneshot::channel();
     let (perm_tx, perm_rx) = mpsc::channel::<PendingPermissionInfo>(4);
 
-    std::thread::spawn(move || {
-        let rt = tokio::runtime::Builder::new_current_thread()
-            .enable_all()
-            .build()
-            .expect("failed to create tokio runtime for ACP connection");
-
-        let local = tokio::task::LocalSet::new();
-
-        local.block_on(&rt, async move {
-            run_acp_resume_connection(
-                stdin,
-                stdout,
-                session_id,
-                acp_session_id,
-                cwd,
-                stream_tx,
-                app_handle,
-                current_message_id,
-                cmd_rx,
-                handshake_tx,
-                perm_tx,
-                perm_rx,
-                mcp_servers,
-            )
-            .await;
-        });
+    let thread = spawn_connection_thread(move || {
+        run_acp_resume_connection(
+            stdin,
+            stdout,
+            session_id,
+            acp_session_id,
+            cwd,
+            stream_tx,
+            app_handle,
+            current_message_id,
+            cmd_rx,
+            handshake_tx,
+            perm_tx,
+            perm_rx,
+            mcp_servers,
+            timeouts,
+            cancel,
+        )
     });
 
-    (cmd_tx, handshake_rx)
+    (cmd_tx, handshake_rx, thread)
 }
 
 // ========================
@@ -826,9 +1030,12 @@ async fn run_acp_connection(
     perm_tx: mpsc::Sender<PendingPermissionInfo>,
     perm_rx: mpsc::Receiver<PendingPermissionInfo>,
     mcp_servers: Vec<agent_client_protocol::McpServer>,
+    timeouts: AcpTimeouts,
+    cancel: CancellationToken,
 ) {
     use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
+    let progress_handle = app_handle.clone();
     let ctx = std::rc::Rc::new(ClientContext {
         session_id: session_id.clone(),
         current_message_id: current_message_id.clone(),
@@ -840,6 +1047,11 @@ async fn run_acp_connection(
 
     let client = ForkClient { ctx };
 
+    // stdout framing is handled by the SDK, which reads each JSON-RPC line in
+    // full (no length cap) and skips lines it cannot parse. It also frames
+    // stdin, writing each message and its newline with a single write. Stdin
+    // is left unbuffered: the SDK never flushes, so a buffer would hold back
+    // requests.
     let (conn, io_future) = acp::ClientSideConnection::new(
         client,
         stdin.compat_write(),
@@ -851,14 +1063,15 @@ async fn run_acp_connection(
 
     tokio::task::spawn_local(async move {
         if let Err(e) = io_future.await {
-            eprintln!("[ACP] IO future error: {:?}", e);
+            eprintln!("[ACP] IO future error: {}", truncate_for_log(&format!("{:?}", e)));
         }
         println!("[ACP] IO future ended for session");
     });
 
     // Handshake: initialize + session/new
     let handshake_result = async {
-        let init_response = acp_initialize_with_retry(&conn).await?;
+        report_progress(&progress_handle, &session_id, SessionProgressPhase::Initializing).await;
+        let init_response = acp_initialize_with_retry(&conn, timeouts.initialize, &cancel).await?;
         let supports_load = init_response.agent_capabilities.load_session;
 
         if let Some(ref info) = init_response.agent_info {
@@ -869,14 +1082,19 @@ async fn run_acp_connection(
             );
         }
 
-        let session_response = conn
-            .new_session(
+        report_progress(&progress_handle, &session_id, SessionProgressPhase::CreatingAcpSession)
+            .await;
+        let session_response = with_timeout(
+            "session/new",
+            timeouts.new_session,
+            conn.new_session(
                 NewSessionRequest::new(&cwd)
                     .mcp_servers(mcp_servers)
                     .meta(build_worktree_meta(&cwd)),
-            )
-            .await
-            .map_err(|e| format!("session/new failed: {:?}", e))?;
+            ),
+        )
+        .await?
+        .map_err(|e| format!("session/new failed: {:?}", e))?;
 
         let acp_session_id = session_response.session_id.to_string();
         println!("[ACP] Session created: {}", acp_session_id);
@@ -917,7 +1135,16 @@ async fn run_acp_connection(
         }
     }
 
-    run_command_loop(conn, cmd_rx, perm_rx, stream_tx, session_id, current_message_id).await;
+    run_command_loop(
+        conn,
+        cmd_rx,
+        perm_rx,
+        stream_tx,
+        session_id,
+        current_message_id,
+        timeouts,
+    )
+    .await;
 }
 
 async fn run_acp_resume_connection(
@@ -934,6 +1161,8 @@ async fn run_acp_resume_connection(
     perm_tx: mpsc::Sender<PendingPermissionInfo>,
     perm_rx: mpsc::Receiver<PendingPermissionInfo>,
     mcp_servers: Vec<agent_client_protocol::McpServer>,
+    timeouts: AcpTimeouts,
+    cancel: CancellationToken,
 ) {
     use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
@@ -959,14 +1188,14 @@ async fn run_acp_resume_connection(
 
     tokio::task::spawn_local(async move {
         if let Err(e) = io_future.await {
-            eprintln!("[ACP] IO future error: {:?}", e);
+            eprintln!("[ACP] IO future error: {}", truncate_for_log(&format!("{:?}", e)));
         }
         println!("[ACP] IO future ended for session");
     });
 
     // Handshake: initialize + session/load or session/resume
     let handshake_result = async {
-        let init_response = acp_initialize_with_retry(&conn).await?;
+        let init_response = acp_initialize_with_retry(&conn, timeouts.initialize, &cancel).await?;
         let supports_load = init_response.agent_capabilities.load_session;
 
         if let Some(ref info) = init_response.agent_info {
@@ -985,16 +1214,19 @@ async fn run_acp_resume_connection(
         }
 
         println!("[ACP] Trying session/load for {}", acp_session_id);
-        let load_response = conn
-            .load_session(
+        let load_response = with_timeout(
+            "session/load",
+            timeouts.load_session,
+            conn.load_session(
                 LoadSessionRequest::new(acp_session_id.clone(), cwd.clone())
                     .mcp_servers(mcp_servers)
                     .meta(build_worktree_meta(&cwd)),
-            )
-            .await
-            .map_err(|e| {
+            ),
+        )
+        .await?
+        .map_err(|e| {
                 let err_msg = format!("session/load failed for {}: {:?}", acp_session_id, e);
-                println!("[ACP] {}", err_msg);
+                println!("[ACP] {}", truncate_for_log(&err_msg));
                 err_msg
             })?;
         println!("[ACP] Session loaded via session/load for {}", acp_session_id);
@@ -1037,7 +1269,16 @@ async fn run_acp_resume_connection(
         }
     }
 
-    run_command_loop(conn, cmd_rx, perm_rx, stream_tx, session_id, current_message_id).await;
+    run_command_loop(
+        conn,
+        cmd_rx,
+        perm_rx,
+        stream_tx,
+        session_id,
+        current_message_id,
+        timeouts,
+    )
+    .await;
 }
 
 // ========================
@@ -1051,9 +1292,13 @@ async fn run_command_loop(
     stream_tx: mpsc::Sender<StreamChunk>,
     session_id: String,
     current_message_id: Arc<Mutex<String>>,
+    timeouts: AcpTimeouts,
 ) {
     let pending_perm: std::cell::RefCell<Option<PendingPermissionInfo>> =
         std::cell::RefCell::new(None);
+    // Prompts by message id, shared with the tasks running them
+    let in_flight: std::rc::Rc<std::cell::RefCell<HashMap<String, InFlightPrompt>>> =
+        std::rc::Rc::default();
 
     // Wrap connection in Rc for sharing across tasks
     let conn = std::rc::Rc::new(conn);
@@ -1062,11 +1307,20 @@ async fn run_command_loop(
         tokio::select! {
             cmd = cmd_rx.recv() => {
                 match cmd {
-                    Some(AcpCommand::Prompt { session_id: acp_sid, content, reply }) => {
+                    Some(AcpCommand::Prompt { session_id: acp_sid, message_id, content, reply }) => {
                         {
                             let mut msg_id = current_message_id.lock().await;
-                            *msg_id = uuid::Uuid::new_v4().to_string();
+                            *msg_id = message_id.clone();
                         }
+                        in_flight.borrow_mut().insert(
+                            message_id.clone(),
+                            InFlightPrompt {
+                                acp_session_id: acp_sid.clone(),
+                                started_at: std::time::Instant::now(),
+                                reply: Some(reply),
+                                handle: None,
+                            },
+                        );
 
                         let prompt = PromptRequest::new(
                             SessionId::new(&*acp_sid),
@@ -1078,10 +1332,21 @@ async fn run_command_loop(
                         let stream_tx_clone = stream_tx.clone();
                         let session_id_clone = session_id.clone();
                         let current_message_id_clone = current_message_id.clone();
+                        let in_flight_clone = in_flight.clone();
+                        let prompt_message_id = message_id.clone();
 
-                        tokio::task::spawn_local(async move {
+                        let handle = tokio::task::spawn_local(async move {
                             let result = conn_clone.prompt(prompt).await;
 
+                            // Gone when the prompt was cleared in the meantime
+                            let Some(reply) = in_flight_clone
+                                .borrow_mut()
+                                .remove(&prompt_message_id)
+                                .and_then(|p| p.reply)
+                            else {
+                                return;
+                            };
+
                             match result {
                                 Ok(_response) => {
                                     let msg_id = current_message_id_clone.lock().await.clone();
@@ -1094,15 +1359,33 @@ async fn run_command_loop(
                                             chunk_type: None,
                                             tool_call: None,
                                             image_content: None,
+                                            error: None,
                                         })
                                         .await;
                                     let _ = reply.send(Ok(()));
                                 }
                                 Err(e) => {
-                                    let _ = reply.send(Err(format!("prompt failed: {:?}", e)));
+                                    let error = format!("prompt failed: {:?}", e);
+                                    // End the turn for whoever waits on the stream
+                                    let _ = stream_tx_clone
+                                        .send(StreamChunk {
+                                            session_id: session_id_clone,
+                                            message_id: prompt_message_id,
+                                            content: String::new(),
+                                            is_complete: true,
+                                            chunk_type: None,
+                                            tool_call: None,
+                                            image_content: None,
+                                            error: Some(error.clone()),
+                                        })
+                                        .await;
+                                    let _ = reply.send(Err(error));
                                 }
                             }
                         });
+                        if let Some(prompt) = in_flight.borrow_mut().get_mut(&message_id) {
+                            prompt.handle = Some(handle);
+                        }
                     }
                     Some(AcpCommand::Cancel { session_id: acp_sid, reply }) => {
                         println!("[ACP] Received Cancel command for session: {}", acp_sid);
@@ -1121,44 +1404,73 @@ async fn run_command_loop(
                             }
                         }
                     }
+                    Some(AcpCommand::CancelToolCall { session_id: acp_sid, tool_call_id, reply }) => {
+                        println!(
+                            "[ACP] Received CancelToolCall command for session {}: {}",
+                            acp_sid, tool_call_id
+                        );
+                        let params = serde_json::json!({
+                            "sessionId": acp_sid,
+                            "toolCallId": tool_call_id,
+                        });
+                        let result = match serde_json::value::to_raw_value(&params) {
+                            Ok(raw) => conn
+                                .ext_notification(acp::ExtNotification::new(
+                                    CANCEL_TOOL_CALL_METHOD,
+                                    raw.into(),
+                                ))
+                                .await
+                                .map_err(|e| format!("cancel tool call failed: {:?}", e)),
+                            Err(e) => Err(format!("Failed to encode cancel params: {}", e)),
+                        };
+                        let _ = reply.send(result);
+                    }
                     Some(AcpCommand::SetModel { session_id: acp_sid, model_id, reply }) => {
-                        let result = conn
-                            .set_session_model(
-                                SetSessionModelRequest::new(
-                                    SessionId::new(&*acp_sid),
-                                    acp::ModelId::new(&*model_id),
-                                )
-                            )
-                            .await;
+                        let result = with_timeout(
+                            "session/set_model",
+                            timeouts.set_model,
+                            conn.set_session_model(SetSessionModelRequest::new(
+                                SessionId::new(&*acp_sid),
+                                acp::ModelId::new(&*model_id),
+                            )),
+                        )
+                        .await;
 
                         match result {
-                            Ok(_) => {
+                            Ok(Ok(_)) => {
                                 println!("[ACP] Session model set to: {} for session {}", model_id, acp_sid);
                                 let _ = reply.send(Ok(()));
                             }
-                            Err(e) => {
+                            Ok(Err(e)) => {
                                 let _ = reply.send(Err(format!("set_model failed: {:?}", e)));
                             }
+                            Err(e) => {
+                                let _ = reply.send(Err(e));
+                            }
                         }
                     }
                     Some(AcpCommand::SetMode { session_id: acp_sid, mode_id, reply }) => {
-                        let result = conn
-                            .set_session_mode(
-                                SetSessionModeRequest::new(
-                                    SessionId::new(&*acp_sid),
-                                    acp::SessionModeId::new(&*mode_id),
-                                )
-                            )
-                            .await;
+                        let result = with_timeout(
+                            "session/set_mode",
+                            timeouts.set_mode,
+                            conn.set_session_mode(SetSessionModeRequest::new(
+                                SessionId::new(&*acp_sid),
+                                acp::SessionModeId::new(&*mode_id),
+                            )),
+                        )
+                        .await;
 
                         match result {
-                            Ok(_) => {
+                            Ok(Ok(_)) => {
                                 println!("[ACP] Session mode set to: {} for session {}", mode_id, acp_sid);
                                 let _ = reply.send(Ok(()));
                             }
-                            Err(e) => {
+                            Ok(Err(e)) => {
                                 let _ = reply.send(Err(format!("set_mode failed: {:?}", e)));
                             }
+                            Err(e) => {
+                                let _ = reply.send(Err(e));
+                            }
                         }
                     }
                     Some(AcpCommand::SetConfigOption { config_id, value, reply }) => {
@@ -1167,15 +1479,17 @@ async fn run_command_loop(
                             config_id, value
                         );
 
-                        let result = conn
-                            .set_session_config_option(
-                                acp::SetSessionConfigOptionRequest::new(
-                                    SessionId::new(&*session_id),
-                                    acp::SessionConfigId::new(&*config_id),
-                                    acp::SessionConfigValueId::new(&*value),
-                                )
-                            )
-                            .await;
+                        let result = with_timeout(
+                            "session/set_config_option",
+                            timeouts.set_config_option,
+                            conn.set_session_config_option(acp::SetSessionConfigOptionRequest::new(
+                                SessionId::new(&*session_id),
+                                acp::SessionConfigId::new(&*config_id),
+                                acp::SessionConfigValueId::new(&*value),
+                            )),
+                        )
+                        .await
+                        .and_then(|r| r.map_err(|e| format!("{:?}", e)));
 
                         match result {
                             Ok(response) => {
@@ -1184,11 +1498,14 @@ async fn run_command_loop(
                                     response.config_options.len()
                                 );
 
-                                let _ = reply.send(Ok(()));
+                                let _ = reply.send(Ok(response.config_options));
                             }
                             Err(e) => {
-                                eprintln!("[ACP] Failed to set config option: {:?}", e);
-                                let _ = reply.send(Err(format!("Failed to set config option: {:?}", e)));
+                                eprintln!(
+                                    "[ACP] Failed to set config option: {}",
+                                    truncate_for_log(&e)
+                                );
+                                let _ = reply.send(Err(format!("Failed to set config option: {}", e)));
                             }
                         }
                     }
@@ -1207,6 +1524,81 @@ async fn run_command_loop(
                             let _ = reply.send(Err("No pending permission request".to_string()));
                         }
                     }
+                    Some(AcpCommand::ClearPermission { reply }) => {
+                        let cleared = match pending_perm.borrow_mut().take() {
+                            Some(perm_info) => {
+                                let response =
+                                    RequestPermissionResponse::new(RequestPermissionOutcome::Cancelled);
+                                let _ = perm_info.reply.send(response);
+                                true
+                            }
+                            None => false,
+                        };
+                        let _ = reply.send(cleared);
+                    }
+                    Some(AcpCommand::PendingRequests { reply }) => {
+                        let mut requests: Vec<PendingRequest> = in_flight
+                            .borrow()
+                            .iter()
+                            .map(|(message_id, prompt)| PendingRequest {
+                                id: message_id.clone(),
+                                method: "session/prompt".to_string(),
+                                elapsed_ms: prompt.started_at.elapsed().as_millis() as u64,
+                            })
+                            .collect();
+                        if let Some(perm_info) = pending_perm.borrow().as_ref() {
+                            requests.push(PendingRequest {
+                                id: perm_info.tool_call_update.tool_call_id.to_string(),
+                                method: "session/request_permission".to_string(),
+                                elapsed_ms: perm_info.received_at.elapsed().as_millis() as u64,
+                            });
+                        }
+                        requests.sort_by(|a, b| b.elapsed_ms.cmp(&a.elapsed_ms));
+                        let _ = reply.send(requests);
+                    }
+                    Some(AcpCommand::ClearPendingRequests { reply }) => {
+                        let prompts: Vec<(String, InFlightPrompt)> =
+                            in_flight.borrow_mut().drain().collect();
+                        let mut cleared = prompts.len();
+                        for (message_id, prompt) in prompts {
+                            if let Some(handle) = prompt.handle {
+                                handle.abort();
+                            }
+                            // Dropping the request doesn't stop the agent, so ask it to
+                            let _ = conn
+                                .cancel(CancelNotification::new(SessionId::new(
+                                    &*prompt.acp_session_id,
+                                )))
+                                .await;
+                            if let Some(prompt_reply) = prompt.reply {
+                                let _ = prompt_reply.send(Err("Request cleared".to_string()));
+                            }
+                            // Let the frontend close the turn it is streaming
+                            let _ = stream_tx
+                                .send(StreamChunk {
+                                    session_id: session_id.clone(),
+                                    message_id,
+                                    content: String::new(),
+                                    is_complete: true,
+                                    chunk_type: None,
+                                    tool_call: None,
+                                    image_content: None,
+                                    error: None,
+                                })
+                                .await;
+                        }
+                        if let Some(perm_info) = pending_perm.borrow_mut().take() {
+                            let response =
+                                RequestPermissionResponse::new(RequestPermissionOutcome::Cancelled);
+                            let _ = perm_info.reply.send(response);
+                            cleared += 1;
+                        }
+                        println!(
+                            "[ACP] Cleared {} pending requests for session {}",
+                            cleared, session_id
+                        );
+                        let _ = reply.send(cleared);
+                    }
                     Some(AcpCommand::Shutdown) | None => {
                         println!("[ACP] Command loop shutting down for session {}", session_id);
                         break;
@@ -1226,8 +1618,48 @@ async fn run_command_loop(
 // Initialize
 // ========================
 
+/// Forward a creation phase to the SessionManager, if it is registered.
+async fn report_progress(app_handle: &AppHandle, session_id: &str, phase: SessionProgressPhase) {
+    if let Some(manager) = app_handle.try_state::<SessionManager>() {
+        manager.report_progress(session_id, phase).await;
+    }
+}
+
+/// Protocol versions the client can talk to. A version other than the one we
+/// request is accepted with a warning as long as it falls inside this range.
+const SUPPORTED_PROTOCOL_VERSIONS: std::ops::RangeInclusive<u64> = 1..=2;
+
+/// Accept the agent's protocol version if it is in the supported range
+fn check_protocol_version(agent_version: &acp::ProtocolVersion) -> Result<(), String> {
+    if *agent_version == acp::ProtocolVersion::LATEST {
+        return Ok(());
+    }
+
+    let number = serde_json::to_value(agent_version)
+        .ok()
+        .and_then(|v| v.as_u64());
+    match number {
+        Some(n) if SUPPORTED_PROTOCOL_VERSIONS.contains(&n) => {
+            eprintln!(
+                "[ACP] Warning: agent speaks protocol version {}, client requested {}; continuing",
+                agent_version,
+                acp::ProtocolVersion::LATEST
+            );
+            Ok(())
+        }
+        _ => Err(format!(
+            "Incompatible protocol version: agent={}, client supports {}-{}",
+            agent_version,
+            SUPPORTED_PROTOCOL_VERSIONS.start(),
+            SUPPORTED_PROTOCOL_VERSIONS.end()
+        )),
+    }
+}
+
 async fn acp_initialize_with_retry(
     conn: &acp::ClientSideConnection,
+    attempt_timeout: Duration,
+    cancel: &CancellationToken,
 ) -> Result<InitializeResponse, String> {
     let mut last_error = None;
     for attempt in 1..=15 {
@@ -1237,18 +1669,18 @@ async fn acp_initialize_with_retry(
                     .title("Forkestra"),
             );
 
-        let result = conn.initialize(request).await;
+        let result = tokio::select! {
+            result = with_timeout("initialize", attempt_timeout, conn.initialize(request)) => {
+                result.and_then(|r| r.map_err(|e| format!("{:?}", e)))
+            }
+            _ = cancel.cancelled() => return Err(HANDSHAKE_CANCELLED.to_string()),
+        };
 
         match result {
             Ok(response) => {
                 println!("[ACP] Initialize succeeded on attempt {}", attempt);
 
-                if response.protocol_version != acp::ProtocolVersion::LATEST {
-                    return Err(format!(
-                        "Protocol version mismatch: agent={}, client={}",
-                        response.protocol_version, acp::ProtocolVersion::LATEST
-                    ));
-                }
+                check_protocol_version(&response.protocol_version)?;
 
                 let caps = &response.agent_capabilities;
                 println!(
@@ -1263,11 +1695,15 @@ async fn acp_initialize_with_retry(
             }
             Err(e) => {
                 println!(
-                    "[ACP] Initialize attempt {} failed: {:?}. Retrying...",
-                    attempt, e
+                    "[ACP] Initialize attempt {} failed: {}. Retrying...",
+                    attempt,
+                    truncate_for_log(&e)
                 );
-                last_error = Some(format!("{:?}", e));
-                tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+                last_error = Some(e);
+                tokio::select! {
+                    _ = tokio::time::sleep(std::time::Duration::from_secs(2)) => {}
+                    _ = cancel.cancelled() => return Err(HANDSHAKE_CANCELLED.to_string()),
+                }
             }
         }
     }
@@ -1278,61 +1714,153 @@ async fn acp_initialize_with_retry(
     ))
 }
 
+// ========================
+// Prompt Building
+// ========================
+
+/// Convert prompt parts into the ACP content blocks sent in `session/prompt`
+pub fn build_prompt_blocks(content: Vec<PromptContent>, shape: PromptShape) -> Vec<ContentBlock> {
+    let image_block = |img: ImageContent| {
+        let mut image_content = acp::ImageContent::new(img.data, img.mime_type);
+        if let Some(uri) = img.uri {
+            image_content = image_content.uri(uri);
+        }
+        ContentBlock::Image(image_content)
+    };
+
+    match shape {
+        PromptShape::Standard => content
+            .into_iter()
+            .map(|c| match c {
+                PromptContent::Text { text } => ContentBlock::Text(acp::TextContent::new(text)),
+                PromptContent::Image(img) => image_block(img),
+                PromptContent::ResourceLink(rl) => {
+                    let mut resource_link = acp::ResourceLink::new(rl.name, rl.uri);
+                    if let Some(mime) = rl.mime_type {
+                        resource_link = resource_link.mime_type(mime);
+                    }
+                    ContentBlock::ResourceLink(resource_link)
+                }
+            })
+            .collect(),
+        PromptShape::SingleText => {
+            let mut parts = Vec::new();
+            let mut images = Vec::new();
+            for c in content {
+                match c {
+                    PromptContent::Text { text } => parts.push(text),
+                    PromptContent::Image(img) => images.push(image_block(img)),
+                    PromptContent::ResourceLink(rl) => parts.push(format!("@{}", rl.uri)),
+                }
+            }
+
+            let mut blocks = Vec::with_capacity(images.len() + 1);
+            if !parts.is_empty() {
+                blocks.push(ContentBlock::Text(acp::TextContent::new(parts.join("\n\n"))));
+            }
+            blocks.extend(images);
+            blocks
+        }
+    }
+}
+
 // ========================
 // Model Extraction
 // ========================
 
 /// Extract models from the session response. Prefers the `models` (SessionModelState)
 /// field when available, falls back to extracting from `config_options`.
+///
+/// Some agents report models in both places with different current ids. The list
+/// and the current id always come from the same source, so a stale current id from
+/// the other source is never paired with the chosen list.
 fn extract_models(
     model_state: Option<&acp::SessionModelState>,
     config_options: Option<&[acp::SessionConfigOption]>,
 ) -> (Vec<ModelInfo>, Option<String>) {
-    // Prefer the dedicated `models` field (unstable_session_model)
-    if let Some(state) = model_state {
-        let models: Vec<ModelInfo> = state
-            .available_models
-            .iter()
-            .map(|m| ModelInfo {
-                model_id: m.model_id.to_string(),
-                display_name: m.name.clone(),
-                description: m.description.clone(),
-            })
-            .collect();
-        let current_model_id = Some(state.current_model_id.to_string());
-
-        println!(
-            "[ACP] Extracted {} models from SessionModelState, current_model_id = {:?}",
-            models.len(), current_model_id
-        );
+    let from_state = model_state.map(models_from_state);
+    let from_config = config_options.map(models_from_config_options);
 
-        if !models.is_empty() {
-            return (models, current_model_id);
+    if let (Some((_, Some(state_current))), Some((config_models, Some(config_current)))) =
+        (&from_state, &from_config)
+    {
+        if !config_models.is_empty() && state_current != config_current {
+            println!(
+                "[ACP] SessionModelState current_model_id {:?} disagrees with config_options {:?}, using SessionModelState",
+                state_current, config_current
+            );
         }
     }
 
-    // Fallback: extract from config_options
+    // Prefer the dedicated `models` field (unstable_session_model)
+    let (mut models, current_model_id) = match (from_state, from_config) {
+        (Some(state), _) if !state.0.is_empty() => {
+            println!(
+                "[ACP] Extracted {} models from SessionModelState, current_model_id = {:?}",
+                state.0.len(), state.1
+            );
+            state
+        }
+        (_, Some(config)) => {
+            println!(
+                "[ACP] Extracted {} models from config_options, current_model_id = {:?}",
+                config.0.len(), config.1
+            );
+            config
+        }
+        _ => (Vec::new(), None),
+    };
+
+    models.iter_mut().for_each(models_catalog::apply_friendly_name);
+    (models, current_model_id)
+}
+
+/// Models and current model id from the dedicated `models` field
+fn models_from_state(state: &acp::SessionModelState) -> (Vec<ModelInfo>, Option<String>) {
+    let models = state
+        .available_models
+        .iter()
+        .map(|m| ModelInfo {
+            model_id: m.model_id.to_string(),
+            display_name: m.name.clone(),
+            description: m.description.clone(),
+        })
+        .collect();
+    let current_model_id = Some(state.current_model_id.to_string()).filter(|id| !id.is_empty());
+    (models, current_model_id)
+}
+
+/// Models and current model id from the model-category select in `config_options`
+fn models_from_config_options(
+    options: &[acp::SessionConfigOption],
+) -> (Vec<ModelInfo>, Option<String>) {
     let mut models = Vec::new();
     let mut current_model_id = None;
 
-    println!(
-        "[ACP] Extracting models from config_options: {} options",
-        config_options.map_or(0, |opts| opts.len())
-    );
-
-    if let Some(options) = config_options {
-        for option in options {
-            println!(
-                "[ACP] Config option: name={}, category={:?}",
-                option.name, option.category
-            );
-            if option.category == Some(SessionConfigOptionCategory::Model) {
-                if let SessionConfigKind::Select(select) = &option.kind {
-                    current_model_id = Some(select.current_value.to_string());
+    println!("[ACP] Extracting models from config_options: {} options", options.len());
 
-                    match &select.options {
-                        SessionConfigSelectOptions::Ungrouped(opts) => {
-                            for opt in opts {
+    for option in options {
+        println!(
+            "[ACP] Config option: name={}, category={:?}",
+            option.name, option.category
+        );
+        if option.category == Some(SessionConfigOptionCategory::Model) {
+            if let SessionConfigKind::Select(select) = &option.kind {
+                current_model_id = Some(select.current_value.to_string());
+
+                match &select.options {
+                    SessionConfigSelectOptions::Ungrouped(opts) => {
+                        for opt in opts {
+                            models.push(ModelInfo {
+                                model_id: opt.value.to_string(),
+                                display_name: opt.name.clone(),
+                                description: opt.description.clone(),
+                            });
+                        }
+                    }
+                    SessionConfigSelectOptions::Grouped(groups) => {
+                        for group in groups {
+                            for opt in &group.options {
                                 models.push(ModelInfo {
                                     model_id: opt.value.to_string(),
                                     display_name: opt.name.clone(),
@@ -1340,29 +1868,13 @@ fn extract_models(
                                 });
                             }
                         }
-                        SessionConfigSelectOptions::Grouped(groups) => {
-                            for group in groups {
-                                for opt in &group.options {
-                                    models.push(ModelInfo {
-                                        model_id: opt.value.to_string(),
-                                        display_name: opt.name.clone(),
-                                        description: opt.description.clone(),
-                                    });
-                                }
-                            }
-                        }
-                        _ => {}
                     }
+                    _ => {}
                 }
             }
         }
     }
 
-    println!(
-        "[ACP] Extracted {} models from config_options, current_model_id = {:?}",
-        models.len(), current_model_id
-    );
-
     (models, current_model_id)
 }
 
@@ -1439,23 +1951,61 @@ fn extract_modes(
 // Stderr Reader
 // ========================
 
+/// Marker the agent CLI prints to stderr right before it dies
+const CLI_EXIT_MARKER: &str = "CLI exited with code";
+
+/// The exit message of a stderr line announcing that the agent CLI died
+pub(crate) fn cli_exit_message(line: &str) -> Option<&str> {
+    line.find(CLI_EXIT_MARKER).map(|pos| line[pos..].trim())
+}
+
 pub fn spawn_stderr_reader(
     stderr: tokio::process::ChildStderr,
     provider_name: String,
     stream_tx: mpsc::Sender<StreamChunk>,
     session_id: String,
     current_message_id: Arc<Mutex<String>>,
+    app_handle: AppHandle,
 ) {
-    use tokio::io::AsyncBufReadExt;
-
     tokio::spawn(async move {
-        let reader = tokio::io::BufReader::new(stderr);
-        let mut lines = reader.lines();
+        let mut reader = tokio::io::BufReader::new(stderr);
+        let mut buf = Vec::new();
         let tag_re = regex::Regex::new(r"<local-command-stdout>([\s\S]*?)</local-command-stdout>")
             .expect("invalid regex");
 
-        while let Ok(Some(line)) = lines.next_line().await {
-            println!("[ACP:{}:stderr] {}", provider_name, line);
+        loop {
+            let truncated = match read_bounded_line(&mut reader, &mut buf, MAX_STDERR_LINE_BYTES).await {
+                Ok(Some(truncated)) => truncated,
+                Ok(None) => break,
+                Err(e) => {
+                    eprintln!("[ACP:{}:stderr] Read error: {}", provider_name, e);
+                    break;
+                }
+            };
+            // Agents occasionally write non-UTF-8 bytes; decode lossily instead of giving up
+            let line = String::from_utf8_lossy(&buf);
+            println!("[ACP:{}:stderr] {}", provider_name, truncate_for_log(&line));
+
+            if truncated {
+                eprintln!(
+                    "[ACP:{}:stderr] Skipping oversized line (over {} bytes)",
+                    provider_name, MAX_STDERR_LINE_BYTES
+                );
+                continue;
+            }
+
+            if let Some(manager) = app_handle.try_state::<SessionManager>() {
+                manager
+                    .record_activity(&session_id, ActivityKind::Stderr, &line)
+                    .await;
+            }
+
+            if let Some(message) = cli_exit_message(&line) {
+                if let Some(manager) = app_handle.try_state::<SessionManager>() {
+                    manager.mark_session_crashed(&session_id, message).await;
+                }
+                continue;
+            }
 
             for cap in tag_re.captures_iter(&line) {
                 if let Some(content) = cap.get(1) {
@@ -1471,6 +2021,7 @@ pub fn spawn_stderr_reader(
                                 chunk_type: Some(StreamChunkType::Text),
                                 tool_call: None,
                                 image_content: None,
+                                error: None,
                             })
                             .await;
                     }
@@ -1479,3 +2030,420 @@ pub fn spawn_stderr_reader(
         }
     });
 }
+
+/// Longest stderr line kept in memory; the rest of a longer line is discarded
+const MAX_STDERR_LINE_BYTES: usize = 1024 * 1024;
+
+/// Read one `\n`-terminated line into `buf` (without the newline), keeping at most
+/// `max_len` bytes. The remainder of an over-long line is consumed and dropped so
+/// the reader stays aligned on line boundaries.
+/// Returns `Ok(None)` at EOF, otherwise whether the line was truncated.
+async fn read_bounded_line<R>(
+    reader: &mut R,
+    buf: &mut Vec<u8>,
+    max_len: usize,
+) -> std::io::Result<Option<bool>>
+where
+    R: tokio::io::AsyncBufRead + Unpin,
+{
+    use tokio::io::AsyncBufReadExt;
+
+    buf.clear();
+    let mut truncated = false;
+    let mut read_any = false;
+
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            return Ok(read_any.then_some(truncated));
+        }
+        read_any = true;
+
+        let newline = available.iter().position(|&b| b == b'\n');
+        let chunk = &available[..newline.unwrap_or(available.len())];
+        let room = max_len.saturating_sub(buf.len());
+        if chunk.len() > room {
+            truncated = true;
+        }
+        buf.extend_from_slice(&chunk[..chunk.len().min(room)]);
+
+        let consumed = newline.map_or(available.len(), |i| i + 1);
+        reader.consume(consumed);
+        if newline.is_some() {
+            if buf.last() == Some(&b'\r') {
+                buf.pop();
+            }
+            return Ok(Some(truncated));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+    struct NullClient;
+
+    #[async_trait::async_trait(?Send)]
+    impl acp::Client for NullClient {
+        async fn request_permission(
+            &self,
+            _args: RequestPermissionRequest,
+        ) -> acp::Result<RequestPermissionResponse> {
+            Err(acp::Error::method_not_found())
+        }
+
+        async fn session_notification(&self, _args: SessionNotification) -> acp::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn connection_writes_one_message_per_line() {
+        const COUNT: usize = 50;
+        let local = tokio::task::LocalSet::new();
+        local
+            .run_until(async {
+                let (agent_stdin, client_stdin) = tokio::io::duplex(64);
+                let (_agent_stdout, client_stdout) = tokio::io::duplex(64);
+                let (conn, io_future) = acp::ClientSideConnection::new(
+                    NullClient,
+                    client_stdin.compat_write(),
+                    client_stdout.compat(),
+                    |f| {
+                        tokio::task::spawn_local(f);
+                    },
+                );
+                tokio::task::spawn_local(io_future);
+
+                // Queue every notification before reading so writes back up
+                // behind the small pipe buffer and split mid-message.
+                let conn = std::rc::Rc::new(conn);
+                for i in 0..COUNT {
+                    let conn = conn.clone();
+                    tokio::task::spawn_local(async move {
+                        let session_id = SessionId::new(format!("session-{i}"));
+                        conn.cancel(CancelNotification::new(session_id)).await
+                    });
+                }
+
+                let mut lines = BufReader::new(agent_stdin).lines();
+                let mut seen = Vec::new();
+                while seen.len() < COUNT {
+                    let line = lines.next_line().await.unwrap().expect("stdin closed");
+                    let value: serde_json::Value = serde_json::from_str(&line)
+                        .unwrap_or_else(|e| panic!("not one JSON message: {line:?}: {e}"));
+                    assert_eq!(value["method"], "session/cancel");
+                    seen.push(value["params"]["sessionId"].as_str().unwrap().to_string());
+                }
+                seen.sort();
+                seen.dedup();
+                assert_eq!(seen.len(), COUNT);
+            })
+            .await;
+    }
+
+    /// Tags text chunks with the current message id, like `ForkClient`
+    struct ChunkClient {
+        current_message_id: Arc<Mutex<String>>,
+        stream_tx: mpsc::Sender<StreamChunk>,
+    }
+
+    #[async_trait::async_trait(?Send)]
+    impl acp::Client for ChunkClient {
+        async fn request_permission(
+            &self,
+            _args: RequestPermissionRequest,
+        ) -> acp::Result<RequestPermissionResponse> {
+            Err(acp::Error::method_not_found())
+        }
+
+        async fn session_notification(&self, args: SessionNotification) -> acp::Result<()> {
+            let msg_id = self.current_message_id.lock().await.clone();
+            if let SessionUpdate::AgentMessageChunk(chunk) = &args.update {
+                handle_content_chunk(
+                    &chunk.content,
+                    "s1",
+                    &msg_id,
+                    &self.stream_tx,
+                    StreamChunkType::Text,
+                )
+                .await;
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn turn_chunks_carry_the_prompt_message_id() {
+        let local = tokio::task::LocalSet::new();
+        local
+            .run_until(async {
+                let (agent_stdin, client_stdin) = tokio::io::duplex(4096);
+                let (mut agent_stdout, client_stdout) = tokio::io::duplex(4096);
+                let current_message_id = Arc::new(Mutex::new(String::new()));
+                let (stream_tx, mut stream_rx) = mpsc::channel(16);
+                let (conn, io_future) = acp::ClientSideConnection::new(
+                    ChunkClient {
+                        current_message_id: current_message_id.clone(),
+                        stream_tx: stream_tx.clone(),
+                    },
+                    client_stdin.compat_write(),
+                    client_stdout.compat(),
+                    |f| {
+                        tokio::task::spawn_local(f);
+                    },
+                );
+                tokio::task::spawn_local(io_future);
+
+                // The agent streams one text chunk, then ends the turn
+                tokio::task::spawn_local(async move {
+                    use tokio::io::AsyncWriteExt;
+                    let mut lines = BufReader::new(agent_stdin).lines();
+                    let line = lines.next_line().await.unwrap().unwrap();
+                    let request: serde_json::Value = serde_json::from_str(&line).unwrap();
+                    assert_eq!(request["method"], "session/prompt");
+                    let update = serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "method": "session/update",
+                        "params": {
+                            "sessionId": "acp-1",
+                            "update": {
+                                "sessionUpdate": "agent_message_chunk",
+                                "content": { "type": "text", "text": "hello" }
+                            }
+                        }
+                    });
+                    let response = serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "result": { "stopReason": "end_turn" }
+                    });
+                    for message in [update, response] {
+                        agent_stdout
+                            .write_all(format!("{}\n", message).as_bytes())
+                            .await
+                            .unwrap();
+                    }
+                });
+
+                let (cmd_tx, cmd_rx) = mpsc::channel(4);
+                let (_perm_tx, perm_rx) = mpsc::channel(1);
+                let command_loop = tokio::task::spawn_local(run_command_loop(
+                    conn,
+                    cmd_rx,
+                    perm_rx,
+                    stream_tx,
+                    "s1".to_string(),
+                    current_message_id,
+                    AcpTimeouts::default(),
+                ));
+
+                let (reply_tx, reply_rx) = oneshot::channel();
+                cmd_tx
+                    .send(AcpCommand::Prompt {
+                        session_id: "acp-1".to_string(),
+                        message_id: "m-1".to_string(),
+                        content: vec![ContentBlock::Text(acp::TextContent::new("hi"))],
+                        reply: reply_tx,
+                    })
+                    .await
+                    .unwrap();
+                reply_rx.await.unwrap().unwrap();
+
+                let mut chunks = vec![
+                    stream_rx.recv().await.unwrap(),
+                    stream_rx.recv().await.unwrap(),
+                ];
+                chunks.sort_by_key(|c| c.is_complete);
+                assert!(chunks.iter().all(|c| c.message_id == "m-1"));
+                assert_eq!(chunks[0].content, "hello");
+                assert!(chunks[1].is_complete);
+
+                cmd_tx.send(AcpCommand::Shutdown).await.unwrap();
+                command_loop.await.unwrap();
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn read_bounded_line_splits_and_truncates() {
+        let input: &[u8] = b"short\r\nthis line is too long\nlast";
+        let mut reader = BufReader::with_capacity(4, input);
+        let mut buf = Vec::new();
+
+        let truncated = read_bounded_line(&mut reader, &mut buf, 8).await.unwrap();
+        assert_eq!((truncated, buf.as_slice()), (Some(false), &b"short"[..]));
+
+        let truncated = read_bounded_line(&mut reader, &mut buf, 8).await.unwrap();
+        assert_eq!((truncated, buf.as_slice()), (Some(true), &b"this lin"[..]));
+
+        let truncated = read_bounded_line(&mut reader, &mut buf, 8).await.unwrap();
+        assert_eq!((truncated, buf.as_slice()), (Some(false), &b"last"[..]));
+
+        assert_eq!(
+            read_bounded_line(&mut reader, &mut buf, 8).await.unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn oversized_agent_message_is_parsed_whole() {
+        let text = "x".repeat(8 * 1024 * 1024);
+        let local = tokio::task::LocalSet::new();
+        local
+            .run_until(async {
+                let (_agent_stdin, client_stdin) = tokio::io::duplex(64);
+                let (mut agent_stdout, client_stdout) = tokio::io::duplex(4096);
+                let (stream_tx, mut stream_rx) = mpsc::channel(4);
+                let (_conn, io_future) = acp::ClientSideConnection::new(
+                    ChunkClient {
+                        current_message_id: Arc::new(Mutex::new("m-1".to_string())),
+                        stream_tx,
+                    },
+                    client_stdin.compat_write(),
+                    client_stdout.compat(),
+                    |f| {
+                        tokio::task::spawn_local(f);
+                    },
+                );
+                tokio::task::spawn_local(io_future);
+
+                let update = serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "method": "session/update",
+                    "params": {
+                        "sessionId": "acp-1",
+                        "update": {
+                            "sessionUpdate": "agent_message_chunk",
+                            "content": { "type": "text", "text": text }
+                        }
+                    }
+                });
+                tokio::task::spawn_local(async move {
+                    use tokio::io::AsyncWriteExt;
+                    agent_stdout
+                        .write_all(format!("{}\n", update).as_bytes())
+                        .await
+                        .unwrap();
+                });
+
+                let chunk = stream_rx.recv().await.unwrap();
+                assert_eq!(chunk.content.len(), text.len());
+                assert_eq!(chunk.content, text);
+            })
+            .await;
+    }
+
+    fn protocol_version(n: u64) -> acp::ProtocolVersion {
+        serde_json::from_value(serde_json::json!(n)).unwrap()
+    }
+
+    fn texts(blocks: &[ContentBlock]) -> Vec<&str> {
+        blocks
+            .iter()
+            .filter_map(|b| match b {
+                ContentBlock::Text(t) => Some(t.text.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn image() -> PromptContent {
+        PromptContent::Image(ImageContent {
+            data: "aGk=".to_string(),
+            mime_type: "image/png".to_string(),
+            uri: None,
+        })
+    }
+
+    fn link(uri: &str) -> PromptContent {
+        PromptContent::ResourceLink(crate::models::ResourceLinkContent {
+            uri: uri.to_string(),
+            name: "main.rs".to_string(),
+            mime_type: None,
+        })
+    }
+
+    fn text(text: &str) -> PromptContent {
+        PromptContent::Text {
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn protocol_versions_in_range_are_accepted() {
+        assert!(check_protocol_version(&acp::ProtocolVersion::LATEST).is_ok());
+        for n in SUPPORTED_PROTOCOL_VERSIONS {
+            assert!(check_protocol_version(&protocol_version(n)).is_ok());
+        }
+        let too_new = SUPPORTED_PROTOCOL_VERSIONS.end() + 1;
+        let error = check_protocol_version(&protocol_version(too_new)).unwrap_err();
+        assert!(error.contains("Incompatible protocol version"));
+        assert!(check_protocol_version(&protocol_version(0)).is_err());
+    }
+
+    #[test]
+    fn standard_prompt_keeps_one_block_per_part() {
+        let blocks = build_prompt_blocks(
+            vec![text("fix"), link("file:///main.rs"), image()],
+            PromptShape::Standard,
+        );
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(texts(&blocks), ["fix"]);
+        assert!(
+            matches!(&blocks[1], ContentBlock::ResourceLink(rl) if rl.uri == "file:///main.rs")
+        );
+        assert!(matches!(&blocks[2], ContentBlock::Image(img) if img.mime_type == "image/png"));
+    }
+
+    #[test]
+    fn single_text_prompt_merges_text_before_images() {
+        let blocks = build_prompt_blocks(
+            vec![image(), text("fix"), link("file:///main.rs")],
+            PromptShape::SingleText,
+        );
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(texts(&blocks), ["fix\n\n@file:///main.rs"]);
+        assert!(matches!(&blocks[1], ContentBlock::Image(_)));
+
+        let images_only = build_prompt_blocks(vec![image()], PromptShape::SingleText);
+        assert!(matches!(images_only.as_slice(), [ContentBlock::Image(_)]));
+    }
+
+    #[test]
+    fn diff_from_json_accepts_agent_key_spellings() {
+        let value = serde_json::json!({
+            "file_path": "src/main.rs",
+            "old_string": "a",
+            "new_string": "b",
+        });
+        match diff_from_json(&value) {
+            Some(crate::models::ToolCallContentItem::Diff {
+                path,
+                old_text,
+                new_text,
+            }) => {
+                assert_eq!(path, "src/main.rs");
+                assert_eq!(old_text.as_deref(), Some("a"));
+                assert_eq!(new_text, "b");
+            }
+            other => panic!("expected a diff, got {other:?}"),
+        }
+
+        let new_file = serde_json::json!({ "filePath": "new.rs", "newText": "fn main() {}" });
+        assert!(matches!(
+            diff_from_json(&new_file),
+            Some(crate::models::ToolCallContentItem::Diff { old_text: None, .. })
+        ));
+    }
+
+    #[test]
+    fn diff_from_json_needs_path_and_new_text() {
+        assert!(diff_from_json(&serde_json::json!({ "path": "a.rs" })).is_none());
+        assert!(diff_from_json(&serde_json::json!({ "newText": "x" })).is_none());
+        assert!(diff_from_json(&serde_json::json!({ "path": 1, "newText": "x" })).is_none());
+    }
+}
@@ -1,12 +1,16 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use agent_client_protocol::{McpServer, SessionConfigOption};
 use async_trait::async_trait;
 use tauri::AppHandle;
 use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
 
 use crate::error::{AppError, AppResult};
-use crate::models::{ModeInfo, ModelInfo, PromptContent, ProviderInfo, ProviderType, StreamChunk};
+use crate::models::{
+    AgentSessionInfo, ModeInfo, ModelInfo, PendingRequest, PromptContent, ProviderInfo,
+    ProviderType, StreamChunk,
+};
 
 #[async_trait]
 pub trait ProviderAdapter: Send + Sync {
@@ -29,28 +33,47 @@ pub trait ProviderAdapter: Send + Sync {
     /// Resume an existing ACP session by its session ID
     ///
     /// - `worktree_path`: Directory where the ACP process will run (for file access isolation)
-    /// - `project_path`: Original project path used for session file lookup (should match session/new cwd)
+    /// - `acp_cwd`: Exact cwd the ACP session was created with; agents key their
+    ///   session files by it, so session/load must send the same value
     async fn resume_session(
         &mut self,
         session_id: &str,
         acp_session_id: &str,
         worktree_path: &Path,
-        project_path: &Path,
+        acp_cwd: &str,
         stream_tx: mpsc::Sender<StreamChunk>,
         app_handle: AppHandle,
         mcp_servers: Vec<McpServer>,
     ) -> AppResult<()> {
-        let _ = (session_id, acp_session_id, worktree_path, project_path, stream_tx, app_handle, mcp_servers);
+        let _ = (session_id, acp_session_id, worktree_path, acp_cwd, stream_tx, app_handle, mcp_servers);
         Err(AppError::Provider(
             "This provider does not support session resume".to_string(),
         ))
     }
 
+    /// Sessions the agent persisted in its own storage whose cwd is one of `cwds`
+    /// or inside one of them. Agents that cannot enumerate sessions report none.
+    fn list_agent_sessions(&self, cwds: &[PathBuf]) -> AppResult<Vec<AgentSessionInfo>> {
+        let _ = cwds;
+        Ok(vec![])
+    }
+
+    /// Fingerprint of the launch configuration (command, args, env vars).
+    /// Two adapters with the same fingerprint would spawn identical processes.
+    fn launch_fingerprint(&self) -> Option<String> {
+        None
+    }
+
     /// Get the ACP session ID if available
     fn acp_session_id(&self) -> Option<&str> {
         None
     }
 
+    /// Get the cwd sent in session/new or session/load, if a session is established
+    fn acp_cwd(&self) -> Option<&str> {
+        None
+    }
+
     /// Get available models reported by the ACP provider
     fn available_models(&self) -> Vec<ModelInfo> {
         vec![]
@@ -76,8 +99,9 @@ pub trait ProviderAdapter: Send + Sync {
         vec![]
     }
 
-    /// Send a message to the CLI
-    async fn send_message(&mut self, content: Vec<PromptContent>) -> AppResult<()>;
+    /// Send a message to the CLI; stream chunks for the turn use `message_id`
+    async fn send_message(&mut self, content: Vec<PromptContent>, message_id: &str)
+        -> AppResult<()>;
 
     /// Set the model for the current session
     async fn set_model(&mut self, model_id: &str) -> AppResult<()>;
@@ -85,12 +109,30 @@ pub trait ProviderAdapter: Send + Sync {
     /// Set the mode for the current session
     async fn set_mode(&mut self, mode_id: &str) -> AppResult<()>;
 
-    /// Set a config option for the current session
-    async fn set_config_option(&mut self, config_id: &str, value: &str) -> AppResult<()>;
+    /// Set a config option for the current session. Returns all of the
+    /// session's config options as the agent reports them after the change.
+    async fn set_config_option(
+        &mut self,
+        config_id: &str,
+        value: &str,
+    ) -> AppResult<Vec<agent_client_protocol::SessionConfigOption>>;
 
     /// Check if the session is active
     fn is_active(&self) -> bool;
 
+    /// Use this token to cancel the next start or resume handshake
+    fn set_handshake_cancellation(&mut self, _token: CancellationToken) {}
+
+    /// OS process id of the spawned agent, if one is running
+    fn process_id(&self) -> Option<u32> {
+        None
+    }
+
+    /// Check whether the underlying agent process is still running
+    fn is_process_alive(&mut self) -> bool {
+        self.is_active()
+    }
+
     /// Cancel the current ongoing prompt (graceful)
     async fn cancel(&mut self) -> AppResult<()> {
         Err(AppError::Provider(
@@ -98,6 +140,30 @@ pub trait ProviderAdapter: Send + Sync {
         ))
     }
 
+    /// Interrupt a single in-flight tool call without ending the prompt turn
+    async fn cancel_tool_call(&mut self, _tool_call_id: &str) -> AppResult<()> {
+        Err(AppError::Provider(
+            "This provider does not support cancelling tool calls".to_string(),
+        ))
+    }
+
+    /// Deny and drop a permission request that is still waiting for an
+    /// answer. Returns whether one was pending.
+    async fn clear_pending_permission(&mut self) -> AppResult<bool> {
+        Ok(false)
+    }
+
+    /// Requests sent to or received from the agent that are still unanswered
+    async fn pending_requests(&self) -> AppResult<Vec<PendingRequest>> {
+        Ok(Vec::new())
+    }
+
+    /// Abort every unanswered request, failing whoever awaits it. Returns how
+    /// many were cleared.
+    async fn clear_pending_requests(&mut self) -> AppResult<usize> {
+        Ok(0)
+    }
+
     /// Terminate the session
     async fn terminate(&mut self) -> AppResult<()>;
 }
@@ -1,37 +1,79 @@
-use std::process::Command;
-use std::sync::OnceLock;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use parking_lot::RwLock;
 
 use crate::error::AppResult;
 use crate::models::{
-    builtin_definitions, ProviderDefinition, ProviderInfo, ProviderSettings, ProviderType,
+    builtin_definitions, AuthStatus, ProviderDefinition, ProviderInfo, ProviderSettings,
+    ProviderType,
 };
 
 pub struct ProviderDetector;
 
-/// Cached shell PATH environment variable
-static SHELL_PATH: OnceLock<Option<String>> = OnceLock::new();
+/// Cached shell PATH environment variable. The outer `None` means it has not
+/// been read from the shell yet.
+static SHELL_PATH: RwLock<Option<Option<String>>> = RwLock::new(None);
+
+/// How long an auth status subcommand may run before the check gives up
+const AUTH_STATUS_TIMEOUT: Duration = Duration::from_secs(10);
 
 impl ProviderDetector {
     /// Get the PATH from user's shell configuration
     pub fn get_shell_path() -> Option<String> {
-        SHELL_PATH
-            .get_or_init(|| {
-                let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string());
+        if let Some(path) = SHELL_PATH.read().as_ref() {
+            return path.clone();
+        }
+        // Spawning the shell is slow, so do it without holding the lock. Two
+        // callers racing here both read the same PATH.
+        let path = Self::read_shell_path();
+        SHELL_PATH.write().get_or_insert(path).clone()
+    }
 
-                // Try login+interactive shell first (-li loads both .zprofile and .zshrc)
-                // Fall back to login-only (-l) if that fails
-                for args in [&["-li", "-c", "echo $PATH"][..], &["-l", "-c", "echo $PATH"][..]] {
-                    if let Ok(output) = Command::new(&shell).args(args).output() {
-                        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
-                        if !path.is_empty() {
-                            return Some(path);
-                        }
-                    }
-                }
+    /// Re-read the PATH from the user's shell, e.g. after a CLI was installed
+    /// while the app was running. Later detection and spawns use the new value.
+    pub fn refresh_shell_path() -> Option<String> {
+        let path = Self::read_shell_path();
+        *SHELL_PATH.write() = Some(path.clone());
+        println!("[ProviderDetector] Refreshed shell PATH: {:?}", path);
+        path
+    }
 
-                None
-            })
-            .clone()
+    fn read_shell_path() -> Option<String> {
+        let shell = std::env::var("SHELL").unwrap_or_else(|_| "/bin/zsh".to_string());
+        let process_path = std::env::var("PATH").ok();
+        let (path, source) = Self::resolve_shell_path(&shell, process_path, |program, args| {
+            let output = Command::new(program).args(args).output().ok()?;
+            Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        })?;
+        println!("[ProviderDetector] Using PATH from {}", source);
+        Some(path)
+    }
+
+    /// Find the user's PATH, trying in order: `$SHELL` as a login+interactive
+    /// shell (loads both .zprofile and .zshrc), `$SHELL` as a login shell,
+    /// `/bin/sh`, and finally the PATH this process was started with. `run`
+    /// executes a program and returns its trimmed stdout. Returns the PATH and
+    /// a description of where it came from.
+    fn resolve_shell_path(
+        shell: &str,
+        process_path: Option<String>,
+        run: impl Fn(&str, &[&str]) -> Option<String>,
+    ) -> Option<(String, String)> {
+        let attempts: [(&str, &[&str]); 3] = [
+            (shell, &["-li", "-c", "echo $PATH"]),
+            (shell, &["-l", "-c", "echo $PATH"]),
+            ("/bin/sh", &["-c", "echo $PATH"]),
+        ];
+        for (program, args) in attempts {
+            if let Some(path) = run(program, args).filter(|p| !p.is_empty()) {
+                return Some((path, format!("{} {}", program, args[0])));
+            }
+        }
+
+        process_path
+            .filter(|p| !p.is_empty())
+            .map(|p| (p, "the process environment".to_string()))
     }
 
     /// Find executable in PATH, trying shell PATH first
@@ -153,6 +195,62 @@ impl ProviderDetector {
         }
     }
 
+    /// Run the provider CLI's auth status subcommand (`auth_status_args`) and map
+    /// its exit code. Unknown when the CLI is not installed, has no such
+    /// subcommand, or does not finish within `AUTH_STATUS_TIMEOUT`.
+    pub fn check_auth(def: &ProviderDefinition, custom_cli_path: Option<&str>) -> AuthStatus {
+        let Some(args) = def.auth_status_args.as_ref() else {
+            return AuthStatus::Unknown;
+        };
+        let cli_command = def.cli_command.as_deref().unwrap_or(&def.command);
+        let cli_path = custom_cli_path
+            .filter(|p| std::path::Path::new(p).exists())
+            .map(std::path::PathBuf::from)
+            .or_else(|| Self::find_in_path(cli_command));
+        let Some(cli_path) = cli_path else {
+            return AuthStatus::Unknown;
+        };
+
+        let mut cmd = Command::new(&cli_path);
+        cmd.args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if let Some(shell_path) = Self::get_shell_path() {
+            cmd.env("PATH", &shell_path);
+        }
+        let mut child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                eprintln!(
+                    "[ProviderDetector] Failed to run auth status for {}: {}",
+                    def.id, e
+                );
+                return AuthStatus::Unknown;
+            }
+        };
+
+        let started = Instant::now();
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) if status.success() => return AuthStatus::LoggedIn,
+                Ok(Some(_)) => return AuthStatus::LoggedOut,
+                Ok(None) if started.elapsed() < AUTH_STATUS_TIMEOUT => {
+                    std::thread::sleep(Duration::from_millis(100));
+                }
+                Ok(None) | Err(_) => {
+                    eprintln!(
+                        "[ProviderDetector] Auth status for {} did not finish, giving up",
+                        def.id
+                    );
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return AuthStatus::Unknown;
+                }
+            }
+        }
+    }
+
     /// Detect a specific provider (backward-compat helper for built-in types)
     pub fn detect_provider(
         provider_type: &ProviderType,
@@ -214,3 +312,61 @@ impl ProviderDetector {
         Ok(version)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve(
+        process_path: Option<&str>,
+        outputs: &[(&str, &str, &str)],
+    ) -> Option<(String, String)> {
+        ProviderDetector::resolve_shell_path(
+            "/bin/zsh",
+            process_path.map(str::to_string),
+            |program, args| {
+                outputs
+                    .iter()
+                    .find(|(p, flag, _)| *p == program && *flag == args[0])
+                    .map(|(_, _, out)| out.to_string())
+            },
+        )
+    }
+
+    #[test]
+    fn shell_path_prefers_login_interactive_shell() {
+        let found = resolve(
+            Some("/proc"),
+            &[("/bin/zsh", "-li", "/a:/b"), ("/bin/zsh", "-l", "/c")],
+        );
+        assert_eq!(
+            found,
+            Some(("/a:/b".to_string(), "/bin/zsh -li".to_string()))
+        );
+    }
+
+    #[test]
+    fn shell_path_skips_empty_and_failed_attempts() {
+        let found = resolve(
+            None,
+            &[("/bin/zsh", "-li", ""), ("/bin/sh", "-c", "/usr/bin")],
+        );
+        assert_eq!(
+            found,
+            Some(("/usr/bin".to_string(), "/bin/sh -c".to_string()))
+        );
+    }
+
+    #[test]
+    fn shell_path_falls_back_to_process_path() {
+        let found = resolve(Some("/usr/local/bin"), &[]);
+        assert_eq!(
+            found,
+            Some((
+                "/usr/local/bin".to_string(),
+                "the process environment".to_string()
+            ))
+        );
+        assert_eq!(resolve(Some(""), &[]), None);
+    }
+}
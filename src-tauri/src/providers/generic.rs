@@ -1,41 +1,57 @@
 use std::collections::HashMap;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
 
 use async_trait::async_trait;
 use tauri::AppHandle;
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio_util::sync::CancellationToken;
 
 use crate::error::{AppError, AppResult};
 use crate::models::{
-    ModeInfo, ModelInfo, PromptContent, ProviderDefinition, ProviderInfo, ProviderSettings,
-    ProviderType, StreamChunk,
+    AgentSessionInfo, ModeInfo, ModelInfo, PendingRequest, PromptContent, PromptShape,
+    ProviderDefinition, ProviderInfo, ProviderSettings, ProviderType, StreamChunk,
 };
 use crate::providers::acp_client_sdk::{
-    build_clean_env_with_custom, spawn_acp_connection, spawn_acp_resume_connection,
-    spawn_stderr_reader, AcpCommand,
+    build_clean_env_with_custom, build_prompt_blocks, spawn_acp_connection,
+    spawn_acp_resume_connection, spawn_stderr_reader, AcpCommand, AcpHandshakeResult,
+    AcpTimeouts, HANDSHAKE_CANCELLED,
 };
 use crate::providers::adapter::ProviderAdapter;
 use crate::providers::detector::ProviderDetector;
+use crate::utils::truncate_for_log;
+
+/// How long `terminate` waits for the ACP connection thread to exit
+const ACP_THREAD_JOIN_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 
 pub struct GenericAcpAdapter {
     provider_type: ProviderType,
     provider_name: String,
     command: String,
     args: Vec<String>,
+    prompt_shape: PromptShape,
     child: Option<tokio::process::Child>,
     cmd_tx: Option<mpsc::Sender<AcpCommand>>,
+    /// Dedicated thread running the ACP LocalSet, joined on terminate
+    acp_thread: Option<std::thread::JoinHandle<()>>,
     acp_session_id: Option<String>,
+    /// cwd the ACP session was created or loaded with
+    acp_cwd: Option<String>,
     session_id: Option<String>,
     current_message_id: Arc<Mutex<String>>,
     is_active: bool,
     env_vars: HashMap<String, String>,
+    /// Niceness applied to the spawned process, see `ProviderSettings::nice`
+    nice: Option<i32>,
     available_models: Vec<ModelInfo>,
     current_model_id: Option<String>,
     available_modes: Vec<ModeInfo>,
     current_mode_id: Option<String>,
     config_options: Vec<agent_client_protocol::SessionConfigOption>,
+    timeouts: AcpTimeouts,
+    /// Cancels an in-flight handshake and kills the spawned agent
+    handshake_cancel: CancellationToken,
 }
 
 impl GenericAcpAdapter {
@@ -80,21 +96,33 @@ impl GenericAcpAdapter {
             provider_name: def.name.clone(),
             command,
             args: def.args.clone(),
+            prompt_shape: def.prompt_shape,
             child: None,
             cmd_tx: None,
+            acp_thread: None,
             acp_session_id: None,
+            acp_cwd: None,
             session_id: None,
             current_message_id: Arc::new(Mutex::new(uuid::Uuid::new_v4().to_string())),
             is_active: false,
             env_vars,
+            nice: settings.and_then(|s| s.nice),
             available_models: vec![],
             current_model_id: None,
             available_modes: vec![],
             current_mode_id: None,
             config_options: vec![],
+            timeouts: AcpTimeouts::default(),
+            handshake_cancel: CancellationToken::new(),
         }
     }
 
+    /// Use these ACP request timeouts instead of the defaults
+    pub fn with_timeouts(mut self, timeouts: AcpTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
     /// Spawn the ACP process. Returns (child, stdin, stdout, stderr).
     fn spawn_process(
         &self,
@@ -118,22 +146,37 @@ impl GenericAcpAdapter {
         );
         println!("  Working directory: {}", worktree_path.display());
 
-        let mut child = tokio::process::Command::new(&resolved_cmd)
+        let mut command = tokio::process::Command::new(&resolved_cmd);
+        command
             .args(&self.args)
             .current_dir(worktree_path)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
-            .envs(&env)
-            .spawn()
-            .map_err(|e| {
-                AppError::Provider(format!(
-                    "Failed to spawn {} {:?}: {}",
-                    resolved_cmd.display(),
-                    self.args,
-                    e
-                ))
-            })?;
+            .envs(&env);
+
+        #[cfg(unix)]
+        if let Some(nice) = self.nice {
+            let nice = nice.clamp(-20, 19);
+            println!("  Niceness: {}", nice);
+            // Best effort: a negative niceness needs privileges, and failing to
+            // get it shouldn't keep the agent from starting
+            unsafe {
+                command.pre_exec(move || {
+                    libc::setpriority(libc::PRIO_PROCESS, 0, nice);
+                    Ok(())
+                });
+            }
+        }
+
+        let mut child = command.spawn().map_err(|e| {
+            AppError::Provider(format!(
+                "Failed to spawn {} {:?}: {}",
+                resolved_cmd.display(),
+                self.args,
+                e
+            ))
+        })?;
 
         let stdin = child
             .stdin
@@ -150,6 +193,27 @@ impl GenericAcpAdapter {
 
         Ok((child, stdin, stdout, stderr))
     }
+
+    /// Wait for the ACP handshake, killing the agent if it is cancelled first
+    async fn await_handshake(
+        &self,
+        child: &mut tokio::process::Child,
+        handshake_rx: oneshot::Receiver<Result<AcpHandshakeResult, String>>,
+    ) -> AppResult<AcpHandshakeResult> {
+        let result = tokio::select! {
+            biased;
+            _ = self.handshake_cancel.cancelled() => {
+                println!("[{}] Handshake cancelled, killing agent", self.provider_name);
+                let _ = child.kill().await;
+                return Err(AppError::Provider(HANDSHAKE_CANCELLED.to_string()));
+            }
+            result = handshake_rx => result,
+        };
+
+        result
+            .map_err(|_| AppError::Provider("Handshake channel closed".to_string()))?
+            .map_err(AppError::Provider)
+    }
 }
 
 #[async_trait]
@@ -178,7 +242,8 @@ impl ProviderAdapter for GenericAcpAdapter {
             self.provider_name, session_id
         );
 
-        let (child, stdin, stdout, stderr) = self.spawn_process(worktree_path)?;
+        let (mut child, stdin, stdout, stderr) = self.spawn_process(worktree_path)?;
+        let cwd = worktree_path.to_string_lossy().to_string();
 
         spawn_stderr_reader(
             stderr,
@@ -186,23 +251,23 @@ impl ProviderAdapter for GenericAcpAdapter {
             stream_tx.clone(),
             session_id.to_string(),
             self.current_message_id.clone(),
+            app_handle.clone(),
         );
 
-        let (cmd_tx, handshake_rx) = spawn_acp_connection(
+        let (cmd_tx, handshake_rx, acp_thread) = spawn_acp_connection(
             stdin,
             stdout,
             session_id.to_string(),
-            worktree_path.to_string_lossy().to_string(),
+            cwd.clone(),
             stream_tx,
             app_handle,
             self.current_message_id.clone(),
             mcp_servers,
+            self.timeouts,
+            self.handshake_cancel.clone(),
         );
 
-        let handshake = handshake_rx
-            .await
-            .map_err(|_| AppError::Provider("Handshake channel closed".to_string()))?
-            .map_err(|e| AppError::Provider(e))?;
+        let handshake = self.await_handshake(&mut child, handshake_rx).await?;
 
         println!(
             "[{}] ACP session established: {}",
@@ -211,11 +276,17 @@ impl ProviderAdapter for GenericAcpAdapter {
 
         self.child = Some(child);
         self.cmd_tx = Some(cmd_tx);
+        self.acp_thread = Some(acp_thread);
         self.acp_session_id = Some(handshake.session_id);
+        self.acp_cwd = Some(cwd);
         self.session_id = Some(session_id.to_string());
         println!(
-            "[{}] Handshake complete: available_models = {:?}, current_model = {:?}, available_modes = {:?}, current_mode = {:?}",
-            self.provider_name, handshake.models, handshake.current_model_id, handshake.modes, handshake.current_mode_id
+            "[{}] Handshake complete: {}",
+            self.provider_name,
+            truncate_for_log(&format!(
+                "available_models = {:?}, current_model = {:?}, available_modes = {:?}, current_mode = {:?}",
+                handshake.models, handshake.current_model_id, handshake.modes, handshake.current_mode_id
+            ))
         );
         self.available_models = handshake.models;
         self.current_model_id = handshake.current_model_id;
@@ -232,21 +303,21 @@ impl ProviderAdapter for GenericAcpAdapter {
         session_id: &str,
         acp_session_id: &str,
         worktree_path: &Path,
-        project_path: &Path,
+        acp_cwd: &str,
         stream_tx: mpsc::Sender<StreamChunk>,
         app_handle: AppHandle,
         mcp_servers: Vec<agent_client_protocol::McpServer>,
     ) -> AppResult<()> {
         println!(
-            "[{}] Resuming ACP session {} for {} (worktree: {}, project: {})",
+            "[{}] Resuming ACP session {} for {} (worktree: {}, cwd: {})",
             self.provider_name,
             acp_session_id,
             session_id,
             worktree_path.display(),
-            project_path.display()
+            acp_cwd
         );
 
-        let (child, stdin, stdout, stderr) = self.spawn_process(worktree_path)?;
+        let (mut child, stdin, stdout, stderr) = self.spawn_process(worktree_path)?;
 
         spawn_stderr_reader(
             stderr,
@@ -254,24 +325,24 @@ impl ProviderAdapter for GenericAcpAdapter {
             stream_tx.clone(),
             session_id.to_string(),
             self.current_message_id.clone(),
+            app_handle.clone(),
         );
 
-        let (cmd_tx, handshake_rx) = spawn_acp_resume_connection(
+        let (cmd_tx, handshake_rx, acp_thread) = spawn_acp_resume_connection(
             stdin,
             stdout,
             session_id.to_string(),
             acp_session_id.to_string(),
-            worktree_path.to_string_lossy().to_string(),
+            acp_cwd.to_string(),
             stream_tx,
             app_handle,
             self.current_message_id.clone(),
             mcp_servers,
+            self.timeouts,
+            self.handshake_cancel.clone(),
         );
 
-        let handshake = handshake_rx
-            .await
-            .map_err(|_| AppError::Provider("Handshake channel closed".to_string()))?
-            .map_err(|e| AppError::Provider(e))?;
+        let handshake = self.await_handshake(&mut child, handshake_rx).await?;
 
         println!(
             "[{}] ACP session resumed: {}",
@@ -280,11 +351,17 @@ impl ProviderAdapter for GenericAcpAdapter {
 
         self.child = Some(child);
         self.cmd_tx = Some(cmd_tx);
+        self.acp_thread = Some(acp_thread);
         self.acp_session_id = Some(handshake.session_id);
+        self.acp_cwd = Some(acp_cwd.to_string());
         self.session_id = Some(session_id.to_string());
         println!(
-            "[{}] Handshake complete: available_models = {:?}, current_model = {:?}, available_modes = {:?}, current_mode = {:?}",
-            self.provider_name, handshake.models, handshake.current_model_id, handshake.modes, handshake.current_mode_id
+            "[{}] Handshake complete: {}",
+            self.provider_name,
+            truncate_for_log(&format!(
+                "available_models = {:?}, current_model = {:?}, available_modes = {:?}, current_mode = {:?}",
+                handshake.models, handshake.current_model_id, handshake.modes, handshake.current_mode_id
+            ))
         );
         self.available_models = handshake.models;
         self.current_model_id = handshake.current_model_id;
@@ -296,10 +373,27 @@ impl ProviderAdapter for GenericAcpAdapter {
         Ok(())
     }
 
+    fn list_agent_sessions(&self, cwds: &[PathBuf]) -> AppResult<Vec<AgentSessionInfo>> {
+        match self.provider_type {
+            ProviderType::Claude => Ok(list_claude_sessions(cwds)),
+            _ => Ok(vec![]),
+        }
+    }
+
+    fn launch_fingerprint(&self) -> Option<String> {
+        let mut env: Vec<(&String, &String)> = self.env_vars.iter().collect();
+        env.sort();
+        Some(format!("{} {:?} {:?}", self.command, self.args, env))
+    }
+
     fn acp_session_id(&self) -> Option<&str> {
         self.acp_session_id.as_deref()
     }
 
+    fn acp_cwd(&self) -> Option<&str> {
+        self.acp_cwd.as_deref()
+    }
+
     fn available_models(&self) -> Vec<ModelInfo> {
         self.available_models.clone()
     }
@@ -320,7 +414,11 @@ impl ProviderAdapter for GenericAcpAdapter {
         self.config_options.clone()
     }
 
-    async fn send_message(&mut self, content: Vec<PromptContent>) -> AppResult<()> {
+    async fn send_message(
+        &mut self,
+        content: Vec<PromptContent>,
+        message_id: &str,
+    ) -> AppResult<()> {
         let cmd_tx = self
             .cmd_tx
             .as_ref()
@@ -363,34 +461,14 @@ impl ProviderAdapter for GenericAcpAdapter {
             }
         }
 
-        // Convert PromptContent to ACP ContentBlock
-        use agent_client_protocol::{ContentBlock, ImageContent as AcpImageContent, TextContent};
-        let content_blocks: Vec<ContentBlock> = content
-            .into_iter()
-            .map(|c| match c {
-                PromptContent::Text { text } => ContentBlock::Text(TextContent::new(text)),
-                PromptContent::Image(img) => {
-                    let mut image_content = AcpImageContent::new(img.data, img.mime_type);
-                    if let Some(uri) = img.uri {
-                        image_content = image_content.uri(uri);
-                    }
-                    ContentBlock::Image(image_content)
-                }
-                PromptContent::ResourceLink(rl) => {
-                    let mut resource_link =
-                        agent_client_protocol::ResourceLink::new(rl.name, rl.uri);
-                    if let Some(mime) = rl.mime_type {
-                        resource_link = resource_link.mime_type(mime);
-                    }
-                    ContentBlock::ResourceLink(resource_link)
-                }
-            })
-            .collect();
+        // Convert PromptContent to ACP ContentBlocks in the shape this agent expects
+        let content_blocks = build_prompt_blocks(content, self.prompt_shape);
 
         // Normal message: send as prompt
         let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
         let cmd = AcpCommand::Prompt {
             session_id: acp_session_id.clone(),
+            message_id: message_id.to_string(),
             content: content_blocks,
             reply: reply_tx,
         };
@@ -404,7 +482,7 @@ impl ProviderAdapter for GenericAcpAdapter {
             match reply_rx.await {
                 Ok(Ok(())) => {}
                 Ok(Err(e)) => {
-                    eprintln!("[{}] Prompt error: {}", provider_name, e);
+                    eprintln!("[{}] Prompt error: {}", provider_name, truncate_for_log(&e));
                 }
                 Err(_) => {
                     eprintln!("[{}] Prompt reply channel closed", provider_name);
@@ -471,7 +549,11 @@ impl ProviderAdapter for GenericAcpAdapter {
             .map_err(|e| AppError::Provider(e))
     }
 
-    async fn set_config_option(&mut self, config_id: &str, value: &str) -> AppResult<()> {
+    async fn set_config_option(
+        &mut self,
+        config_id: &str,
+        value: &str,
+    ) -> AppResult<Vec<agent_client_protocol::SessionConfigOption>> {
         let cmd_tx = self
             .cmd_tx
             .as_ref()
@@ -488,16 +570,33 @@ impl ProviderAdapter for GenericAcpAdapter {
             AppError::Provider(format!("Failed to send set_config_option command: {}", e))
         })?;
 
-        reply_rx
+        let config_options = reply_rx
             .await
             .map_err(|_| AppError::Provider("Set config option reply channel closed".to_string()))?
-            .map_err(|e| AppError::Provider(e))
+            .map_err(|e| AppError::Provider(e))?;
+        self.config_options = config_options.clone();
+        Ok(config_options)
     }
 
     fn is_active(&self) -> bool {
         self.is_active
     }
 
+    fn set_handshake_cancellation(&mut self, token: CancellationToken) {
+        self.handshake_cancel = token;
+    }
+
+    fn process_id(&self) -> Option<u32> {
+        self.child.as_ref().and_then(|child| child.id())
+    }
+
+    fn is_process_alive(&mut self) -> bool {
+        match self.child.as_mut() {
+            Some(child) => matches!(child.try_wait(), Ok(None)),
+            None => false,
+        }
+    }
+
     async fn cancel(&mut self) -> AppResult<()> {
         println!("[{}] cancel() called", self.provider_name);
         let cmd_tx = self
@@ -533,6 +632,95 @@ impl ProviderAdapter for GenericAcpAdapter {
         result
     }
 
+    async fn cancel_tool_call(&mut self, tool_call_id: &str) -> AppResult<()> {
+        let cmd_tx = self
+            .cmd_tx
+            .as_ref()
+            .ok_or_else(|| AppError::Provider("Session not started".to_string()))?;
+
+        let acp_session_id = self
+            .acp_session_id
+            .as_ref()
+            .ok_or_else(|| AppError::Provider("ACP session not established".to_string()))?;
+
+        println!(
+            "[{}] Sending CancelToolCall command for {} in session {}",
+            self.provider_name, tool_call_id, acp_session_id
+        );
+        let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+        let cmd = AcpCommand::CancelToolCall {
+            session_id: acp_session_id.clone(),
+            tool_call_id: tool_call_id.to_string(),
+            reply: reply_tx,
+        };
+
+        cmd_tx.send(cmd).await.map_err(|e| {
+            AppError::Provider(format!("Failed to send cancel tool call command: {}", e))
+        })?;
+
+        reply_rx
+            .await
+            .map_err(|_| AppError::Provider("Cancel tool call reply channel closed".to_string()))?
+            .map_err(AppError::Provider)
+    }
+
+    async fn clear_pending_permission(&mut self) -> AppResult<bool> {
+        let cmd_tx = self
+            .cmd_tx
+            .as_ref()
+            .ok_or_else(|| AppError::Provider("Session not started".to_string()))?;
+
+        let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+        cmd_tx
+            .send(AcpCommand::ClearPermission { reply: reply_tx })
+            .await
+            .map_err(|e| {
+                AppError::Provider(format!("Failed to send clear permission command: {}", e))
+            })?;
+
+        reply_rx
+            .await
+            .map_err(|_| AppError::Provider("Clear permission reply channel closed".to_string()))
+    }
+
+    async fn pending_requests(&self) -> AppResult<Vec<PendingRequest>> {
+        let cmd_tx = self
+            .cmd_tx
+            .as_ref()
+            .ok_or_else(|| AppError::Provider("Session not started".to_string()))?;
+
+        let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+        cmd_tx
+            .send(AcpCommand::PendingRequests { reply: reply_tx })
+            .await
+            .map_err(|e| {
+                AppError::Provider(format!("Failed to send pending requests command: {}", e))
+            })?;
+
+        reply_rx
+            .await
+            .map_err(|_| AppError::Provider("Pending requests reply channel closed".to_string()))
+    }
+
+    async fn clear_pending_requests(&mut self) -> AppResult<usize> {
+        let cmd_tx = self
+            .cmd_tx
+            .as_ref()
+            .ok_or_else(|| AppError::Provider("Session not started".to_string()))?;
+
+        let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+        cmd_tx
+            .send(AcpCommand::ClearPendingRequests { reply: reply_tx })
+            .await
+            .map_err(|e| {
+                AppError::Provider(format!("Failed to send clear requests command: {}", e))
+            })?;
+
+        reply_rx
+            .await
+            .map_err(|_| AppError::Provider("Clear requests reply channel closed".to_string()))
+    }
+
     async fn terminate(&mut self) -> AppResult<()> {
         println!("[{}] Terminating session", self.provider_name);
 
@@ -544,8 +732,30 @@ impl ProviderAdapter for GenericAcpAdapter {
             let _ = child.kill().await;
         }
 
+        // Join the ACP thread so terminated sessions don't leak OS threads.
+        // Killing the child closes its pipes, so the LocalSet should unwind quickly.
+        if let Some(thread) = self.acp_thread.take() {
+            let join = tokio::task::spawn_blocking(move || thread.join());
+            match tokio::time::timeout(ACP_THREAD_JOIN_TIMEOUT, join).await {
+                Ok(Ok(Ok(()))) => {}
+                Ok(Ok(Err(_))) => {
+                    eprintln!("[{}] ACP thread panicked during shutdown", self.provider_name);
+                }
+                Ok(Err(e)) => {
+                    eprintln!("[{}] Failed to join ACP thread: {}", self.provider_name, e);
+                }
+                Err(_) => {
+                    eprintln!(
+                        "[{}] ACP thread did not exit within {:?}, detaching",
+                        self.provider_name, ACP_THREAD_JOIN_TIMEOUT
+                    );
+                }
+            }
+        }
+
         self.is_active = false;
         self.acp_session_id = None;
+        self.acp_cwd = None;
         self.session_id = None;
         self.available_models.clear();
         self.current_model_id = None;
@@ -553,3 +763,147 @@ impl ProviderAdapter for GenericAcpAdapter {
         Ok(())
     }
 }
+
+/// Claude Code keeps one `<session-id>.jsonl` per session under
+/// `~/.claude/projects/<cwd with every non-alphanumeric character replaced by '-'>`
+fn list_claude_sessions(cwds: &[PathBuf]) -> Vec<AgentSessionInfo> {
+    let Some(projects_dir) = dirs::home_dir().map(|h| h.join(".claude").join("projects")) else {
+        return vec![];
+    };
+    let Ok(entries) = std::fs::read_dir(&projects_dir) else {
+        return vec![];
+    };
+    let encoded: Vec<String> = cwds.iter().map(|c| encode_claude_project_dir(c)).collect();
+
+    let mut sessions = Vec::new();
+    for entry in entries.flatten() {
+        let dir_name = entry.file_name().to_string_lossy().to_string();
+        if !encoded
+            .iter()
+            .any(|e| dir_name == *e || dir_name.starts_with(&format!("{}-", e)))
+        {
+            continue;
+        }
+        let Ok(files) = std::fs::read_dir(entry.path()) else {
+            continue;
+        };
+        for file in files.flatten() {
+            let path = file.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("jsonl") {
+                continue;
+            }
+            let Some(acp_session_id) = path.file_stem().map(|s| s.to_string_lossy().to_string())
+            else {
+                continue;
+            };
+            // The directory name is lossy, so check the recorded cwd when there is one
+            let cwd = read_claude_session_cwd(&path);
+            if let Some(ref cwd) = cwd {
+                if !cwds.iter().any(|c| Path::new(cwd).starts_with(c)) {
+                    continue;
+                }
+            }
+            let updated_at = file
+                .metadata()
+                .and_then(|m| m.modified())
+                .ok()
+                .map(chrono::DateTime::<chrono::Utc>::from);
+            sessions.push(AgentSessionInfo {
+                provider: ProviderType::Claude,
+                acp_session_id,
+                cwd,
+                updated_at,
+            });
+        }
+    }
+    sessions
+}
+
+fn encode_claude_project_dir(path: &Path) -> String {
+    path.to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// The `cwd` recorded in the first entries of a Claude session file
+fn read_claude_session_cwd(path: &Path) -> Option<String> {
+    use std::io::BufRead;
+
+    let file = std::fs::File::open(path).ok()?;
+    std::io::BufReader::new(file)
+        .lines()
+        .take(20)
+        .map_while(Result::ok)
+        .find_map(|line| {
+            serde_json::from_str::<serde_json::Value>(&line)
+                .ok()?
+                .get("cwd")?
+                .as_str()
+                .map(str::to_string)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::providers::acp_client_sdk::{live_connection_threads, spawn_connection_thread};
+
+    /// Adapter whose ACP connection thread runs until it is told to shut down
+    fn connected_adapter() -> GenericAcpAdapter {
+        let def = crate::models::builtin_definitions().remove(0);
+        let mut adapter = GenericAcpAdapter::new(&def, None);
+        let (cmd_tx, mut cmd_rx) = mpsc::channel::<AcpCommand>(1);
+        adapter.acp_thread = Some(spawn_connection_thread(move || async move {
+            while let Some(cmd) = cmd_rx.recv().await {
+                if matches!(cmd, AcpCommand::Shutdown) {
+                    break;
+                }
+            }
+        }));
+        adapter.cmd_tx = Some(cmd_tx);
+        adapter.is_active = true;
+        adapter
+    }
+
+    #[tokio::test]
+    async fn terminating_sessions_joins_their_connection_threads() {
+        let baseline = live_connection_threads();
+        let mut adapters: Vec<_> = (0..20).map(|_| connected_adapter()).collect();
+        assert_eq!(live_connection_threads(), baseline + 20);
+
+        for adapter in &mut adapters {
+            adapter.terminate().await.unwrap();
+            assert!(!adapter.is_active());
+        }
+        assert_eq!(live_connection_threads(), baseline);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn cancelling_the_handshake_kills_the_agent_promptly() {
+        let def = crate::models::builtin_definitions().remove(0);
+        let mut adapter = GenericAcpAdapter::new(&def, None);
+        let cancel = CancellationToken::new();
+        adapter.set_handshake_cancellation(cancel.clone());
+
+        // An agent that never answers `initialize`
+        let mut child = tokio::process::Command::new("sleep")
+            .arg("30")
+            .spawn()
+            .unwrap();
+        let (_handshake_tx, handshake_rx) = oneshot::channel();
+
+        let started = std::time::Instant::now();
+        let canceller = tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            cancel.cancel();
+        });
+        let result = adapter.await_handshake(&mut child, handshake_rx).await;
+        canceller.await.unwrap();
+
+        assert!(matches!(result, Err(AppError::Provider(e)) if e == HANDSHAKE_CANCELLED));
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+        assert!(child.try_wait().unwrap().is_some());
+    }
+}
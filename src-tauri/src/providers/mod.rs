@@ -2,6 +2,7 @@ pub mod acp_client_sdk;
 pub mod adapter;
 pub mod detector;
 pub mod generic;
+pub mod models_catalog;
 
 pub use adapter::ProviderAdapter;
 pub use detector::ProviderDetector;
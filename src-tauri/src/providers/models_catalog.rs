@@ -0,0 +1,112 @@
+use crate::models::ModelInfo;
+
+/// Known models as (model_id, display_name, description), grouped by provider.
+/// Used when an agent reports a model by id without a friendly name.
+/// Ids must be unique across providers.
+const CATALOG: &[(&str, &str, &str)] = &[
+    // Claude
+    ("default", "Default", "Recommended model for your account"),
+    ("opus", "Opus", "Most capable Claude model for complex work"),
+    ("sonnet", "Sonnet", "Balanced Claude model for everyday tasks"),
+    ("haiku", "Haiku", "Fastest Claude model for quick answers"),
+    ("claude-opus-4-20250514", "Claude Opus 4", "Most capable Claude model for complex work"),
+    ("claude-sonnet-4-20250514", "Claude Sonnet 4", "Balanced Claude model for everyday tasks"),
+    ("claude-haiku-4-20250514", "Claude Haiku 4", "Fastest Claude model for quick answers"),
+    // Codex
+    ("gpt-5-codex", "GPT-5 Codex", "GPT-5 tuned for agentic coding"),
+    ("gpt-5", "GPT-5", "General-purpose reasoning model"),
+    // Gemini
+    ("gemini-2.5-pro", "Gemini 2.5 Pro", "Most capable Gemini model for complex work"),
+    ("gemini-2.5-flash", "Gemini 2.5 Flash", "Fast Gemini model for everyday tasks"),
+    // Kimi
+    ("moonshot-v1-128k", "Moonshot v1 128K", "Long-context Moonshot model"),
+    ("kimi-k2", "Kimi K2", "Kimi's agentic coding model"),
+    // Qwen Code
+    ("qwen3-coder-plus", "Qwen3 Coder Plus", "Qwen's agentic coding model"),
+];
+
+/// Look up the display name and description of a known model id
+pub fn lookup(model_id: &str) -> Option<(&'static str, &'static str)> {
+    CATALOG
+        .iter()
+        .find(|(id, _, _)| *id == model_id)
+        .map(|(_, name, description)| (*name, *description))
+}
+
+/// Fill in the display name and description of a model the agent reported by bare id.
+/// Names and descriptions the agent did supply are left untouched.
+pub fn apply_friendly_name(model: &mut ModelInfo) {
+    let has_name = !model.display_name.trim().is_empty() && model.display_name != model.model_id;
+    if has_name && model.description.is_some() {
+        return;
+    }
+    let Some((display_name, description)) = lookup(&model.model_id) else {
+        return;
+    };
+    if !has_name {
+        model.display_name = display_name.to_string();
+    }
+    if model.description.is_none() {
+        model.description = Some(description.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(model_id: &str, display_name: &str, description: Option<&str>) -> ModelInfo {
+        ModelInfo {
+            model_id: model_id.to_string(),
+            display_name: display_name.to_string(),
+            description: description.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn bare_ids_get_catalog_names() {
+        let mut m = model("sonnet", "sonnet", None);
+        apply_friendly_name(&mut m);
+        assert_eq!(m.display_name, "Sonnet");
+        assert_eq!(
+            m.description.as_deref(),
+            Some("Balanced Claude model for everyday tasks")
+        );
+
+        let mut m = model("gpt-5", " ", None);
+        apply_friendly_name(&mut m);
+        assert_eq!(m.display_name, "GPT-5");
+    }
+
+    #[test]
+    fn agent_supplied_names_are_kept() {
+        let mut m = model("sonnet", "Sonnet (1M)", None);
+        apply_friendly_name(&mut m);
+        assert_eq!(m.display_name, "Sonnet (1M)");
+        assert_eq!(
+            m.description.as_deref(),
+            Some("Balanced Claude model for everyday tasks")
+        );
+
+        let mut m = model("sonnet", "sonnet", Some("From the agent"));
+        apply_friendly_name(&mut m);
+        assert_eq!(m.display_name, "Sonnet");
+        assert_eq!(m.description.as_deref(), Some("From the agent"));
+    }
+
+    #[test]
+    fn unknown_ids_are_left_alone() {
+        let mut m = model("my-local-model", "my-local-model", None);
+        apply_friendly_name(&mut m);
+        assert_eq!(m.display_name, "my-local-model");
+        assert_eq!(m.description, None);
+    }
+
+    #[test]
+    fn catalog_ids_are_unique() {
+        let mut ids: Vec<_> = CATALOG.iter().map(|(id, _, _)| *id).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), CATALOG.len());
+    }
+}
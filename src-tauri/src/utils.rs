@@ -0,0 +1,187 @@
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Default maximum number of bytes of a single value written to the logs
+pub const DEFAULT_MAX_LOG_LINE_LENGTH: usize = 2000;
+
+/// Current log cap, updated from `AdvancedSettings` whenever settings change
+static MAX_LOG_LINE_LENGTH: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_LOG_LINE_LENGTH);
+
+/// Truncate `s` to at most `max_bytes` bytes without splitting a UTF-8 character.
+pub fn truncate_str(s: &str, max_bytes: usize) -> &str {
+    if s.len() <= max_bytes {
+        return s;
+    }
+    let mut end = max_bytes;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// Match `text` against a glob `pattern` where `*` matches any run of
+/// characters and `?` matches exactly one
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text index it is currently matched up to
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star, matched)) = backtrack {
+            p = star + 1;
+            t = matched + 1;
+            backtrack = Some((star, matched + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Whether `dest` is `source` or lies inside it once symlinks are resolved.
+/// `dest` may not exist yet; its nearest existing ancestor is resolved instead,
+/// so a symlinked destination folder that points back into `source` is caught.
+pub fn is_within_dir(dest: &Path, source: &Path) -> bool {
+    let source = source
+        .canonicalize()
+        .unwrap_or_else(|_| source.to_path_buf());
+    dest.starts_with(&source) || resolve_existing_prefix(dest).starts_with(&source)
+}
+
+/// Canonicalize the longest existing prefix of `path` and re-append the rest
+fn resolve_existing_prefix(path: &Path) -> PathBuf {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(resolved) = existing.canonicalize() {
+            return rest.iter().rev().fold(resolved, |acc, name| acc.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+/// Image types agent images may be stored as, with their file extensions
+const IMAGE_MIME_EXTENSIONS: [(&str, &str); 4] = [
+    ("image/png", "png"),
+    ("image/jpeg", "jpg"),
+    ("image/gif", "gif"),
+    ("image/webp", "webp"),
+];
+
+/// File extension for a supported image MIME type
+pub fn image_extension_for_mime(mime_type: &str) -> Option<&'static str> {
+    let mime_type = mime_type.trim().to_ascii_lowercase();
+    IMAGE_MIME_EXTENSIONS
+        .iter()
+        .find(|(mime, _)| *mime == mime_type)
+        .map(|(_, ext)| *ext)
+}
+
+/// MIME type for a supported image file extension
+pub fn image_mime_for_extension(extension: &str) -> Option<&'static str> {
+    IMAGE_MIME_EXTENSIONS
+        .iter()
+        .find(|(_, ext)| ext.eq_ignore_ascii_case(extension))
+        .map(|(mime, _)| *mime)
+}
+
+/// Set the maximum logged line length. `0` disables truncation.
+pub fn set_max_log_line_length(len: usize) {
+    MAX_LOG_LINE_LENGTH.store(len, Ordering::Relaxed);
+}
+
+/// Cap a string for logging. Only affects what is printed, never what is
+/// forwarded to the frontend or persisted.
+pub fn truncate_for_log(s: &str) -> Cow<'_, str> {
+    let max = MAX_LOG_LINE_LENGTH.load(Ordering::Relaxed);
+    if max == 0 || s.len() <= max {
+        return Cow::Borrowed(s);
+    }
+    let kept = truncate_str(s, max);
+    Cow::Owned(format!(
+        "{}... [{} bytes truncated]",
+        kept,
+        s.len() - kept.len()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_str_keeps_char_boundaries() {
+        assert_eq!(truncate_str("hello", 10), "hello");
+        assert_eq!(truncate_str("hello", 3), "hel");
+        // "é" is two bytes; cutting inside it backs off to the previous boundary
+        assert_eq!(truncate_str("héllo", 2), "h");
+        assert_eq!(truncate_str("héllo", 3), "hé");
+        assert_eq!(truncate_str("", 0), "");
+    }
+
+    #[test]
+    fn truncate_for_log_respects_the_cap() {
+        set_max_log_line_length(4);
+        assert_eq!(truncate_for_log("abcd"), "abcd");
+        assert_eq!(truncate_for_log("abcdef"), "abcd... [2 bytes truncated]");
+        set_max_log_line_length(0);
+        assert!(matches!(
+            truncate_for_log("abcdef"),
+            Cow::Borrowed("abcdef")
+        ));
+        set_max_log_line_length(DEFAULT_MAX_LOG_LINE_LENGTH);
+    }
+
+    #[test]
+    fn image_mime_types_round_trip() {
+        assert_eq!(image_extension_for_mime(" Image/PNG "), Some("png"));
+        assert_eq!(image_mime_for_extension("JPG"), Some("image/jpeg"));
+        assert_eq!(image_extension_for_mime("image/svg+xml"), None);
+    }
+
+    #[test]
+    fn glob_match_handles_wildcards() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*.rs", "main.rs"));
+        assert!(!glob_match("*.rs", "main.rs.bak"));
+        assert!(glob_match("a?c", "abc"));
+        assert!(!glob_match("a?c", "ac"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(glob_match("a*c", "abcbc"));
+        assert!(!glob_match("a*d", "abcbc"));
+        assert!(glob_match("**", "anything"));
+        assert!(!glob_match("abc", "abcd"));
+        assert!(glob_match("é*", "éa"));
+    }
+
+    #[test]
+    fn is_within_dir_resolves_symlinks() {
+        let source = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        assert!(is_within_dir(source.path(), source.path()));
+        assert!(is_within_dir(&source.path().join("new/dir"), source.path()));
+        assert!(!is_within_dir(&outside.path().join("dir"), source.path()));
+
+        #[cfg(unix)]
+        {
+            let link = outside.path().join("link");
+            std::os::unix::fs::symlink(source.path(), &link).unwrap();
+            assert!(is_within_dir(&link.join("not-yet"), source.path()));
+        }
+    }
+}
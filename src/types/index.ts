@@ -24,6 +24,7 @@ export interface ProviderSettings {
   enabled: boolean;
   custom_cli_path: string | null;
   env_vars?: Record<string, string>;
+  nice?: number;
 }
 
 // Provider definition describes how to launch an ACP provider
@@ -35,8 +36,14 @@ export interface ProviderDefinition {
   cli_command?: string;
   env?: Record<string, string>;
   builtin: boolean;
+  prompt_shape?: PromptShape;
+  auth_status_args?: string[] | null;
 }
 
+export type PromptShape = "standard" | "single_text";
+
+export type AuthStatus = "logged_in" | "logged_out" | "unknown";
+
 // Default settings factory
 export function createDefaultProviderSettings(): ProviderSettings {
   return {
@@ -80,6 +87,7 @@ export interface Session {
   project_path: string;
   is_local: boolean;
   acp_session_id?: string | null;
+  acp_cwd?: string | null;
   model?: string | null;
   available_models: ModelInfo[];
   mode?: string | null;
@@ -87,6 +95,9 @@ export interface Session {
   available_commands: AvailableCommand[];
   config_options: ConfigOption[];
   error?: SessionError | null;
+  pinned?: boolean;
+  system_prompt?: string | null;
+  ui_color?: string | null;
 }
 
 export interface CreateSessionRequest {
@@ -97,11 +108,22 @@ export interface CreateSessionRequest {
   use_local?: boolean;
   fetch_first?: boolean;
   excluded_mcp_ids?: string[];
+  system_prompt?: string;
 }
 
+export type ProviderErrorKind =
+  | "auth_required"
+  | "rate_limited"
+  | "invalid_params"
+  | "method_not_found"
+  | "resource_not_found"
+  | "internal_error"
+  | "other";
+
 export interface SessionError {
   code: string;
   message: string;
+  kind?: ProviderErrorKind;
 }
 
 export interface SessionStatusEvent {
@@ -113,7 +135,7 @@ export interface SessionStatusEvent {
 
 // Message types
 export type MessageRole = "user" | "assistant" | "system";
-export type MessageContentType = "text" | "tool_use" | "tool_result" | "error";
+export type MessageContentType = "text" | "tool_use" | "tool_result" | "error" | "image";
 
 export interface ToolUseInfo {
   tool_name: string;
@@ -216,6 +238,8 @@ export interface StreamChunk {
   chunk_type?: StreamChunkType;
   tool_call?: ToolCallInfo;
   image_content?: ImageContent;
+  /** Why the turn failed, set on the final chunk of a failed prompt */
+  error?: string;
 }
 
 export interface PermissionOptionInfo {
@@ -265,6 +289,13 @@ export interface PlanUpdateEvent {
   entries: PlanEntry[];
 }
 
+// Emitted after a message is saved; message is null when too large to send
+export interface MessageSavedEvent {
+  session_id: string;
+  message_id: string;
+  message: ChatMessage | null;
+}
+
 // MCP types
 export type McpServerSource =
   | { type: "user" }